@@ -0,0 +1,29 @@
+use anyhow::anyhow;
+use frodobuf::render::{OutputLanguage, RenderConfig, Renderer};
+use midl_parser::parse_string;
+
+// the schema of the golden-file tests, which uses every feature of the generator
+const INPUT_FILE: &str = "../frodobuf/testdata/store.midl";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = std::path::PathBuf::from(&std::env::var("OUT_DIR").unwrap());
+    println!("cargo:rerun-if-changed={}", INPUT_FILE);
+
+    let idl_text = std::fs::read_to_string(INPUT_FILE)
+        .map_err(|e| anyhow!("reading input file '{}': {}", INPUT_FILE, e))?;
+    let descriptor = parse_string(&idl_text).map_err(|e| anyhow!("problem with the idl: {}", e))?;
+    let schema = descriptor.schema;
+
+    let mut renderer = Renderer::init(&RenderConfig {
+        language: OutputLanguage::Rust,
+        ..Default::default()
+    })?;
+    renderer.set("schema", serde_json::to_value(&schema)?);
+    renderer.codegen_for_schema(&schema, &out_dir)?;
+
+    // json schemas of the messages, to check the bodies the http gateway accepts
+    for (name, doc) in frodobuf::codegen::json_schema::export_json_schema(&schema) {
+        std::fs::write(out_dir.join(name), serde_json::to_string_pretty(&doc)?)?;
+    }
+    Ok(())
+}
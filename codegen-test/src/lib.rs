@@ -0,0 +1,7 @@
+//! Rust code generated from `frodobuf/testdata/store.midl`.
+//! The tests in `tests/` build it and call the generated server through the generated client.
+#![allow(unused_imports)]
+#![allow(clippy::ptr_arg)]
+pub mod store {
+    include!(concat!(env!("OUT_DIR"), "/store.rs"));
+}
@@ -0,0 +1,24 @@
+//! Batched calls through the generated server and client for store.midl
+mod common;
+
+use common::*;
+use frodobuf::context::Context;
+use frodobuf_codegen_test::store::*;
+use futures::executor::block_on;
+
+#[test]
+fn batch() {
+    let (client, ctx) = (client(), Context::default());
+    let mut batch = client.batch();
+    let found = batch.get(&"b1".to_string()).unwrap();
+    let missing = batch.get(&"nope".to_string()).unwrap();
+    let sum = batch.checksum(&vec![1, 2, 3]).unwrap();
+    assert_eq!(batch.len(), 3);
+    let results = block_on(batch.send(&ctx)).unwrap();
+    assert_eq!(results.get(&found).unwrap().count, 3);
+    assert!(matches!(
+        results.get(&missing),
+        Err(StoreGetError::NotFound(_))
+    ));
+    assert_eq!(results.get(&sum).unwrap(), 6);
+}
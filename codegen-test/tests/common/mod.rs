@@ -0,0 +1,127 @@
+//! Store kept in memory, implementing the generated server of store.midl,
+//! and a client of it over a loopback transport
+#![allow(dead_code)]
+use async_trait::async_trait;
+use frodobuf::{
+    client::ClientConfig, context::Context, mock::LoopbackTransport, stream, Message,
+    MessageDispatch, RpcError,
+};
+use frodobuf_codegen_test::store::*;
+use std::{collections::HashMap, sync::Mutex};
+
+/// Store kept in memory
+#[derive(Default)]
+pub struct MemStore {
+    pub items: Mutex<HashMap<String, Item>>,
+    pub touched: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl Store for MemStore {
+    async fn get(&self, _ctx: &Context<'_>, key: &String) -> Result<Item, StoreGetError> {
+        if key.starts_with('_') {
+            return Err(StoreGetError::Denied(Denied {
+                reason: "private".into(),
+            }));
+        }
+        let items = self.items.lock().unwrap();
+        items
+            .get(key)
+            .cloned()
+            .ok_or_else(|| StoreGetError::NotFound(NotFound { key: key.clone() }))
+    }
+
+    async fn list(&self, _ctx: &Context<'_>) -> Result<Page, RpcError> {
+        let mut items: Vec<Item> = self.items.lock().unwrap().values().cloned().collect();
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(Page {
+            items,
+            blobs: Vec::new(),
+        })
+    }
+
+    async fn put(&self, _ctx: &Context<'_>, item: &Item) -> Result<(), RpcError> {
+        let mut items = self.items.lock().unwrap();
+        items.insert(item.key.clone(), item.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, _ctx: &Context<'_>, key: &String) -> Result<(), StoreDeleteError> {
+        match self.items.lock().unwrap().remove(key) {
+            Some(_) => Ok(()),
+            None => Err(StoreDeleteError::NotFound(NotFound { key: key.clone() })),
+        }
+    }
+
+    async fn checksum(&self, _ctx: &Context<'_>, data: &Vec<u8>) -> Result<u32, RpcError> {
+        Ok(data.iter().map(|b| *b as u32).sum())
+    }
+
+    async fn touch(&self, _ctx: &Context<'_>, key: &String) -> Result<(), RpcError> {
+        self.touched.lock().unwrap().push(key.clone());
+        Ok(())
+    }
+
+    async fn convert(&self, _ctx: &Context<'_>, value: &Types) -> Result<Vec<Types>, RpcError> {
+        Ok(vec![value.clone(), value.clone()])
+    }
+
+    async fn watch(
+        &self,
+        _ctx: &Context<'_>,
+        prefix: &String,
+    ) -> Result<stream::ItemStream<Item>, RpcError> {
+        let mut items: Vec<Item> = self
+            .items
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|item| item.key.starts_with(prefix.as_str()))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(stream::ItemStream::from_items(items))
+    }
+}
+
+impl StoreServer for MemStore {}
+
+#[async_trait]
+impl MessageDispatch for MemStore {
+    async fn dispatch(
+        &self,
+        ctx: &Context<'_>,
+        message: Message<'_>,
+    ) -> Result<Message<'static>, RpcError> {
+        if message.method == frodobuf::batch::BATCH_METHOD {
+            return frodobuf::batch::dispatch_batch(self, ctx, message.arg.as_ref()).await;
+        }
+        StoreServer::dispatch(self, ctx, &message).await
+    }
+}
+
+pub fn item(key: &str, count: u32) -> Item {
+    Item {
+        key: key.to_string(),
+        count,
+        updated: 0,
+        price: 1.5,
+        active: true,
+        data: vec![1, 2, 3],
+        tags: vec!["t".to_string()],
+        sizes: HashMap::new(),
+        note: None,
+    }
+}
+
+pub fn client() -> StoreClient<LoopbackTransport<MemStore>> {
+    let store = MemStore::default();
+    for (key, count) in [("a1", 1), ("a2", 2), ("b1", 3)].iter() {
+        store
+            .items
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), item(key, *count));
+    }
+    StoreClient::new(ClientConfig::target("store"), LoopbackTransport::new(store))
+}
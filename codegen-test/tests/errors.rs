@@ -0,0 +1,25 @@
+//! Declared errors through the generated server and client for store.midl
+mod common;
+
+use common::*;
+use frodobuf::context::Context;
+use frodobuf_codegen_test::store::*;
+use futures::executor::block_on;
+
+#[test]
+fn typed_errors() {
+    let (client, ctx) = (client(), Context::default());
+    match block_on(client.get(&ctx, &"zz".to_string())) {
+        Err(StoreGetError::NotFound(e)) => assert_eq!(e.key, "zz"),
+        res => panic!("expected NotFound, got {:?}", res),
+    }
+    match block_on(client.get(&ctx, &"_x".to_string())) {
+        Err(StoreGetError::Denied(e)) => assert_eq!(e.reason, "private"),
+        res => panic!("expected Denied, got {:?}", res),
+    }
+    block_on(client.delete(&ctx, &"a1".to_string())).unwrap();
+    assert!(matches!(
+        block_on(client.delete(&ctx, &"a1".to_string())),
+        Err(StoreDeleteError::NotFound(_))
+    ));
+}
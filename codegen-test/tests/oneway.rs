@@ -0,0 +1,17 @@
+//! Oneway calls through the generated server and client for store.midl
+mod common;
+
+use common::*;
+use frodobuf::{client::ClientConfig, context::Context, mock::LoopbackTransport};
+use frodobuf_codegen_test::store::*;
+use futures::executor::block_on;
+
+#[test]
+fn oneway() {
+    // clones of the transport share the store
+    let transport = LoopbackTransport::new(MemStore::default());
+    let client = StoreClient::new(ClientConfig::target("store"), transport.clone());
+    block_on(client.touch(&Context::default(), &"a1".to_string())).unwrap();
+    let touched = transport.handler().touched.lock().unwrap().clone();
+    assert_eq!(touched, vec!["a1".to_string()]);
+}
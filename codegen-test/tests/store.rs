@@ -0,0 +1,110 @@
+//! Round trips through the generated server and client for store.midl
+mod common;
+
+use common::*;
+use frodobuf::context::Context;
+use frodobuf_codegen_test::store::*;
+use futures::executor::block_on;
+use std::{collections::HashMap, sync::Arc};
+
+#[test]
+fn unary() {
+    let (client, ctx) = (client(), Context::default());
+    let got = block_on(client.get(&ctx, &"a2".to_string())).unwrap();
+    assert_eq!(
+        (got.key.as_str(), got.count, got.data),
+        ("a2", 2, vec![1, 2, 3])
+    );
+
+    block_on(client.put(&ctx, &item("c1", 9))).unwrap();
+    let page = block_on(client.list(&ctx)).unwrap();
+    let keys: Vec<&str> = page.items.iter().map(|i| i.key.as_str()).collect();
+    assert_eq!(keys, vec!["a1", "a2", "b1", "c1"]);
+}
+
+#[test]
+fn http_routes() {
+    // the routes call the server with the service prefix
+    let store = MemStore::default();
+    store
+        .items
+        .lock()
+        .unwrap()
+        .insert("a1".into(), item("a1", 1));
+    let gateway = frodobuf::http::Gateway::new(Arc::new(store)).with_routes(store_http_routes());
+    let resp = block_on(gateway.handle("POST", "/store/Store/checksum", b"[1, 2, 3]"));
+    assert_eq!((resp.status, resp.body), (200, b"6".to_vec()));
+    let resp = block_on(gateway.handle("POST", "/store/Store/get", b"\"zz\""));
+    assert_eq!(resp.status, 422);
+}
+
+#[test]
+fn schema_valid_body() {
+    let schema: serde_json::Value =
+        serde_json::from_str(include_str!(concat!(env!("OUT_DIR"), "/Item.schema.json"))).unwrap();
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|name| name.as_str().unwrap())
+        .collect();
+    assert!(!required.contains(&"note"));
+
+    // a body with only the required properties is accepted, and the optional field is None
+    let mut body = serde_json::to_value(item("c1", 4)).unwrap();
+    body.as_object_mut()
+        .unwrap()
+        .retain(|name, _| required.contains(&name.as_str()));
+    let store = Arc::new(MemStore::default());
+    let gateway = frodobuf::http::Gateway::new(store.clone()).with_routes(store_http_routes());
+    let resp = block_on(gateway.handle(
+        "POST",
+        "/store/Store/put",
+        &serde_json::to_vec(&body).unwrap(),
+    ));
+    assert_eq!(resp.status, 200, "{}", String::from_utf8_lossy(&resp.body));
+    assert_eq!(store.items.lock().unwrap()["c1"].note, None);
+
+    body["note"] = "fragile".into();
+    let resp = block_on(gateway.handle(
+        "POST",
+        "/store/Store/put",
+        &serde_json::to_vec(&body).unwrap(),
+    ));
+    assert_eq!(resp.status, 200);
+    assert_eq!(
+        store.items.lock().unwrap()["c1"].note.as_deref(),
+        Some("fragile")
+    );
+}
+
+#[test]
+fn datetime_field() {
+    // datetime is an RFC-3339 string in rust, json and json schema
+    let value = Types {
+        tiny: 1,
+        count: 2,
+        total: 3,
+        flags: 4,
+        size: 5,
+        offset: 6,
+        enabled: true,
+        label: "label".into(),
+        raw: vec![7],
+        ratio: 0.5,
+        score: 0.25,
+        created: "2021-06-01T12:30:00Z".into(),
+        attrs: HashMap::new(),
+        ids: vec![8],
+        item: item("a", 1),
+    };
+    let got = block_on(client().convert(&Context::default(), &value)).unwrap();
+    assert_eq!(got.len(), 2);
+    assert_eq!(got[0].created, "2021-06-01T12:30:00Z");
+    let json = serde_json::to_value(&value).unwrap();
+    assert_eq!(json["created"], "2021-06-01T12:30:00Z");
+
+    let schema: serde_json::Value =
+        serde_json::from_str(include_str!(concat!(env!("OUT_DIR"), "/Types.schema.json"))).unwrap();
+    assert_eq!(schema["properties"]["created"]["format"], "date-time");
+}
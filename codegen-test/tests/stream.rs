@@ -0,0 +1,17 @@
+//! Streaming calls through the generated server and client for store.midl
+mod common;
+
+use common::*;
+use frodobuf::{context::Context, stream};
+use frodobuf_codegen_test::store::*;
+use futures::executor::block_on;
+
+#[test]
+fn server_stream() {
+    let (client, ctx) = (client(), Context::default());
+    let items =
+        block_on(async { stream::collect(client.watch(&ctx, &"a".to_string()).await?).await })
+            .unwrap();
+    let keys: Vec<&str> = items.iter().map(|i| i.key.as_str()).collect();
+    assert_eq!(keys, vec!["a1", "a2"]);
+}
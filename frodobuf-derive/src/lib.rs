@@ -26,6 +26,58 @@ fn attr_traits(attr: &Attribute, key: &str) -> Vec<syn::Path> {
     traits
 }
 
+/// options from `#[frodobuf(...)]` attributes
+#[derive(Default)]
+struct FrodobufOpts {
+    /// function that constructs the actor, `#[frodobuf(init = "my_init")]`
+    init: Option<syn::Path>,
+    /// name of the provider's dispatcher field, `#[frodobuf(dispatcher = "field")]`
+    dispatcher: Option<Ident>,
+}
+
+/// parse `#[frodobuf(key = "value", ...)]` attributes, where each key is one of `allowed`.
+/// Aborts with an error at the offending item if the attribute is malformed.
+fn frodobuf_opts(attrs: &[Attribute], allowed: &[&str]) -> FrodobufOpts {
+    let mut opts = FrodobufOpts::default();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("frodobuf")) {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(ml)) => ml,
+            _ => abort!(attr.span(), "expected `#[frodobuf(key = \"value\")]`"),
+        };
+        for nested in list.nested.iter() {
+            let nv = match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) => nv,
+                _ => abort!(nested.span(), "expected `key = \"value\"`"),
+            };
+            let key = nv.path.to_token_stream().to_string();
+            if !allowed.contains(&key.as_str()) {
+                abort!(
+                    nv.path.span(),
+                    "unknown frodobuf option `{}`. expected one of: {}",
+                    key,
+                    allowed.join(", ")
+                );
+            }
+            let value = match &nv.lit {
+                syn::Lit::Str(s) => s,
+                _ => abort!(nv.lit.span(), "value of `{}` must be a string", key),
+            };
+            match key.as_str() {
+                "init" => match value.parse::<syn::Path>() {
+                    Ok(path) => opts.init = Some(path),
+                    Err(_) => abort!(value.span(), "init must be the path of a function"),
+                },
+                "dispatcher" => match value.parse::<Ident>() {
+                    Ok(id) => opts.dispatcher = Some(id),
+                    Err(_) => abort!(value.span(), "dispatcher must be a field name"),
+                },
+                _ => {}
+            }
+        }
+    }
+    opts
+}
+
 #[allow(dead_code)]
 struct ServerDef {
     attrs: Vec<Attribute>,
@@ -62,10 +114,18 @@ impl syn::parse::Parse for ServerDef {
     }
 }
 
+/// Implements the wapc guest entry point and message dispatch for an actor.
+///
+/// The actor is constructed once, on the first call, and the same instance handles
+/// all subsequent calls, so state kept in the struct (behind a `Mutex`, `RwLock`,
+/// or atomics, since handlers take `&self`) persists across calls.
+/// The actor is constructed with `Default::default()`, or with a function
+/// named in `#[frodobuf(init = "my_init")]`, which takes no parameters and returns the actor.
 #[proc_macro_error]
-#[proc_macro_derive(FrodobufActor, attributes(services))]
+#[proc_macro_derive(FrodobufActor, attributes(services, frodobuf))]
 pub fn derive_actor(input: TokenStream) -> TokenStream {
     let actor_server = parse_macro_input!(input as ServerDef);
+    let opts = frodobuf_opts(&actor_server.attrs, &["init"]);
 
     let mut traits = Vec::new();
     for attr in actor_server.attrs.iter() {
@@ -78,14 +138,25 @@ pub fn derive_actor(input: TokenStream) -> TokenStream {
         );
     }
     let actor_ident = actor_server.ident;
+    let init = match opts.init {
+        Some(init) => quote!(#init()),
+        None => quote!(<#actor_ident as Default>::default()),
+    };
     let dispatch_impl = gen_dispatch(&traits, &actor_ident);
     let output = quote!(
 
+    frodobuf::lazy_static::lazy_static! {
+        // the actor instance, shared by all guest calls
+        static ref __FRODOBUF_ACTOR: #actor_ident = #init;
+    }
+
     #[link(wasm_import_module = "wapc")]
     extern "C" {
         pub fn __guest_response(ptr: *const u8, len: usize);
         pub fn __guest_error(ptr: *const u8, len: usize);
         pub fn __guest_request(op_ptr: *const u8, ptr: *const u8);
+        pub fn __guest_caller_len() -> usize;
+        pub fn __guest_caller(ptr: *const u8);
     }
 
     #[no_mangle]
@@ -111,18 +182,17 @@ pub fn derive_actor(input: TokenStream) -> TokenStream {
             )
         };
         let method = String::from_utf8_lossy(op);
-        let context = context::Context::default();
-        let actor = #actor_ident ::default();
-        let resp = futures::executor::block_on({
-            MessageDispatch::dispatch(
-                &actor,
-                &context,
-                Message {
-                    method: &method,
-                    arg: std::borrow::Cow::Borrowed(slice),
-                },
-            )
-        });
+        // public key of the calling actor, empty if the host doesn't know the caller
+        let caller = unsafe {
+            let mut caller = vec![0u8; __guest_caller_len() as _];
+            if !caller.is_empty() {
+                __guest_caller(caller.as_mut_ptr());
+            }
+            String::from_utf8_lossy(&caller).to_string()
+        };
+        let caller = if caller.is_empty() { None } else { Some(caller.as_str()) };
+        let actor: &#actor_ident = &__FRODOBUF_ACTOR;
+        let resp = frodobuf::actor::guest_dispatch(actor, caller, &method, slice);
         match resp {
             Ok(Message { arg, .. }) => {
                 unsafe {
@@ -131,10 +201,8 @@ pub fn derive_actor(input: TokenStream) -> TokenStream {
                 1
             }
             Err(e) => {
-                //let errmsg = format!("Guest call failed for method {} on {}: {}",
-                //        &method, #actor_ident, e);
-                let errmsg = format!("Guest call failed for method {}: {}",
-                        &method, e);
+                // return the error envelope, so the caller can decode the error
+                let errmsg = e.to_envelope_bytes();
                 unsafe {
                     __guest_error(errmsg.as_ptr(), errmsg.len() as _);
                 }
@@ -187,6 +255,9 @@ fn gen_dispatch(traits: &[syn::Path], ident: &Ident) -> TokenStream2 {
                 ctx: &context::Context<'_>,
                 message: Message<'_>,
             ) -> Result<Message<'static>, RpcError> {
+                if message.method == frodobuf::batch::BATCH_METHOD {
+                    return frodobuf::batch::dispatch_batch(self, ctx, message.arg.as_ref()).await;
+                }
                 let (trait_name, trait_method) = message
                     .method
                     .rsplit_once('.')
@@ -217,10 +288,20 @@ fn gen_dispatch(traits: &[syn::Path], ident: &Ident) -> TokenStream2 {
 
 */
 
+/// Implements `CapabilityProvider` and message dispatch for a capability provider.
+///
+/// The struct must have a field of type `frodobuf::provider::DispatcherHandle`,
+/// which holds the host dispatcher after `configure_dispatch`, and must implement
+/// `frodobuf::provider::ProviderLifecycle`, which is called when the provider is stopped.
+/// The field is named `dispatcher`, unless another name is given with
+/// `#[frodobuf(dispatcher = "field")]`.
+/// Use `ProviderDispatcherTransport::new(self.dispatcher.clone())` with a generated client
+/// to send messages to actors.
 #[proc_macro_error]
-#[proc_macro_derive(FrodobufProvider, attributes(services))]
+#[proc_macro_derive(FrodobufProvider, attributes(services, frodobuf))]
 pub fn derive_provider(input: TokenStream) -> TokenStream {
     let provider_server = parse_macro_input!(input as ServerDef);
+    let opts = frodobuf_opts(&provider_server.attrs, &["dispatcher"]);
 
     let mut traits = Vec::new();
     for attr in provider_server.attrs.iter() {
@@ -232,8 +313,39 @@ pub fn derive_provider(input: TokenStream) -> TokenStream {
             "Missing list of traits. try `#[services(Trait1,Trait2)]`"
         );
     }
+    let dispatcher = opts
+        .dispatcher
+        .unwrap_or_else(|| Ident::new("dispatcher", provider_server.ident_span));
+    match provider_server
+        .fields
+        .iter()
+        .find(|f| f.ident.as_ref() == Some(&dispatcher))
+    {
+        None => abort!(
+            provider_server.ident_span,
+            "Missing dispatcher field. try adding `{}: DispatcherHandle`",
+            dispatcher
+        ),
+        Some(field) => {
+            let is_handle = match &field.ty {
+                syn::Type::Path(tp) => tp
+                    .path
+                    .segments
+                    .last()
+                    .map(|seg| seg.ident == "DispatcherHandle")
+                    .unwrap_or(false),
+                _ => false,
+            };
+            if !is_handle {
+                abort!(
+                    field.ty.span(),
+                    "field `{}` must have type `DispatcherHandle`",
+                    dispatcher
+                );
+            }
+        }
+    }
     let ident = provider_server.ident;
-    //let fields = actor_server.fields;
     let dispatch_impl = gen_dispatch(&traits, &ident);
     let output = quote!(
 
@@ -244,8 +356,9 @@ pub fn derive_provider(input: TokenStream) -> TokenStream {
         /// managed in the host
         fn configure_dispatch( &self, dispatcher: Box<dyn provider::Dispatcher>,
              ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-          let mut lock = self.dispatcher.write().unwrap();
-          *lock = Some(dispatcher);
+          self.#dispatcher.set_fn(move |actor: &str, op: &str, msg: &[u8]| {
+              dispatcher.dispatch(actor, op, msg)
+          });
           Ok(())
         }
 
@@ -267,7 +380,9 @@ pub fn derive_provider(input: TokenStream) -> TokenStream {
                     method: op,
                     arg: std::borrow::Cow::Borrowed(arg),
                 },
-            ))?;
+            ))
+            // the host returns the error text to the actor, which decodes the envelope
+            .map_err(|e| String::from_utf8_lossy(&e.to_envelope_bytes()).to_string())?;
             Ok(response.arg.to_vec())
         }
 
@@ -277,7 +392,7 @@ pub fn derive_provider(input: TokenStream) -> TokenStream {
         /// WARNING: do not do anything in this function that can
         /// cause a panic, including attempting to write to STDOUT while the host process is terminating
         fn stop(&self) {
-                #ident :: stop(&self);
+                frodobuf::provider::ProviderLifecycle::stop(self);
         }
     }
 
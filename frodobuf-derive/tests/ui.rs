@@ -0,0 +1,8 @@
+//! Checks the error messages from the derive macros, using the cases in tests/ui.
+//! To update the expected output after changing a message, run with `TRYBUILD=overwrite`.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
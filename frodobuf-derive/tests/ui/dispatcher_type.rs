@@ -0,0 +1,9 @@
+use frodobuf_derive::FrodobufProvider;
+
+#[derive(FrodobufProvider)]
+#[services(Store)]
+struct StoreProvider {
+    dispatcher: String,
+}
+
+fn main() {}
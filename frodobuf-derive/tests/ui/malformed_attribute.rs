@@ -0,0 +1,8 @@
+use frodobuf_derive::FrodobufActor;
+
+#[derive(Default, FrodobufActor)]
+#[services(Actor)]
+#[frodobuf(init = new_counter)]
+struct Counter {}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use frodobuf_derive::FrodobufProvider;
+
+#[derive(FrodobufProvider)]
+#[services(Store)]
+#[frodobuf(dispatcher = "handle")]
+struct StoreProvider {
+    dispatcher: u32,
+}
+
+fn main() {}
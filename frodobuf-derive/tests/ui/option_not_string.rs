@@ -0,0 +1,8 @@
+use frodobuf_derive::FrodobufActor;
+
+#[derive(Default, FrodobufActor)]
+#[services(Actor)]
+#[frodobuf(init = 5)]
+struct Counter {}
+
+fn main() {}
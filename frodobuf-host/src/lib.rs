@@ -0,0 +1,711 @@
+//! Native host simulator for frodobuf actors
+//!
+//! Loads a compiled actor with wasmtime, implements the `wapc` host imports,
+//! and routes the actor's host calls to native [`MessageDispatch`] handlers,
+//! so actors can be exercised in `cargo test` without deploying to a wasmcloud host.
+//!
+#![deny(missing_docs)]
+
+use async_trait::async_trait;
+use frodobuf::{client, context, Message, MessageDispatch, RpcError, Transport};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    rc::Rc,
+    sync::{mpsc, Arc, Mutex},
+    thread::JoinHandle,
+};
+use thiserror::Error as ThisError;
+use wasmtime::{Caller, Engine, Extern, Instance, Linker, Memory, Module, Store, Trap};
+
+/// wapc import module name
+const WAPC_MODULE: &str = "wapc";
+/// entry point exported by actors
+const GUEST_CALL: &str = "__guest_call";
+/// optional initializers exported by some actors, called after instantiation
+const INIT_FUNCTIONS: &[&str] = &["_start", "wapc_init"];
+
+/// Errors from the host simulator
+#[derive(ThisError, Debug)]
+pub enum HostError {
+    /// The wasm module could not be compiled or instantiated
+    #[error("loading actor: {0}")]
+    Load(String),
+
+    /// The actor does not export a required function or memory
+    #[error("actor is missing export '{0}'")]
+    MissingExport(String),
+
+    /// The actor trapped during a call
+    #[error("actor trapped: {0}")]
+    Trap(String),
+
+    /// The actor's thread has stopped
+    #[error("actor is no longer running")]
+    Stopped,
+}
+
+impl From<HostError> for RpcError {
+    fn from(e: HostError) -> RpcError {
+        RpcError::HostError(e.to_string())
+    }
+}
+
+type Handler = Arc<dyn MessageDispatch + Send + Sync>;
+
+/// Handler registered for a binding and namespace
+#[derive(Clone)]
+struct Route {
+    binding: String,
+    namespace: String,
+    handler: Handler,
+}
+
+/// Registry of native handlers for host calls made by actors.
+/// Handlers must be registered before loading the actors that call them.
+#[derive(Clone, Default)]
+pub struct HostSimulator {
+    routes: Vec<Route>,
+    logs: Arc<Mutex<Vec<String>>>,
+}
+
+impl HostSimulator {
+    /// Constructs a simulator with no handlers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes host calls for `binding` (usually "default") and `namespace`
+    /// (a capability contract id such as "wasmcloud:keyvalue", or an actor's public key)
+    /// to the handler. A later registration for the same binding and namespace replaces
+    /// the earlier one.
+    pub fn register<H>(&mut self, binding: &str, namespace: &str, handler: H) -> &mut Self
+    where
+        H: MessageDispatch + Send + Sync + 'static,
+    {
+        self.register_shared(binding, namespace, Arc::new(handler))
+    }
+
+    /// Routes host calls to a shared handler, so the test can keep a reference to it,
+    /// for example, to inspect calls recorded by a mock.
+    pub fn register_shared(
+        &mut self,
+        binding: &str,
+        namespace: &str,
+        handler: Arc<dyn MessageDispatch + Send + Sync>,
+    ) -> &mut Self {
+        self.routes
+            .retain(|r| r.binding != binding || r.namespace != namespace);
+        self.routes.push(Route {
+            binding: binding.to_string(),
+            namespace: namespace.to_string(),
+            handler,
+        });
+        self
+    }
+
+    /// Returns messages written by actors with `__console_log`, in order
+    pub fn logs(&self) -> Vec<String> {
+        self.logs.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Compiles and instantiates an actor. `actor_id` is the actor's public key,
+    /// passed to handlers in `Context::actor`. The module may be in binary or text format.
+    pub fn load_actor(&self, actor_id: &str, wasm: &[u8]) -> Result<WasmActor, HostError> {
+        let (tx, rx) = mpsc::channel::<GuestCall>();
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<(), HostError>>();
+        let routes = self.routes.clone();
+        let logs = self.logs.clone();
+        let actor_id = actor_id.to_string();
+        let wasm = wasm.to_vec();
+
+        // wasmtime stores are not Send, so each actor runs on its own thread
+        let thread = std::thread::spawn(move || {
+            let runtime = match ActorRuntime::new(&wasm, actor_id, routes, logs) {
+                Ok(runtime) => {
+                    let _ = ready_tx.send(Ok(()));
+                    runtime
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            for call in rx.iter() {
+                let result = runtime.guest_call(call.caller.as_deref(), &call.op, &call.msg);
+                let _ = call.reply.send(result);
+            }
+        });
+        ready_rx.recv().map_err(|_| HostError::Stopped)??;
+        Ok(WasmActor {
+            sender: Mutex::new(tx),
+            thread: Some(thread),
+        })
+    }
+}
+
+/// Request sent to the actor's thread
+struct GuestCall {
+    caller: Option<String>,
+    op: String,
+    msg: Vec<u8>,
+    reply: mpsc::Sender<Result<Vec<u8>, RpcError>>,
+}
+
+/// A running actor. Use `call` to invoke an operation directly, or use the actor
+/// as the `Transport` of a generated client.
+pub struct WasmActor {
+    sender: Mutex<mpsc::Sender<GuestCall>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WasmActor {
+    /// Invokes `__guest_call` on the actor with the operation name and serialized argument.
+    /// Returns the actor's response, or the error it returned with `__guest_error`,
+    /// decoded from its envelope. The caller is unknown to the actor; use `call_as`
+    /// to call methods with `@auth` requirements.
+    pub fn call(&self, op: &str, msg: &[u8]) -> Result<Vec<u8>, RpcError> {
+        self.send_call(None, op, msg)
+    }
+
+    /// Invokes `__guest_call` on the actor, as the actor with public key `caller`.
+    /// The actor receives the caller from `__guest_caller`, and passes it to its handlers
+    /// in `Context::actor`.
+    pub fn call_as(&self, caller: &str, op: &str, msg: &[u8]) -> Result<Vec<u8>, RpcError> {
+        self.send_call(Some(caller), op, msg)
+    }
+
+    fn send_call(&self, caller: Option<&str>, op: &str, msg: &[u8]) -> Result<Vec<u8>, RpcError> {
+        let (reply, response) = mpsc::channel();
+        self.sender
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .send(GuestCall {
+                caller: caller.map(String::from),
+                op: op.to_string(),
+                msg: msg.to_vec(),
+                reply,
+            })
+            .map_err(|_| HostError::Stopped)?;
+        response.recv().map_err(|_| HostError::Stopped)?
+    }
+
+    /// Queues a call to the actor and returns without waiting for it to run.
+    /// The actor's response, or error, is discarded.
+    pub fn notify(&self, op: &str, msg: &[u8]) -> Result<(), RpcError> {
+        self.send_notify(None, op, msg)
+    }
+
+    fn send_notify(&self, caller: Option<&str>, op: &str, msg: &[u8]) -> Result<(), RpcError> {
+        let (reply, _) = mpsc::channel();
+        self.sender
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .send(GuestCall {
+                caller: caller.map(String::from),
+                op: op.to_string(),
+                msg: msg.to_vec(),
+                reply,
+            })
+            .map_err(|_| HostError::Stopped)?;
+        Ok(())
+    }
+}
+
+impl Drop for WasmActor {
+    fn drop(&mut self) {
+        // closing the channel ends the actor's thread
+        let (closed, _) = mpsc::channel();
+        *self.sender.lock().unwrap_or_else(|e| e.into_inner()) = closed;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Sends messages to the actor. The call blocks the current thread until the actor returns,
+/// except for `@oneway` methods, which are queued with `notify`.
+/// The caller is `Context::actor`.
+#[async_trait]
+impl Transport for WasmActor {
+    async fn send(
+        &self,
+        ctx: &context::Context<'_>,
+        _config: &client::ClientConfig,
+        req: Message<'_>,
+    ) -> std::result::Result<Message<'static>, RpcError> {
+        let res = self.send_call(ctx.actor, req.method, req.arg.as_ref())?;
+        Ok(Message {
+            method: "_reply",
+            arg: Cow::Owned(res),
+        })
+    }
+
+    async fn send_oneway(
+        &self,
+        ctx: &context::Context<'_>,
+        _config: &client::ClientConfig,
+        req: Message<'_>,
+    ) -> std::result::Result<(), RpcError> {
+        self.send_notify(ctx.actor, req.method, req.arg.as_ref())
+    }
+}
+
+/// Per-call buffers shared between the host functions
+#[derive(Default)]
+struct CallState {
+    guest_caller: Option<String>,
+    guest_request: Option<(String, Vec<u8>)>,
+    guest_response: Option<Vec<u8>>,
+    guest_error: Option<String>,
+    host_response: Option<Vec<u8>>,
+    host_error: Option<String>,
+}
+
+/// wasmtime instance and host state, owned by the actor's thread
+struct ActorRuntime {
+    instance: Instance,
+    state: Rc<RefCell<CallState>>,
+}
+
+impl ActorRuntime {
+    fn new(
+        wasm: &[u8],
+        actor_id: String,
+        routes: Vec<Route>,
+        logs: Arc<Mutex<Vec<String>>>,
+    ) -> Result<Self, HostError> {
+        let engine = Engine::default();
+        let store = Store::new(&engine);
+        let module = Module::new(&engine, wasm).map_err(|e| HostError::Load(e.to_string()))?;
+        let state = Rc::new(RefCell::new(CallState::default()));
+        let mut linker = Linker::new(&store);
+        link_wapc(&mut linker, state.clone(), actor_id, routes, logs)
+            .map_err(|e| HostError::Load(e.to_string()))?;
+        let instance = linker
+            .instantiate(&module)
+            .map_err(|e| HostError::Load(e.to_string()))?;
+        for name in INIT_FUNCTIONS.iter() {
+            if let Some(init) = instance.get_func(name) {
+                init.call(&[]).map_err(|e| HostError::Trap(e.to_string()))?;
+            }
+        }
+        if instance.get_func(GUEST_CALL).is_none() {
+            return Err(HostError::MissingExport(GUEST_CALL.to_string()));
+        }
+        Ok(ActorRuntime { instance, state })
+    }
+
+    fn guest_call(&self, caller: Option<&str>, op: &str, msg: &[u8]) -> Result<Vec<u8>, RpcError> {
+        {
+            let mut state = self.state.borrow_mut();
+            *state = CallState::default();
+            state.guest_caller = caller.map(String::from);
+            state.guest_request = Some((op.to_string(), msg.to_vec()));
+        }
+        let guest_call = self
+            .instance
+            .get_typed_func::<(i32, i32), i32>(GUEST_CALL)
+            .map_err(|_| HostError::MissingExport(GUEST_CALL.to_string()))?;
+        let rc = guest_call
+            .call((op.len() as i32, msg.len() as i32))
+            .map_err(|e| HostError::Trap(e.to_string()))?;
+        let mut state = self.state.borrow_mut();
+        if rc == 1 {
+            Ok(state.guest_response.take().unwrap_or_default())
+        } else {
+            Err(match state.guest_error.take() {
+                Some(error) => RpcError::from_error_bytes(error.as_bytes()),
+                None => RpcError::HostError(format!(
+                    "guest call '{}' failed without an error message",
+                    op
+                )),
+            })
+        }
+    }
+}
+
+fn memory(caller: &Caller<'_>) -> Result<Memory, Trap> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Trap::new("actor does not export memory"))
+}
+
+/// Reads `len` bytes at `ptr` of the actor's memory. The range is checked before
+/// the buffer is allocated, as both values come from the actor.
+fn read_bytes(caller: &Caller<'_>, ptr: i32, len: i32) -> Result<Vec<u8>, Trap> {
+    let memory = memory(caller)?;
+    if ptr < 0 || len < 0 || ptr as usize + len as usize > memory.data_size() {
+        return Err(Trap::new(format!(
+            "out of bounds memory access: ptr {} len {}",
+            ptr, len
+        )));
+    }
+    let mut buf = vec![0u8; len as usize];
+    memory
+        .read(ptr as usize, &mut buf)
+        .map_err(|e| Trap::new(e.to_string()))?;
+    Ok(buf)
+}
+
+fn read_string(caller: &Caller<'_>, ptr: i32, len: i32) -> Result<String, Trap> {
+    Ok(String::from_utf8_lossy(&read_bytes(caller, ptr, len)?).to_string())
+}
+
+fn write_bytes(caller: &Caller<'_>, ptr: i32, data: &[u8]) -> Result<(), Trap> {
+    if ptr < 0 {
+        return Err(Trap::new(format!(
+            "out of bounds memory access: ptr {}",
+            ptr
+        )));
+    }
+    memory(caller)?
+        .write(ptr as usize, data)
+        .map_err(|e| Trap::new(e.to_string()))
+}
+
+/// Defines the wapc host functions imported by actors
+fn link_wapc(
+    linker: &mut Linker,
+    state: Rc<RefCell<CallState>>,
+    actor_id: String,
+    routes: Vec<Route>,
+    logs: Arc<Mutex<Vec<String>>>,
+) -> anyhow::Result<()> {
+    let st = state.clone();
+    linker.func(
+        WAPC_MODULE,
+        "__guest_request",
+        move |caller: Caller<'_>, op_ptr: i32, ptr: i32| -> Result<(), Trap> {
+            if let Some((op, msg)) = st.borrow().guest_request.as_ref() {
+                write_bytes(&caller, op_ptr, op.as_bytes())?;
+                write_bytes(&caller, ptr, msg)?;
+            }
+            Ok(())
+        },
+    )?;
+
+    let st = state.clone();
+    linker.func(WAPC_MODULE, "__guest_caller_len", move || -> i32 {
+        st.borrow()
+            .guest_caller
+            .as_ref()
+            .map(|c| c.len())
+            .unwrap_or(0) as i32
+    })?;
+
+    let st = state.clone();
+    linker.func(
+        WAPC_MODULE,
+        "__guest_caller",
+        move |caller: Caller<'_>, ptr: i32| -> Result<(), Trap> {
+            if let Some(guest_caller) = st.borrow().guest_caller.as_ref() {
+                write_bytes(&caller, ptr, guest_caller.as_bytes())?;
+            }
+            Ok(())
+        },
+    )?;
+
+    let st = state.clone();
+    linker.func(
+        WAPC_MODULE,
+        "__guest_response",
+        move |caller: Caller<'_>, ptr: i32, len: i32| -> Result<(), Trap> {
+            st.borrow_mut().guest_response = Some(read_bytes(&caller, ptr, len)?);
+            Ok(())
+        },
+    )?;
+
+    let st = state.clone();
+    linker.func(
+        WAPC_MODULE,
+        "__guest_error",
+        move |caller: Caller<'_>, ptr: i32, len: i32| -> Result<(), Trap> {
+            st.borrow_mut().guest_error = Some(read_string(&caller, ptr, len)?);
+            Ok(())
+        },
+    )?;
+
+    let st = state.clone();
+    linker.func(
+        WAPC_MODULE,
+        "__host_call",
+        move |caller: Caller<'_>,
+              bd_ptr: i32,
+              bd_len: i32,
+              ns_ptr: i32,
+              ns_len: i32,
+              op_ptr: i32,
+              op_len: i32,
+              ptr: i32,
+              len: i32|
+              -> Result<i32, Trap> {
+            let binding = read_string(&caller, bd_ptr, bd_len)?;
+            let namespace = read_string(&caller, ns_ptr, ns_len)?;
+            let op = read_string(&caller, op_ptr, op_len)?;
+            let msg = read_bytes(&caller, ptr, len)?;
+            let result = match routes
+                .iter()
+                .find(|r| r.binding == binding && r.namespace == namespace)
+            {
+                Some(route) => {
+                    let ctx = context::Context {
+                        actor: Some(&actor_id),
+                        ..Default::default()
+                    };
+                    futures::executor::block_on(route.handler.dispatch(
+                        &ctx,
+                        Message {
+                            method: &op,
+                            arg: Cow::Owned(msg),
+                        },
+                    ))
+                    .map(|m| m.arg.into_owned())
+                }
+                None => Err(RpcError::HostError(format!(
+                    "no handler registered for binding '{}' namespace '{}'",
+                    binding, namespace
+                ))),
+            };
+            let mut state = st.borrow_mut();
+            match result {
+                Ok(response) => {
+                    state.host_response = Some(response);
+                    Ok(1)
+                }
+                Err(e) => {
+                    let envelope = e.to_envelope_bytes();
+                    state.host_error = Some(String::from_utf8_lossy(&envelope).to_string());
+                    Ok(0)
+                }
+            }
+        },
+    )?;
+
+    let st = state.clone();
+    linker.func(WAPC_MODULE, "__host_response_len", move || -> i32 {
+        st.borrow()
+            .host_response
+            .as_ref()
+            .map(|r| r.len())
+            .unwrap_or(0) as i32
+    })?;
+
+    let st = state.clone();
+    linker.func(
+        WAPC_MODULE,
+        "__host_response",
+        move |caller: Caller<'_>, ptr: i32| -> Result<(), Trap> {
+            if let Some(response) = st.borrow().host_response.as_ref() {
+                write_bytes(&caller, ptr, response)?;
+            }
+            Ok(())
+        },
+    )?;
+
+    let st = state.clone();
+    linker.func(WAPC_MODULE, "__host_error_len", move || -> i32 {
+        st.borrow()
+            .host_error
+            .as_ref()
+            .map(|e| e.len())
+            .unwrap_or(0) as i32
+    })?;
+
+    let st = state;
+    linker.func(
+        WAPC_MODULE,
+        "__host_error",
+        move |caller: Caller<'_>, ptr: i32| -> Result<(), Trap> {
+            if let Some(error) = st.borrow().host_error.as_ref() {
+                write_bytes(&caller, ptr, error.as_bytes())?;
+            }
+            Ok(())
+        },
+    )?;
+
+    linker.func(
+        WAPC_MODULE,
+        "__console_log",
+        move |caller: Caller<'_>, ptr: i32, len: i32| -> Result<(), Trap> {
+            let line = read_string(&caller, ptr, len)?;
+            logs.lock().unwrap_or_else(|e| e.into_inner()).push(line);
+            Ok(())
+        },
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Minimal actor in wasm text format. Every operation is forwarded to
+    /// binding "default", namespace "test:echo" with the same name and argument,
+    /// and the host's reply (or error) is returned to the caller.
+    const ECHO_ACTOR: &str = r#"
+    (module
+      (import "wapc" "__guest_request" (func $guest_request (param i32 i32)))
+      (import "wapc" "__guest_response" (func $guest_response (param i32 i32)))
+      (import "wapc" "__guest_error" (func $guest_error (param i32 i32)))
+      (import "wapc" "__host_call"
+          (func $host_call (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+      (import "wapc" "__host_response_len" (func $host_response_len (result i32)))
+      (import "wapc" "__host_response" (func $host_response (param i32)))
+      (import "wapc" "__host_error_len" (func $host_error_len (result i32)))
+      (import "wapc" "__host_error" (func $host_error (param i32)))
+      (import "wapc" "__console_log" (func $console_log (param i32 i32)))
+      (memory (export "memory") 1)
+      (data (i32.const 0) "default")
+      (data (i32.const 16) "test:echo")
+      ;; op at 1024, arg at 2048, reply at 8192
+      (func (export "__guest_call") (param $op_len i32) (param $msg_len i32) (result i32)
+        (local $len i32)
+        (call $guest_request (i32.const 1024) (i32.const 2048))
+        (call $console_log (i32.const 1024) (local.get $op_len))
+        (if (result i32)
+          (call $host_call (i32.const 0) (i32.const 7) (i32.const 16) (i32.const 9)
+                (i32.const 1024) (local.get $op_len) (i32.const 2048) (local.get $msg_len))
+          (then
+            (local.set $len (call $host_response_len))
+            (call $host_response (i32.const 8192))
+            (call $guest_response (i32.const 8192) (local.get $len))
+            (i32.const 1))
+          (else
+            (local.set $len (call $host_error_len))
+            (call $host_error (i32.const 8192))
+            (call $guest_error (i32.const 8192) (local.get $len))
+            (i32.const 0)))))
+    "#;
+
+    /// Handler that reverses its argument, and fails for method "Fail"
+    struct Reverse {}
+
+    #[async_trait]
+    impl MessageDispatch for Reverse {
+        async fn dispatch(
+            &self,
+            ctx: &context::Context<'_>,
+            message: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            assert_eq!(ctx.actor, Some("MTEST"));
+            if message.method == "Fail" {
+                return Err(RpcError::Other("failed on purpose".into()));
+            }
+            let mut arg = message.arg.to_vec();
+            arg.reverse();
+            Ok(Message {
+                method: "_reply",
+                arg: Cow::Owned(arg),
+            })
+        }
+    }
+
+    #[test]
+    fn host_call_round_trip() {
+        let mut host = HostSimulator::new();
+        host.register("default", "test:echo", Reverse {});
+        let actor = host
+            .load_actor("MTEST", ECHO_ACTOR.as_bytes())
+            .expect("load");
+        assert_eq!(actor.call("Reverse", b"abc").unwrap(), b"cba".to_vec());
+
+        // the handler's error is passed through the actor, and decoded to the same variant
+        let err = actor.call("Fail", b"").unwrap_err();
+        assert!(
+            matches!(err, RpcError::Other(ref s) if s == "failed on purpose"),
+            "{:?}",
+            err
+        );
+        assert_eq!(host.logs(), vec!["Reverse".to_string(), "Fail".to_string()]);
+    }
+
+    #[test]
+    fn unregistered_namespace() {
+        let host = HostSimulator::new();
+        let actor = host.load_actor("MTEST", ECHO_ACTOR.as_bytes()).unwrap();
+        let ctx = context::Context::default();
+        let res = futures::executor::block_on(actor.send(
+            &ctx,
+            &client::ClientConfig::actor("MTEST"),
+            Message {
+                method: "Anything",
+                arg: Cow::Borrowed(b"x"),
+            },
+        ));
+        assert!(
+            matches!(res, Err(RpcError::HostError(ref s)) if s.contains("no handler")),
+            "{:?}",
+            res
+        );
+    }
+
+    #[test]
+    fn missing_guest_call() {
+        let host = HostSimulator::new();
+        let res = host.load_actor("MTEST", b"(module (memory (export \"memory\") 1))");
+        assert!(matches!(res, Err(HostError::MissingExport(_))));
+    }
+
+    /// Actor that returns the caller from `__guest_caller`
+    const WHOAMI_ACTOR: &str = r#"
+    (module
+      (import "wapc" "__guest_response" (func $guest_response (param i32 i32)))
+      (import "wapc" "__guest_caller_len" (func $guest_caller_len (result i32)))
+      (import "wapc" "__guest_caller" (func $guest_caller (param i32)))
+      (memory (export "memory") 1)
+      (func (export "__guest_call") (param $op_len i32) (param $msg_len i32) (result i32)
+        (call $guest_caller (i32.const 0))
+        (call $guest_response (i32.const 0) (call $guest_caller_len))
+        (i32.const 1)))
+    "#;
+
+    #[test]
+    fn guest_caller() {
+        let host = HostSimulator::new();
+        let actor = host.load_actor("MTEST", WHOAMI_ACTOR.as_bytes()).unwrap();
+        assert_eq!(actor.call_as("MCALLER", "Whoami", b"").unwrap(), b"MCALLER");
+        assert_eq!(actor.call("Whoami", b"").unwrap(), b"");
+
+        // the transport passes the context's actor
+        let ctx = context::Context {
+            actor: Some("MOTHER"),
+            ..Default::default()
+        };
+        let res = futures::executor::block_on(actor.send(
+            &ctx,
+            &client::ClientConfig::actor("MTEST"),
+            Message {
+                method: "Whoami",
+                arg: Cow::Borrowed(b""),
+            },
+        ))
+        .unwrap();
+        assert_eq!(res.arg.as_ref(), b"MOTHER");
+    }
+
+    /// Actor that responds with a range outside its memory: a negative length
+    /// for a one-byte operation name, or else a range past the end of memory
+    const OVERRUN_ACTOR: &str = r#"
+    (module
+      (import "wapc" "__guest_response" (func $guest_response (param i32 i32)))
+      (memory (export "memory") 1)
+      (func (export "__guest_call") (param $op_len i32) (param $msg_len i32) (result i32)
+        (if (i32.eq (local.get $op_len) (i32.const 1))
+          (then (call $guest_response (i32.const 0) (i32.const -1)))
+          (else (call $guest_response (i32.const 65000) (i32.const 1000))))
+        (i32.const 1)))
+    "#;
+
+    #[test]
+    fn out_of_bounds_memory() {
+        let host = HostSimulator::new();
+        let actor = host.load_actor("MTEST", OVERRUN_ACTOR.as_bytes()).unwrap();
+        let err = actor.call("N", b"").unwrap_err();
+        assert!(err.to_string().contains("out of bounds"), "{:?}", err);
+        let err = actor.call("Past", b"").unwrap_err();
+        assert!(err.to_string().contains("out of bounds"), "{:?}", err);
+    }
+}
@@ -387,6 +387,11 @@ impl<'a> HasAttributes for Enumeration {
     }
 }
 
+/// used to omit false flags from serialized schema, so they don't change the schema id
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
 /// Service method
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Method {
@@ -396,13 +401,15 @@ pub struct Method {
     pub input_type: Option<FieldType>,
     /// Output type, or None if the function return void
     pub output_type: Option<FieldType>,
-
-    /*
-    /// If this method is client streaming
+    /// If this method is client streaming (input is a stream of `input_type`)
+    #[serde(default, skip_serializing_if = "is_false")]
     pub client_streaming: bool,
-    /// If this method is server streaming
+    /// If this method is server streaming (output is a stream of `output_type`)
+    #[serde(default, skip_serializing_if = "is_false")]
     pub server_streaming: bool,
-     */
+    /// Application error types the method may return, declared with `throws`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<Ident>,
     /// Method attributes
     pub attributes: Vec<Attribute>,
 }
@@ -0,0 +1,214 @@
+//! Access control for service methods
+//!
+//! Services and rpc methods may declare requirements with the `@auth` attribute:
+//! ```text
+//!   @auth(role="reader")
+//!   service Store {
+//!       rpc get(string) returns uint32;
+//!       @auth(role="admin")
+//!       rpc delete(string);
+//!   }
+//! ```
+//! The generated `XxxServer::dispatch` checks methods that have requirements, from the
+//! service, the method, or both, before the handler runs. The requirements are passed to an
+//! [`Authorizer`], along with the call's [`Context`](crate::context::Context), whose `actor`
+//! is the public key of the calling actor. Denied calls return [`RpcError::PermissionDenied`].
+//! Methods without requirements are not checked.
+//!
+//! The authorizer is the one registered with [`set_authorizer`], unless the server overrides
+//! `XxxServer::authorizer`. If there is no authorizer, calls to methods with requirements
+//! are denied.
+use crate::{context, RpcError};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+
+/// A call to a method with `@auth` requirements
+#[derive(Debug, Clone, Copy)]
+pub struct AuthCall<'a> {
+    /// Service name
+    pub service: &'a str,
+    /// Method name
+    pub method: &'a str,
+    /// Requirements, as (name, value) pairs from the service's `@auth` attributes,
+    /// followed by the method's. The name is `_` for a value without a name,
+    /// as in `@auth("admin")`. `@auth` with no values adds no requirements.
+    pub requirements: &'a [(&'a str, &'a str)],
+}
+
+/// Decides whether a call is allowed
+pub trait Authorizer: Send + Sync {
+    /// Returns `Ok(())` if the call is allowed, or an error, usually
+    /// `RpcError::PermissionDenied`, if it is not.
+    fn authorize(&self, ctx: &context::Context<'_>, call: &AuthCall<'_>) -> Result<(), RpcError>;
+}
+
+lazy_static::lazy_static! {
+    static ref AUTHORIZER: RwLock<Option<Arc<dyn Authorizer>>> = RwLock::new(None);
+}
+
+/// Sets the authorizer used by all servers that don't override `XxxServer::authorizer`
+pub fn set_authorizer<A: Authorizer + 'static>(authorizer: A) {
+    *AUTHORIZER.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(authorizer));
+}
+
+/// Removes the authorizer set with `set_authorizer`
+pub fn clear_authorizer() {
+    *AUTHORIZER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Returns the authorizer set with `set_authorizer`
+pub fn authorizer() -> Option<Arc<dyn Authorizer>> {
+    AUTHORIZER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Checks a call. Called by the generated server before dispatching
+/// a method with requirements.
+pub fn check(
+    authorizer: Option<Arc<dyn Authorizer>>,
+    ctx: &context::Context<'_>,
+    call: &AuthCall<'_>,
+) -> Result<(), RpcError> {
+    match authorizer {
+        Some(authorizer) => authorizer.authorize(ctx, call),
+        None => Err(RpcError::PermissionDenied(format!(
+            "{}.{}: no authorizer",
+            call.service, call.method
+        ))),
+    }
+}
+
+/// Authorizer that grants roles to actors.
+/// A call is allowed if the calling actor has every role in the call's `role` requirements
+/// (an unnamed value, as in `@auth("admin")`, is also a role).
+/// Calls with no role requirements are allowed for any known caller (`Context::actor` is set).
+/// Other requirement names are denied, so that a misspelled requirement doesn't allow access.
+#[derive(Debug, Default, Clone)]
+pub struct RoleAuthorizer {
+    roles: HashMap<String, HashSet<String>>,
+}
+
+impl RoleAuthorizer {
+    /// Constructs an authorizer with no roles
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grants the role to the actor, identified by its public key
+    pub fn grant<R: Into<String>, A: Into<String>>(mut self, role: R, actor: A) -> Self {
+        self.roles
+            .entry(role.into())
+            .or_default()
+            .insert(actor.into());
+        self
+    }
+}
+
+impl Authorizer for RoleAuthorizer {
+    fn authorize(&self, ctx: &context::Context<'_>, call: &AuthCall<'_>) -> Result<(), RpcError> {
+        let deny = |reason: String| {
+            Err(RpcError::PermissionDenied(format!(
+                "{}.{}: {}",
+                call.service, call.method, reason
+            )))
+        };
+        let actor = match ctx.actor {
+            Some(actor) => actor,
+            None => return deny("unknown caller".into()),
+        };
+        for (name, value) in call.requirements.iter() {
+            match *name {
+                "role" | "_" => {
+                    let granted = self
+                        .roles
+                        .get(*value)
+                        .map(|actors| actors.contains(actor))
+                        .unwrap_or(false);
+                    if !granted {
+                        return deny(format!("requires role {}", value));
+                    }
+                }
+                _ => return deny(format!("unsupported requirement {}", name)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Message, MessageDispatch};
+    use async_trait::async_trait;
+    use std::borrow::Cow;
+
+    #[test]
+    fn roles() {
+        let auth = RoleAuthorizer::new()
+            .grant("reader", "alice")
+            .grant("reader", "bob")
+            .grant("admin", "alice");
+        let ctx = |actor| context::Context {
+            actor,
+            ..Default::default()
+        };
+        let call = |requirements| AuthCall {
+            service: "Store",
+            method: "Delete",
+            requirements,
+        };
+        let admin = call(&[("role", "reader"), ("_", "admin")]);
+        assert!(auth.authorize(&ctx(Some("alice")), &admin).is_ok());
+        assert!(matches!(
+            auth.authorize(&ctx(Some("bob")), &admin),
+            Err(RpcError::PermissionDenied(_))
+        ));
+        assert!(auth.authorize(&ctx(None), &call(&[])).is_err());
+        assert!(auth.authorize(&ctx(Some("carol")), &call(&[])).is_ok());
+        assert!(auth
+            .authorize(&ctx(Some("alice")), &call(&[("group", "x")]))
+            .is_err());
+        assert!(check(None, &ctx(Some("alice")), &call(&[])).is_err());
+    }
+
+    /// actor with one method, "Store.Delete", that requires role "admin"
+    struct Store {
+        authorizer: Arc<dyn Authorizer>,
+    }
+
+    #[async_trait]
+    impl MessageDispatch for Store {
+        async fn dispatch(
+            &self,
+            ctx: &context::Context<'_>,
+            _message: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            let call = AuthCall {
+                service: "Store",
+                method: "Delete",
+                requirements: &[("role", "admin")],
+            };
+            check(Some(self.authorizer.clone()), ctx, &call)?;
+            Ok(Message {
+                method: "_reply",
+                arg: Cow::Borrowed(b""),
+            })
+        }
+    }
+
+    #[test]
+    fn actor_caller() {
+        let store = Store {
+            authorizer: Arc::new(RoleAuthorizer::new().grant("admin", "MALICE")),
+        };
+        // the caller reported by the host is the context's actor
+        let call = |caller| crate::actor::guest_dispatch(&store, caller, "Delete", b"");
+        assert!(call(Some("MALICE")).is_ok());
+        assert!(matches!(
+            call(Some("MBOB")),
+            Err(RpcError::PermissionDenied(_))
+        ));
+        assert!(matches!(call(None), Err(RpcError::PermissionDenied(_))));
+    }
+}
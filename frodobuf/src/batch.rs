@@ -0,0 +1,246 @@
+//! Sending several requests in one transport call
+//!
+//! A [`Batch`] holds serialized requests for any number of methods of one service.
+//! It is sent with [`Transport::send_batch`], which packs all of the requests into a single
+//! message with the method name [`BATCH_METHOD`], so a batch of calls from an actor
+//! costs one `host_call`. The receiver's `MessageDispatch` unpacks the batch with
+//! [`dispatch_batch`], dispatches each request in order, and returns one result per request.
+//! A failing request does not stop the rest of the batch.
+//!
+//! Generated clients have a `batch()` method that returns a typed builder:
+//! ```ignore
+//!   let mut batch = client.batch();
+//!   let a = batch.get(&"a".to_string())?;
+//!   let b = batch.get(&"b".to_string())?;
+//!   let results = batch.send(&ctx).await?;
+//!   let (a, b) = (results.get(&a), results.get(&b));
+//! ```
+//! Streaming and `@oneway` methods can't be batched.
+//!
+use crate::{context, deserialize, serialize, ErrorEnvelope, Message, MessageDispatch, RpcError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{borrow::Cow, marker::PhantomData};
+
+/// Method name of a message containing a batch
+pub const BATCH_METHOD: &str = "_batch";
+
+/// One request of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BatchItem {
+    method: String,
+    #[serde(with = "serde_bytes")]
+    arg: Vec<u8>,
+}
+
+/// Result of one request of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum BatchItemResult {
+    Ok(#[serde(with = "serde_bytes")] Vec<u8>),
+    Err(ErrorEnvelope),
+}
+
+/// Requests to be sent together
+#[derive(Debug, Default, Clone)]
+pub struct Batch {
+    items: Vec<BatchItem>,
+}
+
+impl Batch {
+    /// Constructs an empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of requests in the batch
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns true if the batch has no requests
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Adds a request for a method that returns `R`, or `RpcError`.
+    /// Returns the handle used to get its result from [`BatchResults`].
+    pub fn push<R, E>(&mut self, method: &str, arg: Vec<u8>) -> Pending<R, E> {
+        self.push_item(method, arg, false)
+    }
+
+    /// Adds a request for a method declared with `throws`, whose response
+    /// is a serialized `Result<R, E>`.
+    pub fn push_throws<R, E>(&mut self, method: &str, arg: Vec<u8>) -> Pending<R, E> {
+        self.push_item(method, arg, true)
+    }
+
+    fn push_item<R, E>(&mut self, method: &str, arg: Vec<u8>, throws: bool) -> Pending<R, E> {
+        self.items.push(BatchItem {
+            method: method.to_string(),
+            arg,
+        });
+        Pending {
+            index: self.items.len() - 1,
+            throws,
+            _result: PhantomData,
+        }
+    }
+
+    /// Returns the message containing all requests of the batch
+    pub fn to_message(&self) -> Result<Message<'static>, RpcError> {
+        Ok(Message {
+            method: BATCH_METHOD,
+            arg: Cow::Owned(serialize(&self.items)?),
+        })
+    }
+}
+
+/// Handle to the result of one request in a batch.
+/// `R` is the method's return type, and `E` its error type.
+#[derive(Debug)]
+pub struct Pending<R, E = RpcError> {
+    index: usize,
+    throws: bool,
+    _result: PhantomData<fn() -> (R, E)>,
+}
+
+impl<R, E> Pending<R, E> {
+    /// Returns the position of the request in the batch
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// Results of a batch, in the same order as the requests
+#[derive(Debug, Clone)]
+pub struct BatchResults {
+    results: Vec<BatchItemResult>,
+}
+
+impl BatchResults {
+    /// Decodes the response to a batch message
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, RpcError> {
+        Ok(BatchResults {
+            results: deserialize(buf)?,
+        })
+    }
+
+    /// Returns the number of results
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Returns true if there are no results
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Returns the result of one request
+    pub fn get<R, E>(&self, pending: &Pending<R, E>) -> Result<R, E>
+    where
+        R: DeserializeOwned,
+        E: DeserializeOwned + From<RpcError>,
+    {
+        match self.results.get(pending.index) {
+            Some(BatchItemResult::Ok(buf)) if pending.throws => deserialize::<Result<R, E>>(buf)?,
+            Some(BatchItemResult::Ok(buf)) => Ok(deserialize(buf)?),
+            Some(BatchItemResult::Err(envelope)) => Err(RpcError::from(envelope.clone()).into()),
+            None => Err(RpcError::Invalid(format!(
+                "batch has no result {} (of {})",
+                pending.index,
+                self.results.len()
+            ))
+            .into()),
+        }
+    }
+}
+
+/// Dispatches each request of a batch to the handler, and returns the serialized results.
+/// Called by `MessageDispatch` implementations when they receive a message with the method
+/// name [`BATCH_METHOD`]. Batches may not be nested.
+pub async fn dispatch_batch<D>(
+    handler: &D,
+    ctx: &context::Context<'_>,
+    arg: &[u8],
+) -> Result<Message<'static>, RpcError>
+where
+    D: MessageDispatch + Sync + ?Sized,
+{
+    let items: Vec<BatchItem> = deserialize(arg)?;
+    let mut results = Vec::with_capacity(items.len());
+    for item in items.iter() {
+        let result = if item.method == BATCH_METHOD {
+            Err(RpcError::Invalid("nested batch".into()))
+        } else {
+            handler
+                .dispatch(
+                    ctx,
+                    Message {
+                        method: &item.method,
+                        arg: Cow::Borrowed(&item.arg),
+                    },
+                )
+                .await
+        };
+        results.push(match result {
+            Ok(resp) => BatchItemResult::Ok(resp.arg.into_owned()),
+            Err(e) => BatchItemResult::Err(e.to_envelope()),
+        });
+    }
+    Ok(Message {
+        method: BATCH_METHOD,
+        arg: Cow::Owned(serialize(&results)?),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{client::ClientConfig, mock::LoopbackTransport, Transport};
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+
+    struct Doubler {}
+
+    #[async_trait]
+    impl MessageDispatch for Doubler {
+        async fn dispatch(
+            &self,
+            ctx: &context::Context<'_>,
+            message: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            match message.method {
+                BATCH_METHOD => dispatch_batch(self, ctx, message.arg.as_ref()).await,
+                "Double" => {
+                    let n: u32 = deserialize(message.arg.as_ref())?;
+                    Ok(Message {
+                        method: "Double",
+                        arg: Cow::Owned(serialize(&(n * 2))?),
+                    })
+                }
+                _ => Err(RpcError::MethodNotHandled(message.method.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn per_item_results() {
+        let transport = LoopbackTransport::new(Doubler {});
+        let mut batch = Batch::new();
+        let a: Pending<u32> = batch.push("Double", serialize(&2u32).unwrap());
+        let b: Pending<u32> = batch.push("Triple", serialize(&2u32).unwrap());
+        let c: Pending<u32> = batch.push("Double", serialize(&5u32).unwrap());
+        let results = block_on(transport.send_batch(
+            &context::Context::default(),
+            &ClientConfig::target("x"),
+            &batch,
+        ))
+        .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results.get(&a).unwrap(), 4);
+        assert!(matches!(
+            results.get(&b),
+            Err(RpcError::MethodNotHandled(_))
+        ));
+        assert_eq!(results.get(&c).unwrap(), 10);
+    }
+}
@@ -0,0 +1,404 @@
+//! AssemblyScript language code generation
+//!
+//! Generated code uses `@wapc/as-guest` for host calls and handler registration,
+//! and `@wapc/as-msgpack` for serialization. Values are encoded the same way as
+//! the Rust output (messages as arrays of fields, declared errors as `Result` enums),
+//! so AssemblyScript actors can call, and be called by, actors and providers in Rust.
+//!
+use crate::{
+    codegen::{get, get_attributes, param, CodegenError},
+    render::{ident_to_string, Renderer},
+    strings::{to_camel_case, to_pascal_case, to_snake_case, unquote},
+};
+use frodobuf_schema::model::Schema;
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, JsonValue, Output, RenderContext, RenderError,
+};
+use std::{fs, path::Path};
+
+/// primitive types: (AssemblyScript type, msgpack method suffix, default value)
+fn primitive(name: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    let prim = match name {
+        "Uint8" => ("u8", "UInt8", "0"),
+        "Uint32" => ("u32", "UInt32", "0"),
+        "Uint64" => ("u64", "UInt64", "0"),
+        "Int8" => ("i8", "Int8", "0"),
+        "Int32" => ("i32", "Int32", "0"),
+        "Int64" => ("i64", "Int64", "0"),
+        "Bool" => ("bool", "Bool", "false"),
+        "Float32" => ("f32", "Float32", "0"),
+        "Float64" => ("f64", "Float64", "0"),
+        "String" => ("string", "String", "\"\""),
+        "Datetime" => ("string", "String", "\"\""), // RFC-3339, same as rust
+        _ => return None,
+    };
+    Some(prim)
+}
+
+/// Compound field types
+enum Compound<'v> {
+    Bytes,
+    Object(String),
+    Array(&'v JsonValue),
+    Map(&'v JsonValue, &'v JsonValue),
+}
+
+fn compound(type_val: &JsonValue) -> Result<Compound<'_>, String> {
+    match type_val {
+        JsonValue::String(s) if s == "Bytes" => Ok(Compound::Bytes),
+        JsonValue::Object(map) => {
+            let (k, v) = map
+                .iter()
+                .next()
+                .ok_or_else(|| "empty type object".to_string())?;
+            match k.as_str() {
+                "ObjectOrEnum" => Ok(Compound::Object(to_pascal_case(
+                    &ident_to_string(v).map_err(|e| e.to_string())?,
+                ))),
+                "Array" => Ok(Compound::Array(v)),
+                "Map" => match v.as_array().map(|parts| parts.as_slice()) {
+                    Some([key, val]) => Ok(Compound::Map(key, val)),
+                    _ => Err(
+                        "invalid map: expecting two subtypes: map<key_type,value_type>".to_string(),
+                    ),
+                },
+                _ => Err(format!("unexpected Object type {}", k)),
+            }
+        }
+        _ => Err(format!("expecting typename, found {:?}", type_val)),
+    }
+}
+
+/// AssemblyScript type for the field type
+fn field_type_to_as_type(type_val: &JsonValue) -> Result<String, String> {
+    if let Some((as_type, _, _)) = type_val.as_str().and_then(primitive) {
+        return Ok(as_type.to_string());
+    }
+    let as_type = match compound(type_val)? {
+        Compound::Bytes => "ArrayBuffer".to_string(),
+        Compound::Object(name) => name,
+        Compound::Array(item) => format!("Array<{}>", field_type_to_as_type(item)?),
+        Compound::Map(key, val) => format!(
+            "Map<{}, {}>",
+            field_type_to_as_type(key)?,
+            field_type_to_as_type(val)?
+        ),
+    };
+    Ok(as_type)
+}
+
+/// Initial value of a field of the type
+fn default_value(type_val: &JsonValue) -> Result<String, String> {
+    if let Some((_, _, default)) = type_val.as_str().and_then(primitive) {
+        return Ok(default.to_string());
+    }
+    let value = match compound(type_val)? {
+        Compound::Bytes => "new ArrayBuffer(0)".to_string(),
+        Compound::Object(name) => format!("new {}()", name),
+        _ => format!("new {}()", field_type_to_as_type(type_val)?),
+    };
+    Ok(value)
+}
+
+/// Expression that writes `value` to `encoder`.
+/// `Bytes` are written as msgpack bin for message fields, which rust serializes
+/// with serde_bytes, and as an array of u8 everywhere else.
+fn encode_expr(type_val: &JsonValue, value: &str, field: bool) -> Result<String, String> {
+    if let Some((_, method, _)) = type_val.as_str().and_then(primitive) {
+        return Ok(format!("encoder.write{}({})", method, value));
+    }
+    let expr = match compound(type_val)? {
+        Compound::Bytes if field => format!("encoder.writeByteArray({})", value),
+        Compound::Bytes => format!("writeBytesArray(encoder, {})", value),
+        Compound::Object(_) => format!("{}.encode(encoder)", value),
+        Compound::Array(item) => format!(
+            "encoder.writeArray<{}>({}, (encoder: Writer, item: {}): void => {{ {}; }})",
+            field_type_to_as_type(item)?,
+            value,
+            field_type_to_as_type(item)?,
+            encode_expr(item, "item", false)?
+        ),
+        Compound::Map(key, val) => format!(
+            "encoder.writeMap<{}, {}>({}, (encoder: Writer, key: {}): void => {{ {}; }}, \
+             (encoder: Writer, value: {}): void => {{ {}; }})",
+            field_type_to_as_type(key)?,
+            field_type_to_as_type(val)?,
+            value,
+            field_type_to_as_type(key)?,
+            encode_expr(key, "key", false)?,
+            field_type_to_as_type(val)?,
+            encode_expr(val, "value", false)?
+        ),
+    };
+    Ok(expr)
+}
+
+/// Expression that reads a value of the type from `decoder`
+fn decode_expr(type_val: &JsonValue, field: bool) -> Result<String, String> {
+    if let Some((_, method, _)) = type_val.as_str().and_then(primitive) {
+        return Ok(format!("decoder.read{}()", method));
+    }
+    let expr = match compound(type_val)? {
+        Compound::Bytes if field => "decoder.readByteArray()".to_string(),
+        Compound::Bytes => "readBytesArray(decoder)".to_string(),
+        Compound::Object(name) => format!("{}.decode(decoder)", name),
+        Compound::Array(item) => format!(
+            "decoder.readArray<{}>((decoder: Decoder): {} => {{ return {}; }})",
+            field_type_to_as_type(item)?,
+            field_type_to_as_type(item)?,
+            decode_expr(item, false)?
+        ),
+        Compound::Map(key, val) => format!(
+            "decoder.readMap<{}, {}>((decoder: Decoder): {} => {{ return {}; }}, \
+             (decoder: Decoder): {} => {{ return {}; }})",
+            field_type_to_as_type(key)?,
+            field_type_to_as_type(val)?,
+            field_type_to_as_type(key)?,
+            decode_expr(key, false)?,
+            field_type_to_as_type(val)?,
+            decode_expr(val, false)?
+        ),
+    };
+    Ok(expr)
+}
+
+/// generate AssemblyScript code dependent on schema
+pub fn codegen_schema_assemblyscript(
+    r: &mut Renderer,
+    schema: &Schema,
+    output_dir: &Path,
+) -> Result<(), CodegenError> {
+    let module_name = to_snake_case(&schema.namespace.name);
+    let service_file = output_dir.join(format!("{}.ts", &module_name));
+    let mut out = fs::File::create(&service_file)?;
+    r.render("as-service", &mut out)?;
+    Ok(())
+}
+
+/// Helper functions - "macros" used within templates
+pub fn add_helpers(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    // "to-type" converts a data type to an AssemblyScript type
+    hb.register_helper(
+        "to-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let as_type = field_type_to_as_type(param(h, 0)?).map_err(RenderError::new)?;
+                out.write(&as_type)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "default-value" outputs the initial value of a field of the type
+    hb.register_helper(
+        "default-value",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let value = default_value(param(h, 0)?).map_err(RenderError::new)?;
+                out.write(&value)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "encode" outputs an expression that writes a value to `encoder`:
+    // {{ encode input_type "value" }}
+    hb.register_helper(
+        "encode",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let value = param(h, 1)?
+                    .as_str()
+                    .ok_or_else(|| RenderError::new("value must be a string"))?;
+                let expr = encode_expr(param(h, 0)?, value, false).map_err(RenderError::new)?;
+                out.write(&expr)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "encode-field" outputs an expression that writes a message field to `encoder`:
+    // {{ encode-field this }}
+    hb.register_helper(
+        "encode-field",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let field = param(h, 0)?;
+                let value = format!(
+                    "this.{}",
+                    to_camel_case(&ident_to_string(get(field, "name")?)?)
+                );
+                let expr =
+                    encode_expr(get(field, "typ")?, &value, true).map_err(RenderError::new)?;
+                out.write(&expr)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "decode" outputs an expression that reads a value from `decoder`:
+    // {{ decode input_type }}
+    hb.register_helper(
+        "decode",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let expr = decode_expr(param(h, 0)?, false).map_err(RenderError::new)?;
+                out.write(&expr)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "decode-field" outputs an expression that reads a message field from `decoder`:
+    // {{ decode-field this }}
+    hb.register_helper(
+        "decode-field",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let expr =
+                    decode_expr(get(param(h, 0)?, "typ")?, true).map_err(RenderError::new)?;
+                out.write(&expr)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "docs" outputs documentation comments, followed by a newline and the indentation
+    // in the second parameter, so it can be placed before a declaration:
+    // {{ docs this "  " }}{{ name }}: string;
+    hb.register_helper(
+        "docs",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let indent = h.param(1).and_then(|p| p.value().as_str()).unwrap_or("");
+                let lines = get_attributes(param(h, 0)?)?
+                    .iter()
+                    .filter(|a| a.key.name.as_str() == "doc")
+                    .flat_map(|a| a.values.iter())
+                    .map(|(_, v)| unquote(&v.to_string()).to_string())
+                    .collect::<Vec<String>>();
+                if !lines.is_empty() {
+                    out.write("/**\n")?;
+                    for line in lines.iter() {
+                        out.write(&format!("{} * {}\n", indent, line))?;
+                    }
+                    out.write(&format!("{} */\n{}", indent, indent))?;
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    Ok(())
+}
+
+/// Add AssemblyScript code generation templates
+pub fn add_templates(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    let templates: Vec<(&str, &str)> = vec![
+        (
+            "as-package-json",
+            include_str!("../../templates/assemblyscript/package.json.hbs"),
+        ),
+        (
+            "as-index",
+            include_str!("../../templates/assemblyscript/index.ts.hbs"),
+        ),
+        (
+            "as-service",
+            include_str!("../../templates/assemblyscript/service.ts.hbs"),
+        ),
+    ];
+    for t in templates.iter() {
+        hb.register_template_string(t.0, t.1)?;
+    }
+    Ok(())
+}
+
+/// Parameters needed for generating a set of files for a new AssemblyScript project
+pub struct CreateProject<'cp> {
+    /// path to idl input file
+    pub input: &'cp Path,
+
+    /// override package name from file
+    pub package: &'cp str,
+
+    /// output directory.
+    pub output: &'cp Path,
+}
+
+/// Generate the full set of AssemblyScript files for a project.
+/// Unlike rust projects, which generate code from the idl in build.rs,
+/// the module for the schema is generated here, and regenerated with `midl update`.
+pub fn create_project<'cp>(
+    r: &mut Renderer,
+    schema: &Schema,
+    arg: CreateProject<'cp>,
+) -> Result<(), CodegenError> {
+    let project_dir = arg.output.join("assemblyscript");
+    let src_dir = project_dir.join("assembly");
+    fs::create_dir_all(&src_dir)?;
+
+    r.set("package", arg.package);
+    r.set(
+        "idl-source",
+        format!("../{}", &arg.input.display()).as_str(),
+    );
+    r.set("module-name", to_snake_case(&schema.namespace.name));
+
+    let mut out = fs::File::create(project_dir.join("package.json"))?;
+    r.render("as-package-json", &mut out)?;
+
+    let mut out = fs::File::create(src_dir.join("index.ts"))?;
+    r.render("as-index", &mut out)?;
+
+    r.codegen_for_schema(schema, &src_dir)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{codegen::check_golden, render::OutputLanguage};
+
+    #[test]
+    fn golden() {
+        check_golden(
+            OutputLanguage::AssemblyScript,
+            "as-service",
+            "assemblyscript/store.ts",
+        );
+    }
+}
@@ -0,0 +1,771 @@
+//! C language code generation
+//!
+//! For each package, a header is generated with a struct for each message,
+//! a C enum for each enumeration, and functions to encode, decode, and free messages.
+//! Serialization uses the msgpack writer and reader in `frodobuf.h`, which is generated
+//! alongside, with declarations of the wapc host functions for actors built for wasm32.
+//! Messages are encoded the same way as the Rust output.
+//!
+use crate::{
+    codegen::{get, get_attributes, param, CodegenError, Lines},
+    render::{ident_to_string, Renderer},
+    strings::{to_pascal_case, to_snake_case, unquote},
+};
+use frodobuf_schema::model::Schema;
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, JsonValue, Output, RenderContext,
+    RenderError, ScopedJson,
+};
+use std::{fs, path::Path};
+
+/// indentation of generated code
+const INDENT: &str = "    ";
+
+/// primitive types: (C type, msgpack reader suffix, msgpack writer suffix)
+fn primitive(name: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    let prim = match name {
+        "Uint8" => ("uint8_t", "u8", "uint"),
+        "Uint32" => ("uint32_t", "u32", "uint"),
+        "Uint64" => ("uint64_t", "u64", "uint"),
+        "Int8" => ("int8_t", "i8", "int"),
+        "Int32" => ("int32_t", "i32", "int"),
+        "Int64" => ("int64_t", "i64", "int"),
+        "Bool" => ("bool", "bool", "bool"),
+        "Float32" => ("float", "f32", "f32"),
+        "Float64" => ("double", "f64", "f64"),
+        "String" => ("char *", "str", "str"),
+        "Datetime" => ("char *", "str", "str"), // RFC-3339, same as rust
+        _ => return None,
+    };
+    Some(prim)
+}
+
+/// Names of generated declarations, which are prefixed with the package name,
+/// since C doesn't have namespaces
+struct Names {
+    package: String,
+    enums: Vec<String>,
+}
+
+impl Names {
+    /// Names for the schema being rendered
+    fn from_context(ctx: &Context) -> Result<Names, RenderError> {
+        let schema = get(ctx.data(), "schema")?;
+        let package = get(get(schema, "namespace")?, "name")?
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let enums = get(schema, "enums")?
+            .as_array()
+            .map(|enums| {
+                enums
+                    .iter()
+                    .filter_map(|e| e.get("name").and_then(|n| n.as_str()))
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Names { package, enums })
+    }
+
+    /// type name of a message or enum: `StoreItem`
+    fn type_name(&self, name: &str) -> String {
+        format!("{}{}", to_pascal_case(&self.package), to_pascal_case(name))
+    }
+
+    /// prefix of the functions for a message: `store_item`
+    fn fn_prefix(&self, name: &str) -> String {
+        format!("{}_{}", to_snake_case(&self.package), to_snake_case(name))
+    }
+
+    fn is_enum(&self, name: &str) -> bool {
+        self.enums.iter().any(|e| e == name)
+    }
+}
+
+/// Compound field types
+enum Compound<'v> {
+    Bytes,
+    Message(String),
+    Enum(String),
+    Array(&'v JsonValue),
+    Map(&'v JsonValue, &'v JsonValue),
+}
+
+fn compound<'v>(names: &Names, type_val: &'v JsonValue) -> Result<Compound<'v>, String> {
+    match type_val {
+        JsonValue::String(s) if s == "Bytes" => Ok(Compound::Bytes),
+        JsonValue::Object(map) => {
+            let (k, v) = map
+                .iter()
+                .next()
+                .ok_or_else(|| "empty type object".to_string())?;
+            match k.as_str() {
+                "ObjectOrEnum" => {
+                    let name = ident_to_string(v).map_err(|e| e.to_string())?;
+                    if names.is_enum(&name) {
+                        Ok(Compound::Enum(name))
+                    } else {
+                        Ok(Compound::Message(name))
+                    }
+                }
+                "Array" => Ok(Compound::Array(v)),
+                "Map" => match v.as_array().map(|parts| parts.as_slice()) {
+                    Some([key, val]) => Ok(Compound::Map(key, val)),
+                    _ => Err(
+                        "invalid map: expecting two subtypes: map<key_type,value_type>".to_string(),
+                    ),
+                },
+                _ => Err(format!("unexpected Object type {}", k)),
+            }
+        }
+        _ => Err(format!("expecting typename, found {:?}", type_val)),
+    }
+}
+
+/// declaration of `name` with type `c_type`, keeping pointer stars next to the name
+fn declare(c_type: &str, name: &str) -> String {
+    if c_type.ends_with('*') {
+        format!("{}{}", c_type, name)
+    } else {
+        format!("{} {}", c_type, name)
+    }
+}
+
+/// C type for the field type. Arrays and maps are anonymous structs,
+/// with their items allocated separately.
+fn field_type_to_c_type(names: &Names, type_val: &JsonValue) -> Result<String, String> {
+    if let Some((c_type, _, _)) = type_val.as_str().and_then(primitive) {
+        return Ok(c_type.to_string());
+    }
+    let c_type = match compound(names, type_val)? {
+        Compound::Bytes => "frodobuf_bytes".to_string(),
+        Compound::Message(name) | Compound::Enum(name) => names.type_name(&name),
+        Compound::Array(item) => format!(
+            "struct {{ {}; uint32_t len; }}",
+            declare(&field_type_to_c_type(names, item)?, "*items")
+        ),
+        Compound::Map(key, val) => format!(
+            "struct {{ {}; {}; uint32_t len; }}",
+            declare(&field_type_to_c_type(names, key)?, "*keys"),
+            declare(&field_type_to_c_type(names, val)?, "*values")
+        ),
+    };
+    Ok(c_type)
+}
+
+/// Statements that write `value` to writer `w`.
+/// `Bytes` are written as msgpack bin for message fields, which rust serializes
+/// with serde_bytes, and as an array of u8 everywhere else.
+fn encode_lines(
+    names: &Names,
+    type_val: &JsonValue,
+    value: &str,
+    field: bool,
+    level: usize,
+) -> Result<Lines, String> {
+    let mut lines = Lines::default();
+    if let Some((_, _, method)) = type_val.as_str().and_then(primitive) {
+        lines.push(0, format!("frodobuf_write_{}(w, {});", method, value));
+        return Ok(lines);
+    }
+    let i = format!("i{}", level);
+    match compound(names, type_val)? {
+        Compound::Bytes if field => lines.push(0, format!("frodobuf_write_bin(w, {});", value)),
+        Compound::Bytes => lines.push(0, format!("frodobuf_write_bytes_array(w, {});", value)),
+        Compound::Message(name) => lines.push(
+            0,
+            format!("{}_encode(w, &{});", names.fn_prefix(&name), value),
+        ),
+        Compound::Enum(_) => lines.push(0, format!("frodobuf_write_int(w, (int32_t){});", value)),
+        Compound::Array(item) => {
+            lines.push(0, format!("frodobuf_write_array_size(w, {}.len);", value));
+            lines.push(
+                0,
+                format!(
+                    "for (uint32_t {i} = 0; {i} < {}.len; {i}++) {{",
+                    value,
+                    i = i
+                ),
+            );
+            let item_value = format!("{}.items[{}]", value, i);
+            lines.append(1, encode_lines(names, item, &item_value, false, level + 1)?);
+            lines.push(0, "}");
+        }
+        Compound::Map(key, val) => {
+            lines.push(0, format!("frodobuf_write_map_size(w, {}.len);", value));
+            lines.push(
+                0,
+                format!(
+                    "for (uint32_t {i} = 0; {i} < {}.len; {i}++) {{",
+                    value,
+                    i = i
+                ),
+            );
+            let key_value = format!("{}.keys[{}]", value, i);
+            lines.append(1, encode_lines(names, key, &key_value, false, level + 1)?);
+            let val_value = format!("{}.values[{}]", value, i);
+            lines.append(1, encode_lines(names, val, &val_value, false, level + 1)?);
+            lines.push(0, "}");
+        }
+    }
+    Ok(lines)
+}
+
+/// Statements that read a value of the type from reader `r` into `target`,
+/// returning -1 if there is an error
+fn decode_lines(
+    names: &Names,
+    type_val: &JsonValue,
+    target: &str,
+    field: bool,
+    level: usize,
+) -> Result<Lines, String> {
+    let mut lines = Lines::default();
+    let check = |lines: &mut Lines, call: String| {
+        lines.push(0, format!("if ({}) {{", call));
+        lines.push(1, "return -1;");
+        lines.push(0, "}");
+    };
+    if let Some((_, method, _)) = type_val.as_str().and_then(primitive) {
+        check(
+            &mut lines,
+            format!("frodobuf_read_{}(r, &{})", method, target),
+        );
+        return Ok(lines);
+    }
+    let i = format!("i{}", level);
+    match compound(names, type_val)? {
+        Compound::Bytes if field => {
+            check(&mut lines, format!("frodobuf_read_bin(r, &{})", target));
+        }
+        Compound::Bytes => {
+            check(
+                &mut lines,
+                format!("frodobuf_read_bytes_array(r, &{})", target),
+            );
+        }
+        Compound::Message(name) => {
+            check(
+                &mut lines,
+                format!("{}_decode(r, &{})", names.fn_prefix(&name), target),
+            );
+        }
+        Compound::Enum(name) => {
+            let e = format!("e{}", level);
+            lines.push(0, "{");
+            lines.push(1, format!("int32_t {};", e));
+            let mut read = Lines::default();
+            check(&mut read, format!("frodobuf_read_i32(r, &{})", e));
+            lines.append(1, read);
+            lines.push(
+                1,
+                format!("{} = ({}){};", target, names.type_name(&name), e),
+            );
+            lines.push(0, "}");
+        }
+        Compound::Array(item) => {
+            check(
+                &mut lines,
+                format!("frodobuf_read_array_size(r, &{}.len)", target),
+            );
+            lines.push(
+                0,
+                format!(
+                    "{t}.items = frodobuf_calloc({t}.len, sizeof(*{t}.items));",
+                    t = target
+                ),
+            );
+            lines.push(0, format!("if ({t}.len && !{t}.items) {{", t = target));
+            lines.push(1, format!("{}.len = 0;", target));
+            lines.push(1, "return -1;");
+            lines.push(0, "}");
+            lines.push(
+                0,
+                format!(
+                    "for (uint32_t {i} = 0; {i} < {}.len; {i}++) {{",
+                    target,
+                    i = i
+                ),
+            );
+            let item_target = format!("{}.items[{}]", target, i);
+            lines.append(
+                1,
+                decode_lines(names, item, &item_target, false, level + 1)?,
+            );
+            lines.push(0, "}");
+        }
+        Compound::Map(key, val) => {
+            check(
+                &mut lines,
+                format!("frodobuf_read_map_size(r, &{}.len)", target),
+            );
+            for part in &["keys", "values"] {
+                lines.push(
+                    0,
+                    format!(
+                        "{t}.{p} = frodobuf_calloc({t}.len, sizeof(*{t}.{p}));",
+                        t = target,
+                        p = part
+                    ),
+                );
+            }
+            lines.push(
+                0,
+                format!("if ({t}.len && (!{t}.keys || !{t}.values)) {{", t = target),
+            );
+            lines.push(1, format!("{}.len = 0;", target));
+            lines.push(1, "return -1;");
+            lines.push(0, "}");
+            lines.push(
+                0,
+                format!(
+                    "for (uint32_t {i} = 0; {i} < {}.len; {i}++) {{",
+                    target,
+                    i = i
+                ),
+            );
+            let key_target = format!("{}.keys[{}]", target, i);
+            lines.append(1, decode_lines(names, key, &key_target, false, level + 1)?);
+            let val_target = format!("{}.values[{}]", target, i);
+            lines.append(1, decode_lines(names, val, &val_target, false, level + 1)?);
+            lines.push(0, "}");
+        }
+    }
+    Ok(lines)
+}
+
+/// Statements that release memory allocated when `value` was decoded
+fn free_lines(
+    names: &Names,
+    type_val: &JsonValue,
+    value: &str,
+    level: usize,
+) -> Result<Lines, String> {
+    let mut lines = Lines::default();
+    if let Some((c_type, _, _)) = type_val.as_str().and_then(primitive) {
+        if c_type == "char *" {
+            lines.push(0, format!("free({});", value));
+        }
+        return Ok(lines);
+    }
+    let i = format!("i{}", level);
+    match compound(names, type_val)? {
+        Compound::Bytes => lines.push(0, format!("free({}.data);", value)),
+        Compound::Message(name) => {
+            lines.push(0, format!("{}_free(&{});", names.fn_prefix(&name), value))
+        }
+        Compound::Enum(_) => {}
+        Compound::Array(item) => {
+            let item_lines =
+                free_lines(names, item, &format!("{}.items[{}]", value, i), level + 1)?;
+            if !item_lines.is_empty() {
+                lines.push(
+                    0,
+                    format!(
+                        "for (uint32_t {i} = 0; {i} < {}.len; {i}++) {{",
+                        value,
+                        i = i
+                    ),
+                );
+                lines.append(1, item_lines);
+                lines.push(0, "}");
+            }
+            lines.push(0, format!("free({}.items);", value));
+        }
+        Compound::Map(key, val) => {
+            let mut item_lines =
+                free_lines(names, key, &format!("{}.keys[{}]", value, i), level + 1)?;
+            item_lines.append(
+                0,
+                free_lines(names, val, &format!("{}.values[{}]", value, i), level + 1)?,
+            );
+            if !item_lines.is_empty() {
+                lines.push(
+                    0,
+                    format!(
+                        "for (uint32_t {i} = 0; {i} < {}.len; {i}++) {{",
+                        value,
+                        i = i
+                    ),
+                );
+                lines.append(1, item_lines);
+                lines.push(0, "}");
+            }
+            lines.push(0, format!("free({}.keys);", value));
+            lines.push(0, format!("free({}.values);", value));
+        }
+    }
+    Ok(lines)
+}
+
+/// Messages sorted so that each message is declared after the messages it contains,
+/// since C needs the complete type of a struct member. Arrays and maps hold pointers,
+/// so their item types don't need to be declared first.
+fn declaration_order(messages: &[JsonValue]) -> Vec<JsonValue> {
+    let name = |m: &JsonValue| m.get("name").and_then(|n| n.as_str()).map(String::from);
+    let contains = |m: &JsonValue| -> Vec<String> {
+        m.get("fields")
+            .and_then(|f| f.as_array())
+            .map(|fields| {
+                fields
+                    .iter()
+                    .filter_map(|f| f.get("typ")?.get("ObjectOrEnum"))
+                    .filter_map(|t| ident_to_string(t).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let mut remaining: Vec<&JsonValue> = messages.iter().collect();
+    let mut sorted: Vec<JsonValue> = Vec::with_capacity(messages.len());
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|m| {
+            contains(m).iter().all(|dep| {
+                !remaining
+                    .iter()
+                    .any(|other| name(other).as_deref() == Some(dep.as_str()))
+                    || name(m).as_deref() == Some(dep.as_str())
+            })
+        });
+        // a cycle can't be declared in C; keep the original order for the rest
+        let next = ready.unwrap_or(0);
+        sorted.push(remaining.remove(next).clone());
+    }
+    sorted
+}
+
+/// "declaration-order" returns the messages in the parameter in the order they must be declared:
+/// {{#each (declaration-order schema.messages)}}
+struct DeclarationOrder;
+
+impl HelperDef for DeclarationOrder {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let messages = param(h, 0)?
+            .as_array()
+            .ok_or_else(|| RenderError::new("expecting an array of messages"))?;
+        Ok(Some(ScopedJson::Derived(JsonValue::Array(
+            declaration_order(messages),
+        ))))
+    }
+}
+
+/// returns the indentation parameter, a number of levels
+fn indent_param(h: &Helper, n: usize) -> Result<usize, RenderError> {
+    param(h, n)?
+        .as_u64()
+        .map(|n| n as usize)
+        .ok_or_else(|| RenderError::new(format!("parameter {} must be a number", n)))
+}
+
+/// name of a message field in the struct
+fn field_name(field: &JsonValue) -> Result<String, RenderError> {
+    Ok(to_snake_case(&ident_to_string(get(field, "name")?)?))
+}
+
+/// generate C code dependent on schema
+pub fn codegen_schema_c(
+    r: &mut Renderer,
+    schema: &Schema,
+    output_dir: &Path,
+) -> Result<(), CodegenError> {
+    let module_name = to_snake_case(&schema.namespace.name);
+    let mut out = fs::File::create(output_dir.join(format!("{}.h", &module_name)))?;
+    r.render("c-header", &mut out)?;
+    let mut out = fs::File::create(output_dir.join("frodobuf.h"))?;
+    r.render("c-runtime", &mut out)?;
+    Ok(())
+}
+
+/// Helper functions - "macros" used within templates
+pub fn add_helpers(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    hb.register_helper("declaration-order", Box::new(DeclarationOrder));
+
+    // "type-name" outputs the C name of a message or enum, prefixed with the package name
+    hb.register_helper(
+        "type-name",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let names = Names::from_context(ctx)?;
+                out.write(&names.type_name(&ident_to_string(param(h, 0)?)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "fn-prefix" outputs the prefix of the functions for a message: `store_item`
+    hb.register_helper(
+        "fn-prefix",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let names = Names::from_context(ctx)?;
+                out.write(&names.fn_prefix(&ident_to_string(param(h, 0)?)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "const-name" outputs the name of a constant, in upper case with the package name
+    // and the other parameters: {{ const-name ../name name }} => STORE_STATUS_ACTIVE
+    hb.register_helper(
+        "const-name",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let names = Names::from_context(ctx)?;
+                let mut parts = vec![to_snake_case(&names.package)];
+                for p in h.params().iter() {
+                    parts.push(to_snake_case(&ident_to_string(p.value())?));
+                }
+                out.write(&parts.join("_").to_uppercase())?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "field-decl" outputs the declaration of a message field, without the semicolon
+    hb.register_helper(
+        "field-decl",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let names = Names::from_context(ctx)?;
+                let field = param(h, 0)?;
+                let c_type =
+                    field_type_to_c_type(&names, get(field, "typ")?).map_err(RenderError::new)?;
+                out.write(&declare(&c_type, &field_name(field)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "encode-field" outputs statements that write a field of message `o` to writer `w`:
+    // {{ encode-field this 1 }}
+    hb.register_helper(
+        "encode-field",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let names = Names::from_context(ctx)?;
+                let field = param(h, 0)?;
+                let value = format!("o->{}", field_name(field)?);
+                let lines = encode_lines(&names, get(field, "typ")?, &value, true, 0)
+                    .map_err(RenderError::new)?;
+                out.write(&lines.join(INDENT, indent_param(h, 1)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "decode-field" outputs statements that read a field of message `o` from reader `r`:
+    // {{ decode-field this 3 }}
+    hb.register_helper(
+        "decode-field",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let names = Names::from_context(ctx)?;
+                let field = param(h, 0)?;
+                let target = format!("o->{}", field_name(field)?);
+                let lines = decode_lines(&names, get(field, "typ")?, &target, true, 0)
+                    .map_err(RenderError::new)?;
+                out.write(&lines.join(INDENT, indent_param(h, 1)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "free-fields" outputs statements that free the memory of the fields of message `o`:
+    // {{ free-fields fields 1 }}
+    hb.register_helper(
+        "free-fields",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let names = Names::from_context(ctx)?;
+                let mut lines = Lines::default();
+                for field in param(h, 0)?.as_array().into_iter().flatten() {
+                    let value = format!("o->{}", field_name(field)?);
+                    lines.append(
+                        0,
+                        free_lines(&names, get(field, "typ")?, &value, 0)
+                            .map_err(RenderError::new)?,
+                    );
+                }
+                if lines.is_empty() {
+                    // nothing was allocated
+                    lines.push(0, "(void)o;");
+                }
+                out.write(&lines.join(INDENT, indent_param(h, 1)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "to-c-type" outputs the C type of a data type
+    hb.register_helper(
+        "to-c-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let names = Names::from_context(ctx)?;
+                let c_type =
+                    field_type_to_c_type(&names, param(h, 0)?).map_err(RenderError::new)?;
+                out.write(&c_type)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "docs" outputs documentation comments, followed by a newline and the indentation
+    // in the second parameter, so it can be placed before a declaration:
+    // {{ docs this 1 }}char *key;
+    hb.register_helper(
+        "docs",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let indent = INDENT.repeat(indent_param(h, 1).unwrap_or(0));
+                for doc in get_attributes(param(h, 0)?)?
+                    .iter()
+                    .filter(|a| a.key.name.as_str() == "doc")
+                    .flat_map(|a| a.values.iter())
+                {
+                    out.write(&format!(
+                        "/** {} */\n{}",
+                        unquote(&doc.1.to_string()),
+                        indent
+                    ))?;
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    Ok(())
+}
+
+/// Add C code generation templates
+pub fn add_templates(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    let templates: Vec<(&str, &str)> = vec![
+        ("c-header", include_str!("../../templates/c/header.h.hbs")),
+        (
+            "c-runtime",
+            include_str!("../../templates/c/frodobuf.h.hbs"),
+        ),
+        ("c-makefile", include_str!("../../templates/c/Makefile.hbs")),
+    ];
+    for t in templates.iter() {
+        hb.register_template_string(t.0, t.1)?;
+    }
+    Ok(())
+}
+
+/// Parameters needed for generating a set of files for a new C project
+pub struct CreateProject<'cp> {
+    /// path to idl input file
+    pub input: &'cp Path,
+
+    /// output directory.
+    pub output: &'cp Path,
+}
+
+/// Generate the headers for a C project, and a Makefile with a target for regenerating them
+pub fn create_project<'cp>(
+    r: &mut Renderer,
+    schema: &Schema,
+    arg: CreateProject<'cp>,
+) -> Result<(), CodegenError> {
+    let project_dir = arg.output.join("c");
+    fs::create_dir_all(&project_dir)?;
+
+    r.set(
+        "idl-source",
+        format!("../{}", &arg.input.display()).as_str(),
+    );
+    let mut out = fs::File::create(project_dir.join("Makefile"))?;
+    r.render("c-makefile", &mut out)?;
+
+    r.codegen_for_schema(schema, &project_dir)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        codegen::check_golden,
+        render::{OutputLanguage, RenderConfig, Renderer},
+    };
+
+    #[test]
+    fn golden() {
+        check_golden(OutputLanguage::C, "c-header", "c/store.h");
+    }
+
+    #[test]
+    fn enum_fields() {
+        let idl = r#"package sensor;
+            enum Level { LOW = 0; HIGH = 1; }
+            message Reading { Level level; [Level] history; }"#;
+        let schema = midl_parser::parse_string(idl).unwrap().schema;
+        let mut r = Renderer::init(&RenderConfig {
+            language: OutputLanguage::C,
+            ..Default::default()
+        })
+        .unwrap();
+        r.set("schema", serde_json::to_value(&schema).unwrap());
+        let mut out = Vec::new();
+        r.render("c-header", &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("    SENSOR_LEVEL_HIGH = 1,\n"));
+        assert!(out.contains("    SensorLevel level;\n"));
+        assert!(out.contains("frodobuf_write_int(w, (int32_t)o->level);"));
+        assert!(out.contains("o->level = (SensorLevel)e0;"));
+        assert!(out.contains("o->history.items[i0] = (SensorLevel)e1;"));
+        // enums are not allocated
+        assert!(out.contains("    free(o->history.items);\n}"));
+    }
+}
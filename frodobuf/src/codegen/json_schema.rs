@@ -0,0 +1,501 @@
+//! Export of schemas as JSON Schema (draft 2020-12) and OpenAPI 3.1
+//!
+//! The schemas describe the JSON encoding used by the HTTP gateway and the JSON clients:
+//! fields have their declared names, enums are integers, `bytes` are arrays of numbers,
+//! and `datetime` is an RFC-3339 string. Fields are required unless declared `optional`.
+//! `@doc` annotations become descriptions, `@default(value = ..)` a default, and the values of
+//! `@validate` become validation keywords, so that
+//! `@validate(min_length = 1, max_length = 64, pattern = "^[a-z]+$") string key;`
+//! adds `minLength`, `maxLength` and `pattern` to the schema of the field.
+//!
+use crate::strings::{to_pascal_case, unquote};
+use frodobuf_schema::model::{
+    Attribute, Constant, Enumeration, Field, FieldType, Message, Method, Schema, Service,
+    ATTRIBUTE_ID_DOC,
+};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Dialect of the exported JSON Schema documents
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Version of the exported OpenAPI documents
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+/// Names of the values of `@validate`, with their JSON Schema keywords
+const VALIDATION_KEYWORDS: &[(&str, &str)] = &[
+    ("minimum", "minimum"),
+    ("maximum", "maximum"),
+    ("exclusive_minimum", "exclusiveMinimum"),
+    ("exclusive_maximum", "exclusiveMaximum"),
+    ("multiple_of", "multipleOf"),
+    ("min_length", "minLength"),
+    ("max_length", "maxLength"),
+    ("pattern", "pattern"),
+    ("format", "format"),
+    ("min_items", "minItems"),
+    ("max_items", "maxItems"),
+    ("unique_items", "uniqueItems"),
+    ("min_properties", "minProperties"),
+    ("max_properties", "maxProperties"),
+];
+
+/// Returns a JSON Schema document for each message, with the name `<Message>.schema.json`.
+/// Each document is self-contained: messages and enums it uses are in its `$defs`.
+pub fn export_json_schema(schema: &Schema) -> Vec<(String, Value)> {
+    let export = JsonSchemaExport::new(schema, "#/$defs/");
+    let mut documents = Vec::new();
+    for m in export.messages.values() {
+        let mut doc = Map::new();
+        doc.insert("$schema".into(), JSON_SCHEMA_DIALECT.into());
+        doc.insert("title".into(), m.name.name.clone().into());
+        if let Value::Object(body) = export.message(m) {
+            doc.extend(body);
+        }
+        // the message is in its own $defs only if it refers to itself
+        let mut used = BTreeSet::new();
+        export.collect_used(&m.name.name, &mut used);
+        if !used.is_empty() {
+            doc.insert("$defs".into(), export.definitions(&used).into());
+        }
+        documents.push((format!("{}.schema.json", &m.name.name), Value::Object(doc)));
+    }
+    documents
+}
+
+/// Returns an OpenAPI document for the services of the schema.
+/// Each method that the HTTP gateway serves (methods that don't stream) is an operation,
+/// at the path and with the HTTP method from `@http`, or `POST /{package}/{Service}/{method}`.
+/// Methods with declared errors have a `422` response with the error, and all methods
+/// have a default response with the error envelope.
+pub fn export_openapi(schema: &Schema) -> Value {
+    let export = JsonSchemaExport::new(schema, "#/components/schemas/");
+    let mut paths = Map::new();
+    for service in schema.services.iter() {
+        for method in service.methods.iter() {
+            if method.client_streaming || method.server_streaming {
+                continue;
+            }
+            let (http_method, path) = http_route(schema, service, method);
+            let item = paths
+                .entry(path)
+                .or_insert_with(|| Value::Object(Map::new()));
+            item[http_method.to_lowercase()] = export.operation(service, method);
+        }
+    }
+    let mut schemas = export.definitions(&export.names());
+    schemas.insert(
+        "ErrorEnvelope".into(),
+        json!({
+            "type": "object",
+            "description": "Error returned by the gateway and by services, for errors that aren't declared",
+            "properties": {
+                "code": { "type": "integer", "format": "uint32", "minimum": 0, "description": "Numeric error code" },
+                "message": { "type": "string", "description": "Error message" },
+                "details": bytes_schema(),
+            },
+            "required": ["code"],
+        }),
+    );
+    let mut info = Map::new();
+    info.insert("title".into(), schema.namespace.display().into());
+    info.insert("version".into(), "1.0.0".into());
+    if let Some(description) = description(&schema.attributes) {
+        info.insert("description".into(), description.into());
+    }
+    let mut tags = Vec::new();
+    for service in schema.services.iter() {
+        let mut tag = Map::new();
+        tag.insert("name".into(), service.name.name.clone().into());
+        if let Some(description) = description(&service.attributes) {
+            tag.insert("description".into(), description.into());
+        }
+        tags.push(Value::Object(tag));
+    }
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "jsonSchemaDialect": JSON_SCHEMA_DIALECT,
+        "info": info,
+        "tags": tags,
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+/// HTTP method and path of the gateway route for a method
+fn http_route(schema: &Schema, service: &Service, method: &Method) -> (String, String) {
+    let http = method.attributes.iter().find(|a| a.key.name == "http");
+    let value = |name: &str| {
+        http.and_then(|a| a.get(name))
+            .map(|v| unquote(&v.to_string()).to_string())
+            .filter(|v| !v.is_empty())
+    };
+    (
+        value("method").unwrap_or_else(|| "POST".to_string()),
+        value("path").unwrap_or_else(|| {
+            format!(
+                "/{}/{}/{}",
+                &schema.namespace.name, &service.name.name, &method.name
+            )
+        }),
+    )
+}
+
+struct JsonSchemaExport {
+    /// prefix of references to definitions
+    ref_prefix: &'static str,
+    /// messages by name, including nested messages
+    messages: BTreeMap<String, Message>,
+    /// enums by name, including nested enums
+    enums: BTreeMap<String, Enumeration>,
+}
+
+impl JsonSchemaExport {
+    fn new(schema: &Schema, ref_prefix: &'static str) -> Self {
+        let mut export = JsonSchemaExport {
+            ref_prefix,
+            messages: BTreeMap::new(),
+            enums: BTreeMap::new(),
+        };
+        export.add(&schema.messages, &schema.enums);
+        export
+    }
+
+    fn add(&mut self, messages: &[Message], enums: &[Enumeration]) {
+        for e in enums.iter() {
+            self.enums.insert(e.name.clone(), e.clone());
+        }
+        for m in messages.iter() {
+            self.messages.insert(m.name.name.clone(), m.clone());
+            self.add(&m.messages, &m.enums);
+        }
+    }
+
+    /// names of all messages and enums
+    fn names(&self) -> BTreeSet<String> {
+        self.messages
+            .keys()
+            .chain(self.enums.keys())
+            .cloned()
+            .collect()
+    }
+
+    /// schemas of the messages and enums, by name
+    fn definitions(&self, names: &BTreeSet<String>) -> Map<String, Value> {
+        let mut defs = Map::new();
+        for name in names.iter() {
+            if let Some(m) = self.messages.get(name) {
+                defs.insert(name.clone(), self.message(m));
+            } else if let Some(e) = self.enums.get(name) {
+                defs.insert(name.clone(), enumeration(e));
+            }
+        }
+        defs
+    }
+
+    /// adds the names of messages and enums used by the message, directly or indirectly
+    fn collect_used(&self, name: &str, used: &mut BTreeSet<String>) {
+        if let Some(m) = self.messages.get(name) {
+            for f in m.fields.iter() {
+                for t in referenced(&f.typ) {
+                    if used.insert(t.clone()) {
+                        self.collect_used(&t, used);
+                    }
+                }
+            }
+        }
+    }
+
+    fn message(&self, m: &Message) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for f in m.fields.iter() {
+            properties.insert(f.name.clone(), self.field(f));
+            if !f.optional {
+                required.push(Value::from(f.name.clone()));
+            }
+        }
+        let mut obj = Map::new();
+        obj.insert("type".into(), "object".into());
+        if let Some(description) = description(&m.attributes) {
+            obj.insert("description".into(), description.into());
+        }
+        obj.insert("properties".into(), properties.into());
+        if !required.is_empty() {
+            obj.insert("required".into(), required.into());
+        }
+        Value::Object(obj)
+    }
+
+    fn field(&self, f: &Field) -> Value {
+        let mut schema = self.field_type(&f.typ);
+        if let Some(description) = description(&f.attributes) {
+            schema["description"] = description.into();
+        }
+        if let Some(default) = f.default_value() {
+            schema["default"] = constant(&default);
+        }
+        for attrib in f.attributes.iter().filter(|a| a.key.name == "validate") {
+            for (name, value) in attrib.iter() {
+                if let Some((_, keyword)) = VALIDATION_KEYWORDS.iter().find(|(n, _)| n == name) {
+                    schema[*keyword] = constant(value);
+                }
+            }
+        }
+        schema
+    }
+
+    fn field_type(&self, typ: &FieldType) -> Value {
+        match typ {
+            FieldType::Int8 => {
+                json!({ "type": "integer", "format": "int8", "minimum": i8::MIN, "maximum": i8::MAX })
+            }
+            FieldType::Int32 => {
+                json!({ "type": "integer", "format": "int32", "minimum": i32::MIN, "maximum": i32::MAX })
+            }
+            FieldType::Int64 => json!({ "type": "integer", "format": "int64" }),
+            FieldType::Uint8 => {
+                json!({ "type": "integer", "format": "uint8", "minimum": 0, "maximum": u8::MAX })
+            }
+            FieldType::Uint32 => {
+                json!({ "type": "integer", "format": "uint32", "minimum": 0, "maximum": u32::MAX })
+            }
+            FieldType::Uint64 => json!({ "type": "integer", "format": "uint64", "minimum": 0 }),
+            FieldType::Bool => json!({ "type": "boolean" }),
+            FieldType::String => json!({ "type": "string" }),
+            FieldType::Bytes => bytes_schema(),
+            FieldType::Float32 => json!({ "type": "number", "format": "float" }),
+            FieldType::Float64 => json!({ "type": "number", "format": "double" }),
+            FieldType::Datetime => json!({ "type": "string", "format": "date-time" }),
+            FieldType::Array(item) => json!({ "type": "array", "items": self.field_type(item) }),
+            FieldType::Map(kv) => {
+                let mut map =
+                    json!({ "type": "object", "additionalProperties": self.field_type(&kv.1) });
+                if kv.0.is_integer() {
+                    // JSON object keys are strings, so integer keys are written as decimal strings
+                    map["propertyNames"] = json!({ "pattern": "^-?[0-9]+$" });
+                }
+                map
+            }
+            FieldType::ObjectOrEnum(ident) => {
+                json!({ "$ref": format!("{}{}", self.ref_prefix, &ident.name) })
+            }
+        }
+    }
+
+    fn operation(&self, service: &Service, method: &Method) -> Value {
+        let mut op = Map::new();
+        op.insert(
+            "operationId".into(),
+            format!("{}_{}", &service.name.name, &method.name).into(),
+        );
+        op.insert("tags".into(), json!([&service.name.name]));
+        if let Some(description) = description(&method.attributes) {
+            op.insert("description".into(), description.into());
+        }
+        if let Some(input) = method.input_type.as_ref() {
+            op.insert(
+                "requestBody".into(),
+                json!({
+                    "required": true,
+                    "content": { "application/json": { "schema": self.field_type(input) } },
+                }),
+            );
+        }
+        let result = match method.output_type.as_ref() {
+            Some(output) => self.field_type(output),
+            None => json!({ "type": "null" }),
+        };
+        let mut responses = Map::new();
+        responses.insert(
+            "200".into(),
+            json!({
+                "description": "Result of the method",
+                "content": { "application/json": { "schema": result } },
+            }),
+        );
+        if !method.errors.is_empty() {
+            // declared errors are serialized as an object with the error type name as the key
+            let variants = method
+                .errors
+                .iter()
+                .map(|e| {
+                    let name = to_pascal_case(&e.name);
+                    json!({
+                        "type": "object",
+                        "properties": { &name: { "$ref": format!("{}{}", self.ref_prefix, &e.name) } },
+                        "required": [&name],
+                        "additionalProperties": false,
+                    })
+                })
+                .collect::<Vec<_>>();
+            responses.insert(
+                "422".into(),
+                json!({
+                    "description": "Error declared by the method",
+                    "content": { "application/json": { "schema": { "oneOf": variants } } },
+                }),
+            );
+        }
+        responses.insert(
+            "default".into(),
+            json!({
+                "description": "Error",
+                "content": { "application/json": { "schema": { "$ref": format!("{}ErrorEnvelope", self.ref_prefix) } } },
+            }),
+        );
+        op.insert("responses".into(), responses.into());
+        Value::Object(op)
+    }
+}
+
+fn enumeration(e: &Enumeration) -> Value {
+    let values = e.values.iter().map(|v| v.number).collect::<Vec<_>>();
+    let names = e.values.iter().map(|v| v.name.as_str()).collect::<Vec<_>>();
+    let mut obj = Map::new();
+    obj.insert("type".into(), "integer".into());
+    if let Some(description) = description(&e.attributes) {
+        obj.insert("description".into(), description.into());
+    }
+    obj.insert("enum".into(), values.into());
+    // names of the values, in the same order, for generators and documentation
+    obj.insert("x-enum-varnames".into(), names.into());
+    Value::Object(obj)
+}
+
+/// schema of `bytes`, which the JSON encodings write as an array of numbers
+fn bytes_schema() -> Value {
+    json!({ "type": "array", "items": { "type": "integer", "minimum": 0, "maximum": 255 } })
+}
+
+/// names of the messages and enums used by a type
+fn referenced(typ: &FieldType) -> Vec<String> {
+    match typ {
+        FieldType::ObjectOrEnum(ident) => vec![ident.name.clone()],
+        FieldType::Array(item) => referenced(item),
+        FieldType::Map(kv) => referenced(&kv.1),
+        _ => Vec::new(),
+    }
+}
+
+/// joins the `@doc` lines into a description
+fn description(attributes: &[Attribute]) -> Option<String> {
+    let lines = attributes
+        .iter()
+        .filter(|a| a.key.name == ATTRIBUTE_ID_DOC)
+        .flat_map(|a| a.values.iter())
+        .map(|(_, v)| unquote(&v.to_string()).to_string())
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// JSON value of a constant
+fn constant(value: &Constant) -> Value {
+    match value {
+        Constant::U64(v) => (*v).into(),
+        Constant::I64(v) => (*v).into(),
+        Constant::F64(v) => (*v).into(),
+        Constant::Bool(v) => (*v).into(),
+        Constant::Ident(v) => v.display().into(),
+        Constant::String(v) => unquote(v).into(),
+        Constant::Bytes(v) => v.clone().into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codegen::compare_golden;
+
+    fn store() -> Schema {
+        let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        let idl = std::fs::read_to_string(testdata.join("store.midl")).unwrap();
+        midl_parser::parse_string(&idl).unwrap().schema
+    }
+
+    #[test]
+    fn golden() {
+        for (name, doc) in export_json_schema(&store()).iter() {
+            let out = serde_json::to_string_pretty(doc).unwrap() + "\n";
+            compare_golden(&out, &format!("jsonschema/{}", name));
+        }
+        let out = serde_json::to_string_pretty(&export_openapi(&store())).unwrap() + "\n";
+        compare_golden(&out, "openapi/store.json");
+    }
+
+    #[test]
+    fn fields() {
+        let idl = r#"
+            package shop;
+            message Node {
+                @doc("Name of the node")
+                @validate(min_length = 1, max_length = 64, pattern = "^[a-z]+$", bogus = 3)
+                string name;
+                optional uint32 weight;
+                [Node] children;
+                map<int32, Kind> kinds;
+            }
+            enum Kind { A = 0; B = 2; }
+            message Leaf { string id; }
+        "#;
+        let schema = midl_parser::parse_string(idl).unwrap().schema;
+        let docs = export_json_schema(&schema);
+        assert_eq!(
+            docs.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(),
+            vec!["Leaf.schema.json", "Node.schema.json"]
+        );
+        // Leaf uses nothing else, and Node refers to itself and Kind
+        assert!(docs[0].1.get("$defs").is_none());
+        let node = &docs[1].1;
+        assert_eq!(
+            node["$defs"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["Kind", "Node"]
+        );
+        assert_eq!(node["required"], json!(["name", "children", "kinds"]));
+        let name = &node["properties"]["name"];
+        assert_eq!(name["description"], "Name of the node");
+        assert_eq!(name["minLength"], 1);
+        assert_eq!(name["pattern"], "^[a-z]+$");
+        assert!(name.get("bogus").is_none());
+        assert_eq!(
+            node["properties"]["children"]["items"]["$ref"],
+            "#/$defs/Node"
+        );
+        assert_eq!(
+            node["properties"]["kinds"]["propertyNames"]["pattern"],
+            "^-?[0-9]+$"
+        );
+        assert_eq!(node["$defs"]["Kind"]["enum"], json!([0, 2]));
+    }
+
+    #[test]
+    fn routes() {
+        let api = export_openapi(&store());
+        let paths = api["paths"].as_object().unwrap();
+        // streaming methods aren't served by the gateway
+        assert!(!paths.contains_key("/store/Store/watch"));
+        let get = &paths["/store/Store/get"]["post"];
+        assert_eq!(get["operationId"], "Store_get");
+        assert_eq!(
+            get["responses"]["422"]["content"]["application/json"]["schema"]["oneOf"][1]
+                ["required"],
+            json!(["Denied"])
+        );
+        let put = &paths["/store/Store/put"]["post"];
+        assert_eq!(
+            put["responses"]["200"]["content"]["application/json"]["schema"]["type"],
+            "null"
+        );
+        assert!(paths["/store/Store/list"]["post"]
+            .get("requestBody")
+            .is_none());
+    }
+}
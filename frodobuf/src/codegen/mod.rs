@@ -1,7 +1,17 @@
+pub mod assemblyscript;
+pub mod c;
+pub mod json_schema;
+pub mod plugin;
+pub mod proto;
+pub mod python;
 pub mod rust;
 /// utility for running 'rustfmt'
 #[cfg(not(target_arch = "wasm32"))]
 pub mod rustfmt;
+pub mod tinygo;
+pub mod typescript;
+use frodobuf_schema::model::Attribute;
+use handlebars::{Helper, JsonValue, RenderError};
 use thiserror::Error as ThisError;
 
 /// Errors from the code generator
@@ -28,6 +38,40 @@ pub enum CodegenError {
     Other(String),
 }
 
+/// Lines of generated code, each with its depth of indentation
+#[derive(Default)]
+pub(crate) struct Lines(Vec<(usize, String)>);
+
+impl Lines {
+    pub(crate) fn push<S: Into<String>>(&mut self, depth: usize, line: S) {
+        self.0.push((depth, line.into()));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn append(&mut self, depth: usize, other: Lines) {
+        for (d, line) in other.0.into_iter() {
+            self.0.push((depth + d, line));
+        }
+    }
+
+    /// Joins the lines, indenting with `unit` per level.
+    /// The first line is not indented, since the template places it.
+    pub(crate) fn join(&self, unit: &str, indent: usize) -> String {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(n, (depth, line))| {
+                let tabs = if n == 0 { 0 } else { indent + depth };
+                format!("{}{}", unit.repeat(tabs), line)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
 /// Extracts the base filename, without extension, converting to snake case
 /// ```rust
 ///   use frodobuf::codegen::module_name_from_file;
@@ -48,3 +92,62 @@ pub fn module_name_from_file(file: &std::path::Path) -> String {
     };
     crate::strings::to_snake_case(no_suffix)
 }
+
+/// return the helper param
+#[inline]
+pub(crate) fn param<'h>(h: &'h Helper, n: usize) -> Result<&'h JsonValue, RenderError> {
+    Ok(h.param(n)
+        .ok_or_else(|| RenderError::new("param not found"))?
+        .value())
+}
+
+/// get object field
+pub(crate) fn get<'h>(v: &'h JsonValue, key: &'_ str) -> Result<&'h JsonValue, RenderError> {
+    v.as_object()
+        .map(|map| map.get(key))
+        .unwrap_or_default()
+        .ok_or_else(|| RenderError::new(format!("expected object with field {}", key)))
+}
+
+/// get attributes of model object
+pub(crate) fn get_attributes(v: &JsonValue) -> Result<Vec<Attribute>, RenderError> {
+    serde_json::from_value(get(v, "attributes")?.clone())
+        .map_err(|e| RenderError::new(format!("invalid attributes: {}", e)))
+}
+
+/// Renders `testdata/store.midl` with the template, and compares the output
+/// with `testdata/golden/<golden>`. Set `FRODOBUF_BLESS=1` to update the golden file.
+#[cfg(test)]
+pub(crate) fn check_golden(language: crate::render::OutputLanguage, template: &str, golden: &str) {
+    use crate::render::{RenderConfig, Renderer};
+
+    let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+    let idl = std::fs::read_to_string(testdata.join("store.midl")).unwrap();
+    let schema = midl_parser::parse_string(&idl).unwrap().schema;
+    let mut r = Renderer::init(&RenderConfig {
+        language,
+        ..Default::default()
+    })
+    .unwrap();
+    r.set("schema", serde_json::to_value(&schema).unwrap());
+    let mut out = Vec::new();
+    r.render(template, &mut out).unwrap();
+    compare_golden(&String::from_utf8(out).unwrap(), golden);
+}
+
+/// Compares output with `testdata/golden/<golden>`, or updates it if `FRODOBUF_BLESS` is set
+#[cfg(test)]
+pub(crate) fn compare_golden(out: &str, golden: &str) {
+    let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+    let golden = testdata.join("golden").join(golden);
+    if std::env::var("FRODOBUF_BLESS").is_ok() {
+        std::fs::create_dir_all(golden.parent().unwrap()).unwrap();
+        std::fs::write(&golden, out).unwrap();
+    }
+    let expected = std::fs::read_to_string(&golden).unwrap_or_default();
+    assert!(
+        out == expected,
+        "generated code differs from {}; rerun with FRODOBUF_BLESS=1 to update it",
+        golden.display()
+    );
+}
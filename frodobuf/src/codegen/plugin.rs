@@ -0,0 +1,290 @@
+//! Protocol for code generator plugins
+//!
+//! A plugin is a program, by convention named `midl-gen-<name>`, that generates code
+//! for a schema without changes to frodobuf. `midl gen --plugin midl-gen-foo` runs it,
+//! writes a [PluginRequest] to its stdin, encoded as JSON or msgpack, and closes stdin.
+//! The plugin writes a [PluginResponse] to stdout, in the same encoding, and exits.
+//! The files in the response are written by midl, relative to the output directory.
+//!
+//! A plugin that fails should exit with a non-zero status, after writing a description
+//! of the problem to stderr, or return a response with `error` set.
+//! Plugins written in rust can use these types to decode the request and encode the response.
+
+use crate::codegen::CodegenError;
+use frodobuf_schema::model::Schema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Component, Path},
+    process::{Command, Stdio},
+};
+
+/// Version of the plugin protocol, sent in each request
+pub const PLUGIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Encoding of the request and response
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PluginFormat {
+    /// JSON text
+    Json,
+    /// msgpack, with structs encoded as maps of field names
+    Msgpack,
+}
+
+impl Default for PluginFormat {
+    fn default() -> PluginFormat {
+        PluginFormat::Json
+    }
+}
+
+impl std::str::FromStr for PluginFormat {
+    type Err = CodegenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(PluginFormat::Json),
+            "msgpack" | "mp" => Ok(PluginFormat::Msgpack),
+            _ => Err(CodegenError::InvalidParameter(format!(
+                "Unsupported plugin format {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Input to a plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginRequest {
+    /// Version of the protocol (PLUGIN_PROTOCOL_VERSION)
+    pub version: u32,
+
+    /// Path of the idl file, as given to midl
+    pub input: String,
+
+    /// Options for the plugin, from `--opt name=value`
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+
+    /// The parsed schema
+    pub schema: Schema,
+}
+
+/// A file generated by a plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginFile {
+    /// Path of the file, relative to the output directory. Must not contain '..'
+    pub name: String,
+
+    /// Contents of the file
+    pub content: String,
+}
+
+/// Output of a plugin
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PluginResponse {
+    /// Generated files
+    #[serde(default)]
+    pub files: Vec<PluginFile>,
+
+    /// Set if the plugin could not generate code for the schema.
+    /// If set, no files are written.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl PluginRequest {
+    /// Encodes the request
+    pub fn encode(&self, format: PluginFormat) -> Result<Vec<u8>, CodegenError> {
+        match format {
+            PluginFormat::Json => serde_json::to_vec(self).map_err(|e| e.to_string()),
+            PluginFormat::Msgpack => rmp_serde::to_vec_named(self).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| CodegenError::Other(format!("encoding plugin request: {}", e)))
+    }
+
+    /// Decodes a request, for use by plugins
+    pub fn decode(buf: &[u8], format: PluginFormat) -> Result<Self, CodegenError> {
+        decode(buf, format)
+            .map_err(|e| CodegenError::InvalidParameter(format!("invalid plugin request: {}", e)))
+    }
+}
+
+impl PluginResponse {
+    /// Encodes the response, for use by plugins
+    pub fn encode(&self, format: PluginFormat) -> Result<Vec<u8>, CodegenError> {
+        match format {
+            PluginFormat::Json => serde_json::to_vec(self).map_err(|e| e.to_string()),
+            PluginFormat::Msgpack => rmp_serde::to_vec_named(self).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| CodegenError::Other(format!("encoding plugin response: {}", e)))
+    }
+
+    /// Decodes a response
+    pub fn decode(buf: &[u8], format: PluginFormat) -> Result<Self, CodegenError> {
+        decode(buf, format)
+            .map_err(|e| CodegenError::Other(format!("invalid plugin response: {}", e)))
+    }
+
+    /// Writes the files to the output directory, creating subdirectories as needed.
+    /// Files are checked before any are written, so an invalid name leaves the directory unchanged.
+    pub fn write_files(&self, output_dir: &Path) -> Result<(), CodegenError> {
+        if let Some(e) = &self.error {
+            return Err(CodegenError::Other(format!("plugin error: {}", e)));
+        }
+        for file in self.files.iter() {
+            let path = Path::new(&file.name);
+            if file.name.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_)))
+            {
+                return Err(CodegenError::Other(format!(
+                    "plugin file name must be a relative path within the output directory: '{}'",
+                    file.name
+                )));
+            }
+        }
+        for file in self.files.iter() {
+            let path = output_dir.join(&file.name);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, &file.content)?;
+        }
+        Ok(())
+    }
+}
+
+fn decode<T: serde::de::DeserializeOwned>(buf: &[u8], format: PluginFormat) -> Result<T, String> {
+    match format {
+        PluginFormat::Json => serde_json::from_slice(buf).map_err(|e| e.to_string()),
+        PluginFormat::Msgpack => rmp_serde::from_slice(buf).map_err(|e| e.to_string()),
+    }
+}
+
+/// Runs a plugin with the request, and returns its response.
+/// The program is found with the PATH environment variable, unless it is a path.
+/// Arguments are passed to the program, and the plugin's stderr is passed through.
+pub fn run_plugin(
+    program: &Path,
+    args: &[String],
+    request: &PluginRequest,
+    format: PluginFormat,
+) -> Result<PluginResponse, CodegenError> {
+    let input = request.encode(format)?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| {
+            CodegenError::Other(format!(
+                "failed to start plugin '{}': {}",
+                program.display(),
+                e
+            ))
+        })?;
+    // write on another thread, so a plugin that writes before reading all its input can't block
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output().map_err(|e| {
+        CodegenError::Other(format!(
+            "failed waiting for plugin '{}': {}",
+            program.display(),
+            e
+        ))
+    })?;
+    if !output.status.success() {
+        return Err(CodegenError::Other(format!(
+            "plugin '{}' exited with error {}",
+            program.display(),
+            output.status
+        )));
+    }
+    // the plugin may exit without reading its input, so a broken pipe is not an error
+    if let Ok(Err(e)) = writer.join() {
+        if e.kind() != std::io::ErrorKind::BrokenPipe {
+            return Err(CodegenError::Io(e));
+        }
+    }
+    PluginResponse::decode(&output.stdout, format)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request() -> PluginRequest {
+        let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        let idl = std::fs::read_to_string(testdata.join("store.midl")).unwrap();
+        let mut options = BTreeMap::new();
+        options.insert("derive".to_string(), "Hash".to_string());
+        PluginRequest {
+            version: PLUGIN_PROTOCOL_VERSION,
+            input: "store.midl".to_string(),
+            options,
+            schema: midl_parser::parse_string(&idl).unwrap().schema,
+        }
+    }
+
+    #[test]
+    fn request_round_trip() {
+        let req = request();
+        for format in [PluginFormat::Json, PluginFormat::Msgpack].iter() {
+            let decoded = PluginRequest::decode(&req.encode(*format).unwrap(), *format).unwrap();
+            assert_eq!(decoded.options, req.options);
+            assert_eq!(
+                serde_json::to_value(&decoded.schema).unwrap(),
+                serde_json::to_value(&req.schema).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn write_files_checks_names() {
+        let dir = std::env::temp_dir().join(format!("frodobuf-plugin-{}", std::process::id()));
+        let file = |name: &str| PluginFile {
+            name: name.to_string(),
+            content: format!("// {}", name),
+        };
+        let resp = PluginResponse {
+            files: vec![file("store.foo"), file("gen/types.foo")],
+            error: None,
+        };
+        resp.write_files(&dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("gen/types.foo")).unwrap(),
+            "// gen/types.foo"
+        );
+
+        for name in ["../up.foo", "/abs.foo", "gen/../../x", ""].iter() {
+            let resp = PluginResponse {
+                files: vec![file("ok.foo"), file(name)],
+                error: None,
+            };
+            assert!(resp.write_files(&dir).is_err(), "{}", name);
+            assert!(!dir.join("ok.foo").exists());
+        }
+        let resp = PluginResponse {
+            files: vec![file("ok.foo")],
+            error: Some("unsupported".to_string()),
+        };
+        assert!(resp.write_files(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_shell_plugin() {
+        // a plugin that ignores its input and returns one file
+        let args = vec![
+            "-c".to_string(),
+            r#"cat > /dev/null; echo '{"files":[{"name":"a.txt","content":"hi"}]}'"#.to_string(),
+        ];
+        let resp = run_plugin(Path::new("sh"), &args, &request(), PluginFormat::Json).unwrap();
+        assert_eq!(resp.files.len(), 1);
+        assert_eq!(resp.files[0].content, "hi");
+
+        let args = vec!["-c".to_string(), "exit 3".to_string()];
+        assert!(run_plugin(Path::new("sh"), &args, &request(), PluginFormat::Json).is_err());
+    }
+}
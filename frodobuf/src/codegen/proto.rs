@@ -0,0 +1,649 @@
+//! Export of schemas as protobuf (proto3) files
+//!
+//! MIDL types that protobuf doesn't have are mapped to the nearest protobuf type:
+//! `int8` and `uint8` become `int32` and `uint32`, `datetime` is a `string`, and arrays
+//! are `repeated` fields. Where protobuf needs a message, the export adds a wrapper message:
+//! for rpc parameters and return values that aren't messages (`GetRequest` with a field `value`),
+//! for arrays and maps nested in arrays or map values (`Int32List`, `StringInt32Map`),
+//! and for maps with keys that protobuf doesn't allow (`repeated BytesInt32Entry`).
+//! Methods without a parameter or return value use `google.protobuf.Empty`.
+//! `@doc` annotations become comments, and options (`option` or `@option`) that protobuf
+//! defines for the declaration, or custom options in parentheses, become `option` lines.
+//! Other options are written as comments, as protoc would reject them.
+//! Enum values share a namespace with the enum's siblings, so a value whose name is already
+//! used by another enum in the package or message is prefixed with its enum's name.
+//!
+use crate::{
+    codegen::Lines,
+    strings::{to_pascal_case, unquote},
+};
+use frodobuf_schema::model::{
+    Attribute, Enumeration, Field, FieldType, Message, Method, Schema, Service, ATTRIBUTE_ID_DOC,
+    ATTRIBUTE_ID_OPTION, ATTRIBUTE_UNNAMED,
+};
+use inflector::cases::screamingsnakecase::to_screaming_snake_case;
+use std::collections::BTreeSet;
+
+/// indentation of the generated file
+const INDENT: &str = "  ";
+
+/// Renders the schema as a proto3 file
+pub fn export_proto(schema: &Schema) -> String {
+    let mut names = BTreeSet::new();
+    let mut enums = BTreeSet::new();
+    collect_names(&schema.messages, &schema.enums, &mut names, &mut enums);
+    let mut export = ProtoExport {
+        names,
+        enums,
+        wrappers: Vec::new(),
+        wrapper_lines: Lines::default(),
+        uses_empty: false,
+    };
+
+    let mut body = Lines::default();
+    let mut values = BTreeSet::new();
+    for e in schema.enums.iter() {
+        body.push(0, "");
+        body.append(0, export.enumeration(e, &mut values));
+    }
+    for m in schema.messages.iter() {
+        body.push(0, "");
+        body.append(0, export.message(m));
+    }
+    for s in schema.services.iter() {
+        body.push(0, "");
+        body.append(0, export.service(s));
+    }
+
+    let mut lines = Lines::default();
+    lines.push(
+        0,
+        format!(
+            "// Generated by midl from package {}.",
+            schema.namespace.display()
+        ),
+    );
+    lines.push(0, "syntax = \"proto3\";");
+    lines.push(0, "");
+    lines.push(0, format!("package {};", schema.namespace.display()));
+    if export.uses_empty {
+        lines.push(0, "");
+        lines.push(0, "import \"google/protobuf/empty.proto\";");
+    }
+    let options = options(&schema.attributes, Scope::File);
+    if !options.is_empty() {
+        lines.push(0, "");
+        lines.append(0, options);
+    }
+    lines.append(0, body);
+    if !export.wrapper_lines.is_empty() {
+        lines.push(0, "");
+        lines.push(
+            0,
+            "// Wrapper messages for types that protobuf requires to be messages",
+        );
+        lines.append(0, export.wrapper_lines);
+    }
+    lines.join(INDENT, 0) + "\n"
+}
+
+/// Collects the names of messages and enums, including nested declarations
+fn collect_names(
+    messages: &[Message],
+    enums: &[Enumeration],
+    names: &mut BTreeSet<String>,
+    enum_names: &mut BTreeSet<String>,
+) {
+    for e in enums.iter() {
+        names.insert(e.name.clone());
+        enum_names.insert(e.name.clone());
+    }
+    for m in messages.iter() {
+        names.insert(m.name.name.clone());
+        collect_names(&m.messages, &m.enums, names, enum_names);
+    }
+}
+
+struct ProtoExport {
+    /// names of declared and generated messages and enums
+    names: BTreeSet<String>,
+    /// names of declared enums
+    enums: BTreeSet<String>,
+    /// generated wrapper messages for container types: the type, kind of wrapper, and name
+    wrappers: Vec<(FieldType, String, String)>,
+    /// declarations of generated wrapper messages
+    wrapper_lines: Lines,
+    /// whether google.protobuf.Empty is used
+    uses_empty: bool,
+}
+
+impl ProtoExport {
+    /// Declaration of an enum. `scope` has the names of the values of the other enums
+    /// in the same package or message, which the enum's values must not reuse.
+    fn enumeration(&mut self, e: &Enumeration, scope: &mut BTreeSet<String>) -> Lines {
+        let prefix = to_screaming_snake_case(&e.name);
+        let mut lines = comments(&e.attributes);
+        lines.push(0, format!("enum {} {{", &e.name));
+        lines.append(1, options(&e.attributes, Scope::Enum));
+        let numbers = e.values.iter().map(|v| v.number).collect::<BTreeSet<i32>>();
+        if numbers.len() < e.values.len() && !has_option(&e.attributes, "allow_alias") {
+            lines.push(1, "option allow_alias = true;");
+        }
+        let zero_first = e
+            .values
+            .iter()
+            .filter(|v| v.number == 0)
+            .chain(e.values.iter().filter(|v| v.number != 0))
+            .collect::<Vec<_>>();
+        // declared values are named before the added zero value
+        let names = zero_first
+            .iter()
+            .map(|v| enum_value_name(scope, &prefix, &v.name))
+            .collect::<Vec<_>>();
+        // proto3 requires the first value to be zero
+        if !numbers.contains(&0) {
+            let unspecified = format!("{}_UNSPECIFIED", &prefix);
+            lines.push(
+                1,
+                format!("{} = 0;", enum_value_name(scope, &prefix, &unspecified)),
+            );
+        }
+        for (v, name) in zero_first.iter().zip(names.iter()) {
+            lines.append(1, comments(&v.attributes));
+            let (options, ignored) = field_options(&v.attributes, Scope::EnumValue);
+            let mut decl = format!("{} = {}{};{}", name, v.number, options, ignored);
+            if name != &v.name {
+                decl.push_str(&format!(" // renamed from {}", &v.name));
+            }
+            lines.push(1, decl);
+        }
+        lines.push(0, "}");
+        lines
+    }
+
+    fn message(&mut self, m: &Message) -> Lines {
+        let mut lines = comments(&m.attributes);
+        lines.push(0, format!("message {} {{", &m.name.name));
+        lines.append(1, options(&m.attributes, Scope::Message));
+        let mut values = BTreeSet::new();
+        for e in m.enums.iter() {
+            lines.append(1, self.enumeration(e, &mut values));
+        }
+        for nested in m.messages.iter() {
+            lines.append(1, self.message(nested));
+        }
+        for (n, f) in m.fields.iter().enumerate() {
+            let number = if f.number == 0 {
+                n as u32 + 1
+            } else {
+                f.number
+            };
+            lines.append(1, comments(&f.attributes));
+            lines.push(1, self.field(f, number));
+        }
+        lines.push(0, "}");
+        lines
+    }
+
+    /// Declaration of a message field
+    fn field(&mut self, f: &Field, number: u32) -> String {
+        let decl = match &f.typ {
+            FieldType::Array(_) | FieldType::Map(_) => self.container(&f.typ),
+            typ if f.optional => format!("optional {}", self.element(typ)),
+            typ => self.element(typ),
+        };
+        let (options, ignored) = field_options(&f.attributes, Scope::Field);
+        format!("{} {} = {}{};{}", decl, &f.name, number, options, ignored)
+    }
+
+    /// Type of a repeated or map field
+    fn container(&mut self, typ: &FieldType) -> String {
+        match typ {
+            FieldType::Array(item) => format!("repeated {}", self.element(item)),
+            FieldType::Map(kv) => match map_key(&kv.0) {
+                Some(key) => format!("map<{}, {}>", key, self.element(&kv.1)),
+                None => format!("repeated {}", self.wrapper(typ, "Entry")),
+            },
+            _ => self.element(typ),
+        }
+    }
+
+    /// Type of a single value. Arrays and maps are wrapped in messages.
+    fn element(&mut self, typ: &FieldType) -> String {
+        match typ {
+            FieldType::Array(item) => self.wrapper(item, "List"),
+            FieldType::Map(_) => self.wrapper(typ, "Map"),
+            FieldType::ObjectOrEnum(ident) => ident.display(),
+            _ => scalar(typ).to_string(),
+        }
+    }
+
+    /// Returns the name of the message wrapping a container type, adding it if it's new.
+    /// `List` wraps an array of `typ`, `Map` wraps the map `typ`, and `Entry` is an entry of it.
+    fn wrapper(&mut self, typ: &FieldType, suffix: &str) -> String {
+        if let Some((_, _, name)) = self
+            .wrappers
+            .iter()
+            .find(|(t, s, _)| t == typ && s == suffix)
+        {
+            return name.clone();
+        }
+        let name = match typ {
+            FieldType::Map(kv) => format!("{}{}{}", type_name(&kv.0), type_name(&kv.1), suffix),
+            _ => format!("{}{}", type_name(typ), suffix),
+        };
+        let name = self.unique_name(&[name]);
+        self.wrappers
+            .push((typ.clone(), suffix.to_string(), name.clone()));
+        let mut lines = Lines::default();
+        lines.push(0, format!("message {} {{", &name));
+        match (typ, suffix) {
+            (FieldType::Map(kv), "Entry") => {
+                let key = self.element(&kv.0);
+                let value = self.element(&kv.1);
+                lines.push(1, format!("{} key = 1;", key));
+                lines.push(1, format!("{} value = 2;", value));
+            }
+            (FieldType::Map(_), _) => {
+                let decl = self.container(typ);
+                lines.push(1, format!("{} entries = 1;", decl));
+            }
+            _ => {
+                let item = self.element(typ);
+                lines.push(1, format!("repeated {} values = 1;", item));
+            }
+        }
+        lines.push(0, "}");
+        self.wrapper_lines.push(0, "");
+        self.wrapper_lines.append(0, lines);
+        name
+    }
+
+    /// Returns the first candidate name that isn't declared, or the last with a numeric suffix
+    fn unique_name(&mut self, candidates: &[String]) -> String {
+        let name = candidates
+            .iter()
+            .find(|n| !self.names.contains(*n))
+            .cloned()
+            .unwrap_or_else(|| {
+                let last = candidates.last().cloned().unwrap_or_default();
+                (2..)
+                    .map(|n| format!("{}{}", &last, n))
+                    .find(|n| !self.names.contains(n))
+                    .unwrap()
+            });
+        self.names.insert(name.clone());
+        name
+    }
+
+    fn service(&mut self, s: &Service) -> Lines {
+        let mut lines = comments(&s.attributes);
+        lines.push(0, format!("service {} {{", &s.name.name));
+        lines.append(1, options(&s.attributes, Scope::Service));
+        for m in s.methods.iter() {
+            lines.append(1, self.method(&s.name.name, m));
+        }
+        lines.push(0, "}");
+        lines
+    }
+
+    fn method(&mut self, service: &str, m: &Method) -> Lines {
+        let name = to_pascal_case(&m.name);
+        let input = self.rpc_type(service, &name, "Request", m.input_type.as_ref());
+        let output = self.rpc_type(service, &name, "Response", m.output_type.as_ref());
+        let mut lines = comments(&m.attributes);
+        if !m.errors.is_empty() {
+            let errors = m.errors.iter().map(|e| e.display()).collect::<Vec<_>>();
+            lines.push(0, format!("// throws {}", errors.join(", ")));
+        }
+        let options = options(&m.attributes, Scope::Method);
+        let rpc = format!(
+            "rpc {}({}{}) returns ({}{})",
+            &name,
+            if m.client_streaming { "stream " } else { "" },
+            input,
+            if m.server_streaming { "stream " } else { "" },
+            output
+        );
+        if options.is_empty() {
+            lines.push(0, format!("{};", rpc));
+        } else {
+            lines.push(0, format!("{} {{", rpc));
+            lines.append(1, options);
+            lines.push(0, "}");
+        }
+        lines
+    }
+
+    /// Message type of an rpc parameter or return value.
+    /// Values that aren't messages are wrapped in a message named for the method.
+    fn rpc_type(
+        &mut self,
+        service: &str,
+        method: &str,
+        suffix: &str,
+        typ: Option<&FieldType>,
+    ) -> String {
+        let typ = match typ {
+            None => {
+                self.uses_empty = true;
+                return "google.protobuf.Empty".to_string();
+            }
+            Some(FieldType::ObjectOrEnum(ident)) if !self.enums.contains(&ident.name) => {
+                return ident.display();
+            }
+            Some(typ) => typ,
+        };
+        let name = self.unique_name(&[
+            format!("{}{}", method, suffix),
+            format!("{}{}{}", service, method, suffix),
+        ]);
+        let value = Field {
+            name: "value".to_string(),
+            optional: false,
+            typ: typ.clone(),
+            number: 1,
+            attributes: Vec::new(),
+        };
+        let decl = self.field(&value, 1);
+        self.wrapper_lines.push(0, "");
+        self.wrapper_lines.push(0, format!("message {} {{", &name));
+        self.wrapper_lines.push(1, decl);
+        self.wrapper_lines.push(0, "}");
+        name
+    }
+}
+
+/// protobuf type of a scalar
+fn scalar(typ: &FieldType) -> &'static str {
+    match typ {
+        FieldType::Int8 | FieldType::Int32 => "int32",
+        FieldType::Int64 => "int64",
+        FieldType::Uint8 | FieldType::Uint32 => "uint32",
+        FieldType::Uint64 => "uint64",
+        FieldType::Bool => "bool",
+        FieldType::String | FieldType::Datetime => "string",
+        FieldType::Bytes => "bytes",
+        FieldType::Float32 => "float",
+        FieldType::Float64 => "double",
+        FieldType::Map(_) | FieldType::Array(_) | FieldType::ObjectOrEnum(_) => "",
+    }
+}
+
+/// protobuf type of a map key, or None if protobuf doesn't allow the type as a key
+fn map_key(typ: &FieldType) -> Option<&'static str> {
+    match typ {
+        FieldType::Bytes | FieldType::Float32 | FieldType::Float64 => None,
+        FieldType::Map(_) | FieldType::Array(_) | FieldType::ObjectOrEnum(_) => None,
+        _ => Some(scalar(typ)),
+    }
+}
+
+/// Name of a type, used for names of wrapper messages
+fn type_name(typ: &FieldType) -> String {
+    match typ {
+        FieldType::Array(item) => format!("{}List", type_name(item)),
+        FieldType::Map(kv) => format!("{}{}Map", type_name(&kv.0), type_name(&kv.1)),
+        FieldType::ObjectOrEnum(ident) => ident.name.clone(),
+        _ => to_pascal_case(scalar(typ)),
+    }
+}
+
+/// Comments from `@doc` attributes
+fn comments(attributes: &[Attribute]) -> Lines {
+    let mut lines = Lines::default();
+    for a in attributes.iter().filter(|a| a.key == ATTRIBUTE_ID_DOC) {
+        for (_, value) in a.values.iter() {
+            for line in unquote(&value.to_string()).lines() {
+                lines.push(0, format!("// {}", line).trim_end().to_string());
+            }
+        }
+    }
+    lines
+}
+
+/// Kind of declaration that options apply to
+#[derive(Clone, Copy)]
+enum Scope {
+    File,
+    Message,
+    Field,
+    Enum,
+    EnumValue,
+    Service,
+    Method,
+}
+
+impl Scope {
+    /// Options defined by protobuf's descriptor.proto for the declaration
+    fn known(self) -> &'static [&'static str] {
+        match self {
+            Scope::File => &[
+                "java_package",
+                "java_outer_classname",
+                "java_multiple_files",
+                "java_generate_equals_and_hash",
+                "java_string_check_utf8",
+                "optimize_for",
+                "go_package",
+                "cc_generic_services",
+                "java_generic_services",
+                "py_generic_services",
+                "deprecated",
+                "cc_enable_arenas",
+                "objc_class_prefix",
+                "csharp_namespace",
+                "swift_prefix",
+                "php_class_prefix",
+                "php_namespace",
+                "php_metadata_namespace",
+                "ruby_package",
+            ],
+            Scope::Message => &[
+                "message_set_wire_format",
+                "no_standard_descriptor_accessor",
+                "deprecated",
+            ],
+            Scope::Field => &["ctype", "packed", "jstype", "lazy", "deprecated", "weak"],
+            Scope::Enum => &["allow_alias", "deprecated"],
+            Scope::EnumValue | Scope::Service => &["deprecated"],
+            Scope::Method => &["deprecated", "idempotency_level"],
+        }
+    }
+
+    /// Returns true if protoc accepts the option: a known option, or a custom option
+    /// in parentheses, which must be declared in an imported file
+    fn accepts(self, name: &str) -> bool {
+        name.starts_with('(') || self.known().contains(&name)
+    }
+}
+
+/// Option statements from option attributes. Options that protobuf doesn't define
+/// for the declaration are comments.
+fn options(attributes: &[Attribute], scope: Scope) -> Lines {
+    let mut lines = Lines::default();
+    for (name, value) in option_values(attributes) {
+        let comment = if scope.accepts(name) { "" } else { "// " };
+        lines.push(0, format!("{}option {} = {};", comment, name, value));
+    }
+    lines
+}
+
+/// Options of a field or enum value: the options protobuf defines, in brackets
+/// with a leading space, and a comment with the others, with a leading space
+fn field_options(attributes: &[Attribute], scope: Scope) -> (String, String) {
+    let (known, ignored): (Vec<_>, Vec<_>) = option_values(attributes)
+        .map(|(name, value)| (scope.accepts(name), format!("{} = {}", name, value)))
+        .partition(|(accepted, _)| *accepted);
+    let join = |options: Vec<(bool, String)>| {
+        options
+            .into_iter()
+            .map(|(_, option)| option)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let known = if known.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", join(known))
+    };
+    let ignored = if ignored.is_empty() {
+        String::new()
+    } else {
+        format!(" // [{}]", join(ignored))
+    };
+    (known, ignored)
+}
+
+fn option_values(attributes: &[Attribute]) -> impl Iterator<Item = (&String, String)> {
+    attributes
+        .iter()
+        .filter(|a| a.key == ATTRIBUTE_ID_OPTION)
+        .flat_map(|a| a.values.iter())
+        .filter(|(name, _)| name != ATTRIBUTE_UNNAMED)
+        .map(|(name, value)| (name, value.to_string()))
+}
+
+/// Returns true if the option is set
+fn has_option(attributes: &[Attribute], option: &str) -> bool {
+    option_values(attributes).any(|(name, _)| name == option)
+}
+
+/// Returns the name of an enum value, and adds it to the scope. A name that is already
+/// used in the scope is prefixed with the enum's name, and numbered if that is also used.
+fn enum_value_name(scope: &mut BTreeSet<String>, prefix: &str, name: &str) -> String {
+    let name = if !scope.contains(name) {
+        name.to_string()
+    } else {
+        let prefixed = format!("{}_{}", prefix, name);
+        if !scope.contains(&prefixed) {
+            prefixed
+        } else {
+            (2..)
+                .map(|n| format!("{}{}", &prefixed, n))
+                .find(|n| !scope.contains(n))
+                .unwrap()
+        }
+    };
+    scope.insert(name.clone());
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use super::export_proto;
+    use crate::codegen::compare_golden;
+    use frodobuf_schema::model::{Field, FieldType};
+
+    fn export(idl: &str) -> String {
+        export_proto(&midl_parser::parse_string(idl).unwrap().schema)
+    }
+
+    #[test]
+    fn golden() {
+        let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        let idl = std::fs::read_to_string(testdata.join("store.midl")).unwrap();
+        compare_golden(&export(&idl), "proto/store.proto");
+    }
+
+    #[test]
+    fn wrappers_and_options() {
+        let mut schema = midl_parser::parse_string(
+            r#"package shapes;
+            option go_package = "example.com/shapes";
+
+            enum Color {
+              RED = 1;
+              CRIMSON = 1;
+            }
+
+            message Grid {
+              [[int8]] rows;
+              map<int8, [string]> names;
+              map<string, map<string, int32>> nested;
+              Color color?;
+            }
+
+            message ColorRequest {
+              bool x;
+            }
+
+            service Paint {
+              @option(deadline = 5)
+              rpc color(Color) returns map<string, int32>;
+              rpc clear();
+            }"#,
+        )
+        .unwrap()
+        .schema;
+        // the parser only allows integer and string keys, which protobuf allows
+        schema.messages[0].fields.push(Field {
+            name: "index".to_string(),
+            optional: false,
+            typ: FieldType::Map(Box::new((
+                FieldType::Bytes,
+                FieldType::Array(Box::new(FieldType::String)),
+            ))),
+            number: 5,
+            attributes: Vec::new(),
+        });
+        let proto = export_proto(&schema);
+        for expected in [
+            "option go_package = \"example.com/shapes\";",
+            "  option allow_alias = true;\n  COLOR_UNSPECIFIED = 0;\n  RED = 1;",
+            "  repeated Int32List rows = 1;",
+            "  map<int32, StringList> names = 2;",
+            "  map<string, StringInt32Map> nested = 3;",
+            "  optional Color color = 4;",
+            "  repeated BytesStringListEntry index = 5;",
+            // protoc rejects options that protobuf doesn't define
+            "rpc Color(PaintColorRequest) returns (ColorResponse) {\n    // option deadline = 5;\n  }",
+            "rpc Clear(google.protobuf.Empty) returns (google.protobuf.Empty);",
+            "message Int32List {\n  repeated int32 values = 1;\n}",
+            "message StringList {\n  repeated string values = 1;\n}",
+            "message BytesStringListEntry {\n  bytes key = 1;\n  StringList value = 2;\n}",
+            "message StringInt32Map {\n  map<string, int32> entries = 1;\n}",
+            "message PaintColorRequest {\n  Color value = 1;\n}",
+            "message ColorResponse {\n  map<string, int32> value = 1;\n}",
+        ]
+        .iter()
+        {
+            assert!(proto.contains(expected), "missing {}\n{}", expected, proto);
+        }
+    }
+
+    #[test]
+    fn enum_value_names() {
+        let proto = export(
+            r#"package paint;
+
+            enum Color {
+              RED = 1;
+              SHADE_UNSPECIFIED = 2;
+            }
+
+            enum Shade {
+              RED = 1;
+              DARK = 2;
+            }
+
+            message Brush {
+              enum Tip {
+                RED = 1;
+              }
+              Tip tip;
+            }"#,
+        );
+        for expected in [
+            "  COLOR_UNSPECIFIED = 0;\n  RED = 1;\n  SHADE_UNSPECIFIED = 2;",
+            // the value names of enums in the package are unique
+            "  SHADE_SHADE_UNSPECIFIED = 0;\n  SHADE_RED = 1; // renamed from RED\n  DARK = 2;",
+            // enums in a message have their own scope
+            "    TIP_UNSPECIFIED = 0;\n    RED = 1;",
+        ]
+        .iter()
+        {
+            assert!(proto.contains(expected), "missing {}\n{}", expected, proto);
+        }
+    }
+}
@@ -0,0 +1,487 @@
+//! Python language code generation, for test and data scripts
+//!
+//! Generated code has a dataclass for each message, an `IntEnum` for each enum, and a
+//! client class for each service that sends requests with a pluggable send callable.
+//! Values are encoded with msgpack, binary-compatible with the Rust output, by the
+//! encoders in `frodobuf.py`, which is generated next to the module and has no dependencies.
+//!
+use crate::{
+    codegen::{get, get_attributes, param, CodegenError},
+    render::{ident_to_string, Renderer},
+    strings::{to_pascal_case, to_snake_case, unquote},
+};
+use frodobuf_schema::model::Schema;
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, JsonValue, Output, RenderContext, RenderError,
+};
+use std::{fs, path::Path};
+
+/// Python keywords, and `self`, which can't be used as field or method names
+const RESERVED: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "self", "try",
+    "while", "with", "yield",
+];
+
+/// primitive types: (Python type, wire type in `frodobuf.py`, default value)
+fn primitive(name: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    let prim = match name {
+        "Uint8" | "Uint32" | "Uint64" | "Int8" | "Int32" | "Int64" => ("int", "int", "0"),
+        "Bool" => ("bool", "bool", "False"),
+        "Float32" => ("float", "float32", "0.0"),
+        "Float64" => ("float", "float64", "0.0"),
+        "String" => ("str", "str", "\"\""),
+        "Datetime" => ("str", "str", "\"\""), // RFC-3339, same as rust
+        _ => return None,
+    };
+    Some(prim)
+}
+
+/// Compound field types
+enum Compound<'v> {
+    Bytes,
+    Message(String),
+    /// enum with the name of its first value
+    Enum(String, String),
+    Array(&'v JsonValue),
+    Map(&'v JsonValue, &'v JsonValue),
+}
+
+/// enums in the schema being rendered: (name, name of the first value)
+fn enums(ctx: &Context) -> Result<Vec<(String, String)>, RenderError> {
+    Ok(get(get(ctx.data(), "schema")?, "enums")?
+        .as_array()
+        .map(|enums| {
+            enums
+                .iter()
+                .filter_map(|e| {
+                    let name = e.get("name")?.as_str()?;
+                    let first = e.get("values")?.get(0)?.get("name")?.as_str()?;
+                    Some((name.to_string(), first.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+fn compound<'v>(
+    enums: &[(String, String)],
+    type_val: &'v JsonValue,
+) -> Result<Compound<'v>, String> {
+    match type_val {
+        JsonValue::String(s) if s == "Bytes" => Ok(Compound::Bytes),
+        JsonValue::Object(map) => {
+            let (k, v) = map
+                .iter()
+                .next()
+                .ok_or_else(|| "empty type object".to_string())?;
+            match k.as_str() {
+                "ObjectOrEnum" => {
+                    let name = ident_to_string(v).map_err(|e| e.to_string())?;
+                    match enums.iter().find(|(e, _)| e == &name) {
+                        Some((_, first)) => {
+                            Ok(Compound::Enum(to_pascal_case(&name), first.clone()))
+                        }
+                        None => Ok(Compound::Message(to_pascal_case(&name))),
+                    }
+                }
+                "Array" => Ok(Compound::Array(v)),
+                "Map" => match v.as_array().map(|parts| parts.as_slice()) {
+                    Some([key, val]) => Ok(Compound::Map(key, val)),
+                    _ => Err(
+                        "invalid map: expecting two subtypes: map<key_type,value_type>".to_string(),
+                    ),
+                },
+                _ => Err(format!("unexpected Object type {}", k)),
+            }
+        }
+        _ => Err(format!("expecting typename, found {:?}", type_val)),
+    }
+}
+
+/// Python type hint for the field type
+fn field_type_to_py_type(
+    enums: &[(String, String)],
+    type_val: &JsonValue,
+) -> Result<String, String> {
+    if let Some((py_type, _, _)) = type_val.as_str().and_then(primitive) {
+        return Ok(py_type.to_string());
+    }
+    let py_type = match compound(enums, type_val)? {
+        Compound::Bytes => "bytes".to_string(),
+        Compound::Message(name) | Compound::Enum(name, _) => name,
+        Compound::Array(item) => format!("List[{}]", field_type_to_py_type(enums, item)?),
+        Compound::Map(key, val) => format!(
+            "Dict[{}, {}]",
+            field_type_to_py_type(enums, key)?,
+            field_type_to_py_type(enums, val)?
+        ),
+    };
+    Ok(py_type)
+}
+
+/// Default value of a dataclass field of the type
+fn default_value(enums: &[(String, String)], type_val: &JsonValue) -> Result<String, String> {
+    if let Some((_, _, default)) = type_val.as_str().and_then(primitive) {
+        return Ok(default.to_string());
+    }
+    let value = match compound(enums, type_val)? {
+        Compound::Bytes => "b\"\"".to_string(),
+        // messages may be declared later in the module
+        Compound::Message(name) => format!("field(default_factory=lambda: {}())", name),
+        Compound::Enum(name, first) => format!("{}.{}", name, first),
+        Compound::Array(_) => "field(default_factory=list)".to_string(),
+        Compound::Map(_, _) => "field(default_factory=dict)".to_string(),
+    };
+    Ok(value)
+}
+
+/// Expression for the wire type of the field type, used by the encoders in `frodobuf.py`.
+/// `Bytes` are msgpack bin for message fields, which rust serializes with serde_bytes,
+/// and an array of u8 everywhere else.
+fn wire_type(
+    enums: &[(String, String)],
+    type_val: &JsonValue,
+    field: bool,
+) -> Result<String, String> {
+    if let Some((_, wire, _)) = type_val.as_str().and_then(primitive) {
+        return Ok(format!("\"{}\"", wire));
+    }
+    let wire = match compound(enums, type_val)? {
+        Compound::Bytes if field => "\"bin\"".to_string(),
+        Compound::Bytes => "\"bytes\"".to_string(),
+        Compound::Message(name) => format!("(\"message\", {})", name),
+        Compound::Enum(name, _) => format!("(\"enum\", {})", name),
+        Compound::Array(item) => format!("(\"array\", {})", wire_type(enums, item, false)?),
+        Compound::Map(key, val) => format!(
+            "(\"map\", {}, {})",
+            wire_type(enums, key, false)?,
+            wire_type(enums, val, false)?
+        ),
+    };
+    Ok(wire)
+}
+
+/// snake-case name for a field or method, with a trailing underscore if it's reserved
+fn py_name(name: &str) -> String {
+    let name = to_snake_case(name);
+    if RESERVED.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// `@doc` lines of a model object
+fn doc_lines(obj: &JsonValue) -> Result<Vec<String>, RenderError> {
+    Ok(get_attributes(obj)?
+        .iter()
+        .filter(|a| a.key.name.as_str() == "doc")
+        .flat_map(|a| a.values.iter())
+        .map(|(_, v)| unquote(&v.to_string()).to_string())
+        .collect())
+}
+
+/// returns the indentation parameter, a number of levels
+fn indent_param(h: &Helper, n: usize) -> Result<String, RenderError> {
+    param(h, n)?
+        .as_u64()
+        .map(|n| "    ".repeat(n as usize))
+        .ok_or_else(|| RenderError::new(format!("parameter {} must be a number", n)))
+}
+
+/// generate Python code dependent on schema
+pub fn codegen_schema_python(
+    r: &mut Renderer,
+    schema: &Schema,
+    output_dir: &Path,
+) -> Result<(), CodegenError> {
+    let module_name = to_snake_case(&schema.namespace.name);
+    let mut out = fs::File::create(output_dir.join(format!("{}.py", &module_name)))?;
+    r.render("py-service", &mut out)?;
+    let mut out = fs::File::create(output_dir.join("frodobuf.py"))?;
+    r.render("py-runtime", &mut out)?;
+    Ok(())
+}
+
+/// Helper functions - "macros" used within templates
+pub fn add_helpers(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    // "to-type" converts a data type to a Python type hint
+    hb.register_helper(
+        "to-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let py_type =
+                    field_type_to_py_type(&enums(ctx)?, param(h, 0)?).map_err(RenderError::new)?;
+                out.write(&py_type)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "default-value" outputs the default value of a dataclass field of the type
+    hb.register_helper(
+        "default-value",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let value = default_value(&enums(ctx)?, param(h, 0)?).map_err(RenderError::new)?;
+                out.write(&value)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "wire-type" outputs the wire type of a method parameter or result:
+    // {{ wire-type input_type }}
+    hb.register_helper(
+        "wire-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let wire =
+                    wire_type(&enums(ctx)?, param(h, 0)?, false).map_err(RenderError::new)?;
+                out.write(&wire)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "field-wire-type" outputs the wire type of a message field:
+    // {{ field-wire-type this }}
+    hb.register_helper(
+        "field-wire-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let wire = wire_type(&enums(ctx)?, get(param(h, 0)?, "typ")?, true)
+                    .map_err(RenderError::new)?;
+                out.write(&wire)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "py-name" converts a field or method name to snake case, avoiding Python keywords:
+    // {{ py-name ( ident name ) }}
+    hb.register_helper(
+        "py-name",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let name = param(h, 0)?
+                    .as_str()
+                    .ok_or_else(|| RenderError::new("name must be a string"))?;
+                out.write(&py_name(name))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "comments" outputs `#:` documentation comments, followed by a newline and the indentation,
+    // so it can be placed before a declaration:
+    // {{ comments this 1 }}{{ py-name ( ident name ) }}: str = ""
+    hb.register_helper(
+        "comments",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let indent = indent_param(h, 1)?;
+                for line in doc_lines(param(h, 0)?)?.iter() {
+                    out.write(&format!("#: {}\n{}", line, indent))?;
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    // "docstring" outputs a docstring, placed at the start of a class or function body
+    // indented by the second parameter.
+    // For methods that declare errors, the docstring names them:
+    // {{ docstring this 1 }}
+    hb.register_helper(
+        "docstring",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let obj = param(h, 0)?;
+                let indent = indent_param(h, 1)?;
+                let mut lines = doc_lines(obj)?;
+                let errors = obj
+                    .get("errors")
+                    .and_then(|e| e.as_array())
+                    .map(|errors| {
+                        errors
+                            .iter()
+                            .map(|e| Ok(to_pascal_case(&ident_to_string(e)?)))
+                            .collect::<Result<Vec<String>, RenderError>>()
+                    })
+                    .transpose()?
+                    .unwrap_or_default();
+                if let Some((last, rest)) = errors.split_last() {
+                    let names = if rest.is_empty() {
+                        last.clone()
+                    } else {
+                        format!("{} or {}", rest.join(", "), last)
+                    };
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    lines.push(format!("Raises DeclaredError with {}", names));
+                }
+                match lines.as_slice() {
+                    [] => {}
+                    [line] => out.write(&format!("\"\"\"{}\"\"\"", line))?,
+                    _ => {
+                        out.write(&format!("\"\"\"{}\n", lines[0]))?;
+                        for line in lines[1..].iter() {
+                            if line.is_empty() {
+                                out.write("\n")?;
+                            } else {
+                                out.write(&format!("{}{}\n", indent, line))?;
+                            }
+                        }
+                        out.write(&format!("{}\"\"\"", indent))?;
+                    }
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    Ok(())
+}
+
+/// Add Python code generation templates
+pub fn add_templates(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    let templates: Vec<(&str, &str)> = vec![
+        (
+            "py-pyproject",
+            include_str!("../../templates/python/pyproject.toml.hbs"),
+        ),
+        (
+            "py-init",
+            include_str!("../../templates/python/__init__.py.hbs"),
+        ),
+        (
+            "py-runtime",
+            include_str!("../../templates/python/frodobuf.py.hbs"),
+        ),
+        (
+            "py-service",
+            include_str!("../../templates/python/service.py.hbs"),
+        ),
+    ];
+    for t in templates.iter() {
+        hb.register_template_string(t.0, t.1)?;
+    }
+    Ok(())
+}
+
+/// Parameters needed for generating a set of files for a new Python project
+pub struct CreateProject<'cp> {
+    /// path to idl input file
+    pub input: &'cp Path,
+
+    /// Python distribution name
+    pub package: &'cp str,
+
+    /// output directory.
+    pub output: &'cp Path,
+}
+
+/// Generate the full set of Python files for a project.
+/// The module for the schema is generated here, and regenerated with `midl update`.
+pub fn create_project<'cp>(
+    r: &mut Renderer,
+    schema: &Schema,
+    arg: CreateProject<'cp>,
+) -> Result<(), CodegenError> {
+    let module_name = to_snake_case(&schema.namespace.name);
+    let project_dir = arg.output.join("python");
+    let src_dir = project_dir.join(&module_name);
+    fs::create_dir_all(&src_dir)?;
+
+    r.set("package", arg.package);
+    r.set(
+        "idl-source",
+        format!("../../{}", &arg.input.display()).as_str(),
+    );
+    r.set("module-name", module_name);
+
+    let mut out = fs::File::create(project_dir.join("pyproject.toml"))?;
+    r.render("py-pyproject", &mut out)?;
+
+    let mut out = fs::File::create(src_dir.join("__init__.py"))?;
+    r.render("py-init", &mut out)?;
+
+    r.codegen_for_schema(schema, &src_dir)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        codegen::check_golden,
+        render::{OutputLanguage, RenderConfig, Renderer},
+    };
+
+    #[test]
+    fn golden() {
+        check_golden(OutputLanguage::Python, "py-service", "python/store.py");
+    }
+
+    #[test]
+    fn enums_and_reserved_names() {
+        let idl = r#"package sensor;
+            enum Level { LOW = 1; HIGH = 2; }
+            message Reading { Level level; string from; }
+            service Sensor { rpc import(Reading); }"#;
+        let schema = midl_parser::parse_string(idl).unwrap().schema;
+        let mut r = Renderer::init(&RenderConfig {
+            language: OutputLanguage::Python,
+            ..Default::default()
+        })
+        .unwrap();
+        r.set("schema", serde_json::to_value(&schema).unwrap());
+        let mut out = Vec::new();
+        r.render("py-service", &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("    HIGH = 2\n"));
+        // the default is the first value, since 0 may not be defined
+        assert!(out.contains("    level: Level = Level.LOW\n"));
+        assert!(out.contains("    from_: str = \"\"\n"));
+        assert!(out.contains(r#"("level", ("enum", Level)),"#));
+        assert!(out.contains("    def import_(self, arg: Reading) -> None:\n"));
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //!
 use crate::{
-    codegen::{rustfmt, CodegenError},
+    codegen::{get, get_attributes, param, rustfmt, CodegenError},
     render::{ident_to_string, Renderer},
     strings::{to_snake_case, unquote},
 };
@@ -12,28 +12,6 @@ use handlebars::{
 };
 use std::{fs, path::Path};
 
-/// return the helper param
-#[inline]
-fn param<'h>(h: &'h Helper, n: usize) -> Result<&'h JsonValue, RenderError> {
-    Ok(h.param(n)
-        .ok_or_else(|| RenderError::new("param not found"))?
-        .value())
-}
-
-/// get object field
-fn get<'h>(v: &'h JsonValue, key: &'_ str) -> Result<&'h JsonValue, RenderError> {
-    v.as_object()
-        .map(|map| map.get(key))
-        .unwrap_or_default()
-        .ok_or_else(|| RenderError::new(format!("expected object with field {}", key)))
-}
-
-/// get attributes of model object
-fn get_attributes(v: &JsonValue) -> Result<Vec<Attribute>, RenderError> {
-    serde_json::from_value(get(v, "attributes")?.clone())
-        .map_err(|e| RenderError::new(format!("invalid attributes: {}", e)))
-}
-
 fn field_type_to_rust_type(type_val: &JsonValue) -> Result<String, String> {
     let rust_type = match type_val {
         JsonValue::String(s) => {
@@ -49,7 +27,7 @@ fn field_type_to_rust_type(type_val: &JsonValue) -> Result<String, String> {
                 "Float64" => "f64",
                 "String" => "String",
                 "Bytes" => "Vec<u8>",
-                "DateTime" => "String", // TODO: broken
+                "Datetime" => "String", // RFC-3339
                 _ => {
                     return Err(format!("unexpected string type {:?}", type_val));
                 }
@@ -179,6 +157,73 @@ pub fn add_helpers(hb: &mut Handlebars) -> Result<(), CodegenError> {
                 if name != to_snake_case(&name) {
                     out.write(&format!("#[serde(rename=\"{}\")]\n", name))?;
                 }
+                // optional fields are Option, and may be missing from serialized json
+                if field.get("optional").and_then(JsonValue::as_bool) == Some(true) {
+                    out.write("#[serde(default, skip_serializing_if = \"Option::is_none\")]\n")?;
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    // "has-streams" outputs "true" if any method of the service is streaming,
+    // for use in conditionals: {{#if (has-streams this)}}
+    hb.register_helper(
+        "has-streams",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let service = param(h, 0)?;
+                let streams = get(service, "methods")?
+                    .as_array()
+                    .map(|methods| {
+                        methods.iter().any(|m| {
+                            ["client_streaming", "server_streaming"]
+                                .iter()
+                                .any(|k| m.get(k).and_then(JsonValue::as_bool) == Some(true))
+                        })
+                    })
+                    .unwrap_or(false);
+                if streams {
+                    out.write("true")?;
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    // "auth-requirements" outputs the `@auth` requirements of a service and method,
+    // given their attribute lists, as a slice of (name, value) pairs,
+    // or nothing if neither has `@auth`: {{auth-requirements ../attributes attributes}}
+    hb.register_helper(
+        "auth-requirements",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let mut found = false;
+                let mut pairs = Vec::new();
+                for n in 0..2 {
+                    let attributes: Vec<Attribute> =
+                        serde_json::from_value(param(h, n)?.clone())
+                            .map_err(|e| RenderError::new(format!("invalid attributes: {}", e)))?;
+                    for auth in attributes.iter().filter(|a| a.key.name == "auth") {
+                        found = true;
+                        for (name, value) in auth.iter() {
+                            pairs.push(format!("({:?}, {:?})", name, unquote(&value.to_string())));
+                        }
+                    }
+                }
+                if found {
+                    out.write(&format!("&[{}]", pairs.join(", ")))?;
+                }
                 Ok(())
             },
         ),
@@ -250,6 +295,9 @@ pub struct CreateProject<'cp> {
 
     /// output directory.
     pub output: &'cp Path,
+
+    /// optional directory of user templates, used by build.rs
+    pub template_dir: Option<&'cp Path>,
 }
 
 /// Generate the full set of rust files for a project
@@ -269,6 +317,14 @@ pub fn create_project<'cp>(
         "idl-source",
         format!("../{}", &arg.input.display()).as_str(),
     );
+    if let Some(dir) = arg.template_dir {
+        let dir = if dir.is_absolute() {
+            dir.display().to_string()
+        } else {
+            format!("../{}", dir.display())
+        };
+        r.set("template-dir", dir);
+    }
 
     // generate Cargo.toml
     let cargo_out = project_dir.join("Cargo.toml");
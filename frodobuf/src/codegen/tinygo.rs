@@ -0,0 +1,442 @@
+//! TinyGo language code generation
+//!
+//! Generated code uses `github.com/wapc/wapc-guest-tinygo` for host calls and
+//! handler registration, and `github.com/wapc/tinygo-msgpack` for serialization.
+//! Values are encoded the same way as the Rust output, so TinyGo actors can call,
+//! and be called by, actors and providers in Rust.
+//!
+use crate::{
+    codegen::{get, get_attributes, param, CodegenError, Lines},
+    render::{ident_to_string, Renderer},
+    strings::{to_pascal_case, to_snake_case, unquote},
+};
+use frodobuf_schema::model::Schema;
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, JsonValue, Output, RenderContext, RenderError,
+};
+use std::{fs, path::Path};
+
+/// primitive types: (Go type, msgpack method suffix)
+fn primitive(name: &str) -> Option<(&'static str, &'static str)> {
+    let prim = match name {
+        "Uint8" => ("uint8", "Uint8"),
+        "Uint32" => ("uint32", "Uint32"),
+        "Uint64" => ("uint64", "Uint64"),
+        "Int8" => ("int8", "Int8"),
+        "Int32" => ("int32", "Int32"),
+        "Int64" => ("int64", "Int64"),
+        "Bool" => ("bool", "Bool"),
+        "Float32" => ("float32", "Float32"),
+        "Float64" => ("float64", "Float64"),
+        "String" => ("string", "String"),
+        "Datetime" => ("string", "String"), // RFC-3339, same as rust
+        _ => return None,
+    };
+    Some(prim)
+}
+
+/// Compound field types
+enum Compound<'v> {
+    Bytes,
+    Object(String),
+    Array(&'v JsonValue),
+    Map(&'v JsonValue, &'v JsonValue),
+}
+
+fn compound(type_val: &JsonValue) -> Result<Compound<'_>, String> {
+    match type_val {
+        JsonValue::String(s) if s == "Bytes" => Ok(Compound::Bytes),
+        JsonValue::Object(map) => {
+            let (k, v) = map
+                .iter()
+                .next()
+                .ok_or_else(|| "empty type object".to_string())?;
+            match k.as_str() {
+                "ObjectOrEnum" => Ok(Compound::Object(to_pascal_case(
+                    &ident_to_string(v).map_err(|e| e.to_string())?,
+                ))),
+                "Array" => Ok(Compound::Array(v)),
+                "Map" => match v.as_array().map(|parts| parts.as_slice()) {
+                    Some([key, val]) => Ok(Compound::Map(key, val)),
+                    _ => Err(
+                        "invalid map: expecting two subtypes: map<key_type,value_type>".to_string(),
+                    ),
+                },
+                _ => Err(format!("unexpected Object type {}", k)),
+            }
+        }
+        _ => Err(format!("expecting typename, found {:?}", type_val)),
+    }
+}
+
+/// Go type for the field type
+fn field_type_to_go_type(type_val: &JsonValue) -> Result<String, String> {
+    if let Some((go_type, _)) = type_val.as_str().and_then(primitive) {
+        return Ok(go_type.to_string());
+    }
+    let go_type = match compound(type_val)? {
+        Compound::Bytes => "[]byte".to_string(),
+        Compound::Object(name) => name,
+        Compound::Array(item) => format!("[]{}", field_type_to_go_type(item)?),
+        Compound::Map(key, val) => format!(
+            "map[{}]{}",
+            field_type_to_go_type(key)?,
+            field_type_to_go_type(val)?
+        ),
+    };
+    Ok(go_type)
+}
+
+/// Statements that write `value` to `encoder`.
+/// `Bytes` are written as msgpack bin for message fields, which rust serializes
+/// with serde_bytes, and as an array of uint8 everywhere else.
+/// `level` keeps loop variables of nested arrays and maps distinct.
+fn encode_lines(
+    type_val: &JsonValue,
+    value: &str,
+    field: bool,
+    level: usize,
+) -> Result<Lines, String> {
+    let mut lines = Lines::default();
+    if let Some((_, method)) = type_val.as_str().and_then(primitive) {
+        lines.push(0, format!("encoder.Write{}({})", method, value));
+        return Ok(lines);
+    }
+    match compound(type_val)? {
+        Compound::Bytes if field => lines.push(0, format!("encoder.WriteByteArray({})", value)),
+        Compound::Bytes => lines.push(0, format!("writeBytesArray(encoder, {})", value)),
+        Compound::Object(_) => lines.push(0, format!("{}.Encode(encoder)", value)),
+        Compound::Array(item) => {
+            let v = format!("v{}", level);
+            lines.push(0, format!("encoder.WriteArraySize(uint32(len({})))", value));
+            lines.push(0, format!("for _, {} := range {} {{", v, value));
+            lines.append(1, encode_lines(item, &v, false, level + 1)?);
+            lines.push(0, "}");
+        }
+        Compound::Map(key, val) => {
+            let (k, v) = (format!("k{}", level), format!("v{}", level));
+            lines.push(0, format!("encoder.WriteMapSize(uint32(len({})))", value));
+            lines.push(0, format!("for {}, {} := range {} {{", k, v, value));
+            lines.append(1, encode_lines(key, &k, false, level + 1)?);
+            lines.append(1, encode_lines(val, &v, false, level + 1)?);
+            lines.push(0, "}");
+        }
+    }
+    Ok(lines)
+}
+
+/// Statements that read a value of the type from `decoder` into `target`.
+/// `fail` is the statement that returns `err` from the enclosing function.
+fn decode_lines(
+    type_val: &JsonValue,
+    target: &str,
+    fail: &str,
+    field: bool,
+    level: usize,
+) -> Result<Lines, String> {
+    let mut lines = Lines::default();
+    let check = |lines: &mut Lines| {
+        lines.push(0, "if err != nil {");
+        lines.push(1, fail);
+        lines.push(0, "}");
+    };
+    if let Some((_, method)) = type_val.as_str().and_then(primitive) {
+        lines.push(0, format!("{}, err = decoder.Read{}()", target, method));
+        check(&mut lines);
+        return Ok(lines);
+    }
+    match compound(type_val)? {
+        Compound::Bytes if field => {
+            lines.push(0, format!("{}, err = decoder.ReadByteArray()", target));
+            check(&mut lines);
+        }
+        Compound::Bytes => {
+            lines.push(0, format!("{}, err = readBytesArray(decoder)", target));
+            check(&mut lines);
+        }
+        Compound::Object(_) => {
+            lines.push(0, format!("err = {}.Decode(decoder)", target));
+            check(&mut lines);
+        }
+        Compound::Array(item) => {
+            let (size, i) = (format!("size{}", level), format!("i{}", level));
+            lines.push(0, format!("var {} uint32", size));
+            lines.push(0, format!("{}, err = decoder.ReadArraySize()", size));
+            check(&mut lines);
+            lines.push(
+                0,
+                format!(
+                    "{} = make({}, {})",
+                    target,
+                    field_type_to_go_type(type_val)?,
+                    size
+                ),
+            );
+            lines.push(
+                0,
+                format!("for {} := uint32(0); {} < {}; {}++ {{", i, i, size, i),
+            );
+            lines.append(
+                1,
+                decode_lines(item, &format!("{}[{}]", target, i), fail, false, level + 1)?,
+            );
+            lines.push(0, "}");
+        }
+        Compound::Map(key, val) => {
+            let (size, i) = (format!("size{}", level), format!("i{}", level));
+            let (k, v) = (format!("k{}", level), format!("v{}", level));
+            lines.push(0, format!("var {} uint32", size));
+            lines.push(0, format!("{}, err = decoder.ReadMapSize()", size));
+            check(&mut lines);
+            lines.push(
+                0,
+                format!(
+                    "{} = make({}, {})",
+                    target,
+                    field_type_to_go_type(type_val)?,
+                    size
+                ),
+            );
+            lines.push(
+                0,
+                format!("for {} := uint32(0); {} < {}; {}++ {{", i, i, size, i),
+            );
+            lines.push(1, format!("var {} {}", k, field_type_to_go_type(key)?));
+            lines.append(1, decode_lines(key, &k, fail, false, level + 1)?);
+            lines.push(1, format!("var {} {}", v, field_type_to_go_type(val)?));
+            lines.append(1, decode_lines(val, &v, fail, false, level + 1)?);
+            lines.push(1, format!("{}[{}] = {}", target, k, v));
+            lines.push(0, "}");
+        }
+    }
+    Ok(lines)
+}
+
+/// returns the string parameter
+fn str_param<'h>(h: &'h Helper, n: usize) -> Result<&'h str, RenderError> {
+    param(h, n)?
+        .as_str()
+        .ok_or_else(|| RenderError::new(format!("parameter {} must be a string", n)))
+}
+
+/// returns the indentation parameter, a number of tabs
+fn indent_param(h: &Helper, n: usize) -> Result<usize, RenderError> {
+    param(h, n)?
+        .as_u64()
+        .map(|n| n as usize)
+        .ok_or_else(|| RenderError::new(format!("parameter {} must be a number", n)))
+}
+
+/// name of the Go struct field for a message field
+fn field_name(field: &JsonValue) -> Result<String, RenderError> {
+    Ok(to_pascal_case(&ident_to_string(get(field, "name")?)?))
+}
+
+/// generate TinyGo code dependent on schema
+pub fn codegen_schema_tinygo(
+    r: &mut Renderer,
+    schema: &Schema,
+    output_dir: &Path,
+) -> Result<(), CodegenError> {
+    let module_name = to_snake_case(&schema.namespace.name);
+    let service_file = output_dir.join(format!("{}.go", &module_name));
+    let mut out = fs::File::create(&service_file)?;
+    r.render("go-service", &mut out)?;
+    Ok(())
+}
+
+/// Helper functions - "macros" used within templates
+pub fn add_helpers(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    // "to-type" converts a data type to a Go type
+    hb.register_helper(
+        "to-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let go_type = field_type_to_go_type(param(h, 0)?).map_err(RenderError::new)?;
+                out.write(&go_type)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "encode" outputs statements that write a value to `encoder`,
+    // indented with the number of tabs in the third parameter:
+    // {{ encode input_type "arg" 2 }}
+    hb.register_helper(
+        "encode",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let lines = encode_lines(param(h, 0)?, str_param(h, 1)?, false, 0)
+                    .map_err(RenderError::new)?;
+                out.write(&lines.join("\t", indent_param(h, 2)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "encode-field" outputs statements that write a field of message `o` to `encoder`:
+    // {{ encode-field this 1 }}
+    hb.register_helper(
+        "encode-field",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let field = param(h, 0)?;
+                let value = format!("o.{}", field_name(field)?);
+                let lines =
+                    encode_lines(get(field, "typ")?, &value, true, 0).map_err(RenderError::new)?;
+                out.write(&lines.join("\t", indent_param(h, 1)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "decode" outputs statements that read a value from `decoder` into the target,
+    // executing the fail statement if there is an error:
+    // {{ decode output_type "result" "return result, err" 1 }}
+    hb.register_helper(
+        "decode",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let lines =
+                    decode_lines(param(h, 0)?, str_param(h, 1)?, str_param(h, 2)?, false, 0)
+                        .map_err(RenderError::new)?;
+                out.write(&lines.join("\t", indent_param(h, 3)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "decode-field" outputs statements that read a field of message `o` from `decoder`:
+    // {{ decode-field this 3 }}
+    hb.register_helper(
+        "decode-field",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let field = param(h, 0)?;
+                let target = format!("o.{}", field_name(field)?);
+                let lines = decode_lines(get(field, "typ")?, &target, "return err", true, 0)
+                    .map_err(RenderError::new)?;
+                out.write(&lines.join("\t", indent_param(h, 1)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "docs" outputs documentation comments, followed by a newline and the indentation
+    // in the second parameter, so it can be placed before a declaration:
+    // {{ docs this 1 }}{{ to-pascal-case name }} string
+    hb.register_helper(
+        "docs",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let indent = "\t".repeat(indent_param(h, 1).unwrap_or(0));
+                for doc in get_attributes(param(h, 0)?)?
+                    .iter()
+                    .filter(|a| a.key.name.as_str() == "doc")
+                    .flat_map(|a| a.values.iter())
+                {
+                    out.write(&format!("// {}\n{}", unquote(&doc.1.to_string()), indent))?;
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    Ok(())
+}
+
+/// Add TinyGo code generation templates
+pub fn add_templates(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    let templates: Vec<(&str, &str)> = vec![
+        ("go-mod", include_str!("../../templates/tinygo/go.mod.hbs")),
+        (
+            "go-generate",
+            include_str!("../../templates/tinygo/generate.go.hbs"),
+        ),
+        (
+            "go-service",
+            include_str!("../../templates/tinygo/service.go.hbs"),
+        ),
+    ];
+    for t in templates.iter() {
+        hb.register_template_string(t.0, t.1)?;
+    }
+    Ok(())
+}
+
+/// Parameters needed for generating a set of files for a new TinyGo project
+pub struct CreateProject<'cp> {
+    /// path to idl input file
+    pub input: &'cp Path,
+
+    /// Go module path
+    pub package: &'cp str,
+
+    /// output directory.
+    pub output: &'cp Path,
+}
+
+/// Generate the full set of TinyGo files for a project.
+/// The module for the schema is generated here, and regenerated with `go generate`.
+pub fn create_project<'cp>(
+    r: &mut Renderer,
+    schema: &Schema,
+    arg: CreateProject<'cp>,
+) -> Result<(), CodegenError> {
+    let project_dir = arg.output.join("tinygo");
+    fs::create_dir_all(&project_dir)?;
+
+    r.set("package", arg.package);
+    r.set(
+        "idl-source",
+        format!("../{}", &arg.input.display()).as_str(),
+    );
+
+    let mut out = fs::File::create(project_dir.join("go.mod"))?;
+    r.render("go-mod", &mut out)?;
+
+    let mut out = fs::File::create(project_dir.join("generate.go"))?;
+    r.render("go-generate", &mut out)?;
+
+    r.codegen_for_schema(schema, &project_dir)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{codegen::check_golden, render::OutputLanguage};
+
+    #[test]
+    fn golden() {
+        check_golden(OutputLanguage::TinyGo, "go-service", "tinygo/store.go");
+    }
+}
@@ -0,0 +1,355 @@
+//! TypeScript language code generation, for host-side tools such as dashboards
+//!
+//! Generated code has interfaces for messages, enums, and a client class for each service
+//! that sends requests with a pluggable transport function. Requests and responses are
+//! encoded with msgpack, binary-compatible with the Rust output, or with JSON,
+//! as accepted by the HTTP gateway in `frodobuf::http`. The encoders are in `frodobuf.ts`,
+//! which is generated next to the module and has no dependencies.
+//!
+use crate::{
+    codegen::{get, get_attributes, param, CodegenError},
+    render::{ident_to_string, Renderer},
+    strings::{to_pascal_case, to_snake_case, unquote},
+};
+use frodobuf_schema::model::Schema;
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, JsonValue, Output, RenderContext, RenderError,
+};
+use std::{fs, path::Path};
+
+/// primitive types: (TypeScript type, wire type in `frodobuf.ts`)
+fn primitive(name: &str) -> Option<(&'static str, &'static str)> {
+    let prim = match name {
+        "Uint8" | "Uint32" | "Uint64" | "Int8" | "Int32" | "Int64" => ("number", "int"),
+        "Bool" => ("boolean", "bool"),
+        "Float32" => ("number", "float32"),
+        "Float64" => ("number", "float64"),
+        "String" => ("string", "string"),
+        "Datetime" => ("string", "string"), // RFC-3339, same as rust
+        _ => return None,
+    };
+    Some(prim)
+}
+
+/// Compound field types
+enum Compound<'v> {
+    Bytes,
+    Message(String),
+    Enum(String),
+    Array(&'v JsonValue),
+    Map(&'v JsonValue, &'v JsonValue),
+}
+
+/// names of the enums in the schema being rendered
+fn enum_names(ctx: &Context) -> Result<Vec<String>, RenderError> {
+    Ok(get(get(ctx.data(), "schema")?, "enums")?
+        .as_array()
+        .map(|enums| {
+            enums
+                .iter()
+                .filter_map(|e| e.get("name").and_then(|n| n.as_str()))
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+fn compound<'v>(enums: &[String], type_val: &'v JsonValue) -> Result<Compound<'v>, String> {
+    match type_val {
+        JsonValue::String(s) if s == "Bytes" => Ok(Compound::Bytes),
+        JsonValue::Object(map) => {
+            let (k, v) = map
+                .iter()
+                .next()
+                .ok_or_else(|| "empty type object".to_string())?;
+            match k.as_str() {
+                "ObjectOrEnum" => {
+                    let name = ident_to_string(v).map_err(|e| e.to_string())?;
+                    if enums.iter().any(|e| e == &name) {
+                        Ok(Compound::Enum(to_pascal_case(&name)))
+                    } else {
+                        Ok(Compound::Message(to_pascal_case(&name)))
+                    }
+                }
+                "Array" => Ok(Compound::Array(v)),
+                "Map" => match v.as_array().map(|parts| parts.as_slice()) {
+                    Some([key, val]) => Ok(Compound::Map(key, val)),
+                    _ => Err(
+                        "invalid map: expecting two subtypes: map<key_type,value_type>".to_string(),
+                    ),
+                },
+                _ => Err(format!("unexpected Object type {}", k)),
+            }
+        }
+        _ => Err(format!("expecting typename, found {:?}", type_val)),
+    }
+}
+
+/// TypeScript type for the field type. Maps are objects, with numeric keys for integer key types.
+fn field_type_to_ts_type(enums: &[String], type_val: &JsonValue) -> Result<String, String> {
+    if let Some((ts_type, _)) = type_val.as_str().and_then(primitive) {
+        return Ok(ts_type.to_string());
+    }
+    let ts_type = match compound(enums, type_val)? {
+        Compound::Bytes => "Uint8Array".to_string(),
+        Compound::Message(name) | Compound::Enum(name) => name,
+        Compound::Array(item) => format!("{}[]", field_type_to_ts_type(enums, item)?),
+        Compound::Map(key, val) => {
+            let key_type = match key.as_str().and_then(primitive) {
+                Some(("number", _)) => "number",
+                _ => "string",
+            };
+            format!(
+                "Record<{}, {}>",
+                key_type,
+                field_type_to_ts_type(enums, val)?
+            )
+        }
+    };
+    Ok(ts_type)
+}
+
+/// Expression for the wire type of the field type, used by the encoders in `frodobuf.ts`.
+/// `Bytes` are msgpack bin for message fields, which rust serializes with serde_bytes,
+/// and an array of u8 everywhere else.
+fn wire_type(enums: &[String], type_val: &JsonValue, field: bool) -> Result<String, String> {
+    if let Some((_, wire)) = type_val.as_str().and_then(primitive) {
+        return Ok(format!("\"{}\"", wire));
+    }
+    let wire = match compound(enums, type_val)? {
+        Compound::Bytes if field => "\"bin\"".to_string(),
+        Compound::Bytes => "\"bytes\"".to_string(),
+        Compound::Message(name) => format!("types.{}", name),
+        Compound::Enum(_) => "\"int\"".to_string(),
+        Compound::Array(item) => format!("{{ array: {} }}", wire_type(enums, item, false)?),
+        Compound::Map(key, val) => format!(
+            "{{ map: [{}, {}] }}",
+            wire_type(enums, key, false)?,
+            wire_type(enums, val, false)?
+        ),
+    };
+    Ok(wire)
+}
+
+/// generate TypeScript code dependent on schema
+pub fn codegen_schema_typescript(
+    r: &mut Renderer,
+    schema: &Schema,
+    output_dir: &Path,
+) -> Result<(), CodegenError> {
+    let module_name = to_snake_case(&schema.namespace.name);
+    let mut out = fs::File::create(output_dir.join(format!("{}.ts", &module_name)))?;
+    r.render("ts-service", &mut out)?;
+    let mut out = fs::File::create(output_dir.join("frodobuf.ts"))?;
+    r.render("ts-runtime", &mut out)?;
+    Ok(())
+}
+
+/// Helper functions - "macros" used within templates
+pub fn add_helpers(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    // "to-type" converts a data type to a TypeScript type
+    hb.register_helper(
+        "to-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let ts_type = field_type_to_ts_type(&enum_names(ctx)?, param(h, 0)?)
+                    .map_err(RenderError::new)?;
+                out.write(&ts_type)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "wire-type" outputs the wire type of a method parameter or result:
+    // {{ wire-type input_type }}
+    hb.register_helper(
+        "wire-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let wire =
+                    wire_type(&enum_names(ctx)?, param(h, 0)?, false).map_err(RenderError::new)?;
+                out.write(&wire)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "field-wire-type" outputs the wire type of a message field:
+    // {{ field-wire-type this }}
+    hb.register_helper(
+        "field-wire-type",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             ctx: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let wire = wire_type(&enum_names(ctx)?, get(param(h, 0)?, "typ")?, true)
+                    .map_err(RenderError::new)?;
+                out.write(&wire)?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "docs" outputs documentation comments, followed by a newline and the indentation
+    // in the second parameter, so it can be placed before a declaration:
+    // {{ docs this "  " }}{{ name }}: string;
+    hb.register_helper(
+        "docs",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let indent = h.param(1).and_then(|p| p.value().as_str()).unwrap_or("");
+                let lines = get_attributes(param(h, 0)?)?
+                    .iter()
+                    .filter(|a| a.key.name.as_str() == "doc")
+                    .flat_map(|a| a.values.iter())
+                    .map(|(_, v)| unquote(&v.to_string()).to_string())
+                    .collect::<Vec<String>>();
+                match lines.as_slice() {
+                    [] => {}
+                    [line] => out.write(&format!("/** {} */\n{}", line, indent))?,
+                    _ => {
+                        out.write("/**\n")?;
+                        for line in lines.iter() {
+                            out.write(&format!("{} * {}\n", indent, line))?;
+                        }
+                        out.write(&format!("{} */\n{}", indent, indent))?;
+                    }
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    Ok(())
+}
+
+/// Add TypeScript code generation templates
+pub fn add_templates(hb: &mut Handlebars) -> Result<(), CodegenError> {
+    let templates: Vec<(&str, &str)> = vec![
+        (
+            "ts-package-json",
+            include_str!("../../templates/typescript/package.json.hbs"),
+        ),
+        (
+            "ts-tsconfig",
+            include_str!("../../templates/typescript/tsconfig.json.hbs"),
+        ),
+        (
+            "ts-index",
+            include_str!("../../templates/typescript/index.ts.hbs"),
+        ),
+        (
+            "ts-runtime",
+            include_str!("../../templates/typescript/frodobuf.ts.hbs"),
+        ),
+        (
+            "ts-service",
+            include_str!("../../templates/typescript/service.ts.hbs"),
+        ),
+    ];
+    for t in templates.iter() {
+        hb.register_template_string(t.0, t.1)?;
+    }
+    Ok(())
+}
+
+/// Parameters needed for generating a set of files for a new TypeScript project
+pub struct CreateProject<'cp> {
+    /// path to idl input file
+    pub input: &'cp Path,
+
+    /// npm package name
+    pub package: &'cp str,
+
+    /// output directory.
+    pub output: &'cp Path,
+}
+
+/// Generate the full set of TypeScript files for a project.
+/// The module for the schema is generated here, and regenerated with `npm run regen`.
+pub fn create_project<'cp>(
+    r: &mut Renderer,
+    schema: &Schema,
+    arg: CreateProject<'cp>,
+) -> Result<(), CodegenError> {
+    let project_dir = arg.output.join("typescript");
+    let src_dir = project_dir.join("src");
+    fs::create_dir_all(&src_dir)?;
+
+    r.set("package", arg.package);
+    r.set(
+        "idl-source",
+        format!("../{}", &arg.input.display()).as_str(),
+    );
+    r.set("module-name", to_snake_case(&schema.namespace.name));
+
+    let mut out = fs::File::create(project_dir.join("package.json"))?;
+    r.render("ts-package-json", &mut out)?;
+
+    let mut out = fs::File::create(project_dir.join("tsconfig.json"))?;
+    r.render("ts-tsconfig", &mut out)?;
+
+    let mut out = fs::File::create(src_dir.join("index.ts"))?;
+    r.render("ts-index", &mut out)?;
+
+    r.codegen_for_schema(schema, &src_dir)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        codegen::check_golden,
+        render::{OutputLanguage, RenderConfig, Renderer},
+    };
+
+    #[test]
+    fn golden() {
+        check_golden(
+            OutputLanguage::TypeScript,
+            "ts-service",
+            "typescript/store.ts",
+        );
+    }
+
+    #[test]
+    fn enum_and_map_fields() {
+        let idl = r#"package sensor;
+            enum Level { LOW = 0; HIGH = 1; }
+            message Reading { Level level; map<uint32,string> labels; }"#;
+        let schema = midl_parser::parse_string(idl).unwrap().schema;
+        let mut r = Renderer::init(&RenderConfig {
+            language: OutputLanguage::TypeScript,
+            ..Default::default()
+        })
+        .unwrap();
+        r.set("schema", serde_json::to_value(&schema).unwrap());
+        let mut out = Vec::new();
+        r.render("ts-service", &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("  HIGH = 1,\n"));
+        assert!(out.contains("  level: Level;\n"));
+        assert!(out.contains("  labels: Record<number, string>;\n"));
+        // enums are sent as their number
+        assert!(out.contains(r#"["level", "int"],"#));
+        assert!(out.contains(r#"["labels", { map: ["int", "string"] }],"#));
+    }
+}
@@ -36,18 +36,162 @@ pub mod context {
 
         /// Span name/context for tracing. This is a placeholder for now
         pub span: Option<String>,
+
+        /// Time by which the call must complete. Retries are not attempted after the deadline.
+        /// (`Instant` is not available to actors on wasm32; leave this empty there)
+        pub deadline: Option<std::time::Instant>,
     }
 }
 
 /// client is the caller side of any interface
 pub mod client {
+    use super::{context, Message, RpcError, Transport};
+    use futures::future::BoxFuture;
+    use std::{
+        borrow::Cow,
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    /// Function that waits for the duration
+    pub type SleepFn = Arc<dyn Fn(Duration) -> BoxFuture<'static, ()> + Send + Sync>;
+
+    /// Retry policy for methods declared `@idempotent`. Other methods are sent once.
+    /// A failed call is retried, after an exponentially increasing delay with random jitter,
+    /// if its error code is in `retry_codes`, the number of attempts is below `max_attempts`,
+    /// and the delay ends before the deadline in the call context.
+    ///
+    /// Waiting between attempts needs an async timer: the `sleep` function, or else tokio's,
+    /// with the `net` feature and when the call runs in a tokio runtime. Without a timer,
+    /// failed calls are not retried.
+    #[derive(Clone)]
+    pub struct RetryPolicy {
+        /// Maximum number of attempts, including the first
+        pub max_attempts: u32,
+        /// Delay before the first retry
+        pub initial_backoff: Duration,
+        /// Upper limit of the delay between attempts
+        pub max_backoff: Duration,
+        /// Factor by which the delay increases after each retry
+        pub multiplier: f64,
+        /// Fraction of the delay (0.0 - 1.0) that is randomly removed, to spread out retries
+        pub jitter: f64,
+        /// Codes of errors that can be retried (see [`RpcError::code`])
+        pub retry_codes: Vec<u32>,
+        /// Function used to wait between attempts (see [`RetryPolicy::with_sleep`])
+        pub sleep: Option<SleepFn>,
+    }
+
+    impl Default for RetryPolicy {
+        /// 3 attempts, with delays starting at 100ms, retrying host and initialization errors
+        fn default() -> Self {
+            RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(100),
+                max_backoff: Duration::from_secs(5),
+                multiplier: 2.0,
+                jitter: 0.2,
+                retry_codes: vec![
+                    RpcError::NotInitialized.code(),
+                    RpcError::HostError(String::new()).code(),
+                ],
+                sleep: None,
+            }
+        }
+    }
+
+    impl std::fmt::Debug for RetryPolicy {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RetryPolicy")
+                .field("max_attempts", &self.max_attempts)
+                .field("initial_backoff", &self.initial_backoff)
+                .field("max_backoff", &self.max_backoff)
+                .field("multiplier", &self.multiplier)
+                .field("jitter", &self.jitter)
+                .field("retry_codes", &self.retry_codes)
+                .finish()
+        }
+    }
+
+    impl RetryPolicy {
+        /// Constructs the default policy with the maximum number of attempts
+        pub fn new(max_attempts: u32) -> Self {
+            RetryPolicy {
+                max_attempts,
+                ..Default::default()
+            }
+        }
+
+        /// Sets the function used to wait between attempts. It must not block the thread.
+        /// The default uses tokio's timer with the `net` feature; other runtimes should
+        /// supply their own, for example
+        /// `policy.with_sleep(|d| Box::pin(async_std::task::sleep(d)))`
+        pub fn with_sleep<F>(mut self, sleep: F) -> Self
+        where
+            F: Fn(Duration) -> BoxFuture<'static, ()> + Send + Sync + 'static,
+        {
+            self.sleep = Some(Arc::new(sleep));
+            self
+        }
+
+        /// Returns true if the error may be retried
+        pub fn is_retryable(&self, err: &RpcError) -> bool {
+            self.retry_codes.contains(&err.code())
+        }
+
+        /// Returns the delay after the attempt (1-based) has failed
+        /// A negative or NaN `multiplier` keeps the initial delay, and a NaN `jitter` is ignored.
+        pub fn backoff(&self, attempt: u32) -> Duration {
+            let exp = if self.multiplier >= 0.0 {
+                self.multiplier.powi(attempt.saturating_sub(1) as i32)
+            } else {
+                1.0
+            };
+            // min ignores NaN (from 0 * inf), so the delay is within the limit
+            let delay =
+                (self.initial_backoff.as_secs_f64() * exp).min(self.max_backoff.as_secs_f64());
+            let jitter = if self.jitter.is_nan() {
+                0.0
+            } else {
+                self.jitter.clamp(0.0, 1.0) * random_fraction(attempt)
+            };
+            Duration::from_secs_f64((delay * (1.0 - jitter)).max(0.0))
+        }
+
+        /// Returns a future that waits for the delay, or None if there is no timer
+        fn sleep(&self, delay: Duration) -> Option<BoxFuture<'static, ()>> {
+            match &self.sleep {
+                Some(sleep) => Some(sleep(delay)),
+                #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+                None => tokio::runtime::Handle::try_current()
+                    .ok()
+                    .map(|_| -> BoxFuture<'static, ()> { Box::pin(tokio::time::sleep(delay)) }),
+                #[cfg(not(all(feature = "net", not(target_arch = "wasm32"))))]
+                None => None,
+            }
+        }
+    }
+
+    /// pseudo-random number in [0,1), from the randomly-keyed std hasher
+    fn random_fraction(seed: u32) -> f64 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(seed as u64);
+        (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
     /// Client config defines the intended recipient of a message
-    #[derive(Debug)]
+    #[derive(Clone, Debug)]
     pub struct ClientConfig {
         /// Host/link name, usually "default" for the current host
         pub host: String,
         /// Recipient of message, such as actor's public key or provider id
         pub target: String,
+        /// Retry policy for idempotent methods. If None, each call is sent once.
+        /// Retries need a timer (see [`RetryPolicy`]): without one, a failed call is not retried,
+        /// and with the `log` feature a warning is logged.
+        pub retry: Option<RetryPolicy>,
     }
 
     impl ClientConfig {
@@ -57,6 +201,7 @@ pub mod client {
             ClientConfig {
                 host: host.into(),
                 target: target.into(),
+                retry: None,
             }
         }
 
@@ -65,6 +210,7 @@ pub mod client {
             ClientConfig {
                 host: "default".into(),
                 target: target.into(),
+                retry: None,
             }
         }
 
@@ -73,8 +219,69 @@ pub mod client {
             ClientConfig {
                 host: "default".into(),
                 target: target.into(),
+                retry: None,
             }
         }
+
+        /// Sets the retry policy for idempotent methods
+        pub fn with_retry(mut self, policy: RetryPolicy) -> ClientConfig {
+            self.retry = Some(policy);
+            self
+        }
+    }
+
+    /// Sends the message, retrying according to the config's retry policy.
+    /// Used by generated clients for methods declared `@idempotent`.
+    pub async fn send_with_retry<T: Transport + Sync + ?Sized>(
+        transport: &T,
+        ctx: &context::Context<'_>,
+        config: &ClientConfig,
+        method: &str,
+        arg: &[u8],
+    ) -> Result<Message<'static>, RpcError> {
+        let mut attempt = 1;
+        loop {
+            if matches!(ctx.deadline, Some(deadline) if Instant::now() >= deadline) {
+                return Err(RpcError::DeadlineExceeded);
+            }
+            let res = transport
+                .send(
+                    ctx,
+                    config,
+                    Message {
+                        method,
+                        arg: Cow::Borrowed(arg),
+                    },
+                )
+                .await;
+            let policy = match (&res, config.retry.as_ref()) {
+                (Err(e), Some(policy))
+                    if attempt < policy.max_attempts && policy.is_retryable(e) =>
+                {
+                    policy
+                }
+                _ => return res,
+            };
+            let delay = policy.backoff(attempt);
+            // don't retry if the deadline would pass while waiting
+            if matches!(ctx.deadline, Some(deadline) if Instant::now() + delay >= deadline) {
+                return res;
+            }
+            match policy.sleep(delay) {
+                Some(sleep) => sleep.await,
+                // waiting would block the thread
+                None => {
+                    #[cfg(feature = "log")]
+                    log::warn!(
+                        "not retrying {} after attempt {}: no timer to wait with",
+                        method,
+                        attempt
+                    );
+                    return res;
+                }
+            }
+            attempt += 1;
+        }
     }
 }
 
@@ -88,6 +295,36 @@ pub trait Transport: Send {
         config: &client::ClientConfig,
         req: Message<'_>,
     ) -> std::result::Result<Message<'static>, RpcError>;
+
+    /// Sends a message for a `@oneway` method, which has no response.
+    /// The default implementation calls `send` and discards the reply;
+    /// transports that can deliver without waiting for the reply should override it.
+    async fn send_oneway(
+        &self,
+        ctx: &context::Context<'_>,
+        config: &client::ClientConfig,
+        req: Message<'_>,
+    ) -> std::result::Result<(), RpcError>
+    where
+        Self: Sync,
+    {
+        self.send(ctx, config, req).await.map(|_| ())
+    }
+
+    /// Sends all requests of a batch in one message, and returns the result of each request.
+    /// The receiver must unpack the batch with `frodobuf::batch::dispatch_batch`.
+    async fn send_batch(
+        &self,
+        ctx: &context::Context<'_>,
+        config: &client::ClientConfig,
+        batch: &crate::batch::Batch,
+    ) -> std::result::Result<crate::batch::BatchResults, RpcError>
+    where
+        Self: Sync,
+    {
+        let resp = self.send(ctx, config, batch.to_message()?).await?;
+        crate::batch::BatchResults::from_bytes(resp.arg.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -129,47 +366,207 @@ pub fn serialize<T: Serialize>(data: &T) -> Result<Vec<u8>, RpcError> {
 
 /// An error that can occur in the processing of an RPC. This is not request-specific errors but
 /// rather cross-cutting errors that can always occur.
-#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+///
+/// Each variant has a stable numeric code, returned by [`RpcError::code`].
+/// Errors are sent between actors, providers, and the host as a serialized [`ErrorEnvelope`],
+/// and decoded back into the same variant by the receiver.
+#[derive(thiserror::Error, Debug, Clone, Serialize, Deserialize)]
 pub enum RpcError {
-    /// The request exceeded its deadline.
+    /// The request exceeded its deadline. (code 1)
     #[error("the request exceeded its deadline")]
     DeadlineExceeded,
 
-    /// A capability provider was called before its configure_dispatch was called.
+    /// A capability provider was called before its configure_dispatch was called. (code 2)
     #[error("the capability provider has not been initialized")]
     NotInitialized,
 
-    /// The message was invalid
+    /// The message was invalid (code 3)
     #[error("the message was invalid")]
     Invalid(String),
 
+    /// The method is not handled by the recipient (code 4)
     #[error("method not handled {0}")]
     MethodNotHandled(String),
 
     /// Error that can be returned if server has not implemented
-    /// an optional interface method
+    /// an optional interface method (code 5)
     #[error("method not implemented")]
     NotImplemented,
 
+    /// Error sending through the host (code 6)
     #[error("Host send error {0}")]
     HostError(String),
 
+    /// Message could not be deserialized (code 7)
     #[error("deserialization: {0}")]
     Deser(String),
 
+    /// Message could not be serialized (code 8)
     #[error("serialization: {0}")]
     Ser(String),
 
+    /// Invalid parameter (code 9)
     #[error("invalid parameter: {0}")]
     InvalidParameter(String),
 
-    /// Error occurred in actor's rpc handler
+    /// Error occurred in actor's rpc handler (code 10)
     #[error("actor: {0}")]
     ActorHandler(String),
 
-    /// Anything else
+    /// Anything else (code 11)
     #[error("{0}")]
     Other(String),
+
+    /// The caller is not authorized to call the method (code 12)
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// Error with an application-defined code and optional serialized details.
+    /// Codes below [`RpcError::APPLICATION_CODE_MIN`] are reserved for the other variants:
+    /// a `Status` with one has the code of `Other`, and is sent as `Other`
+    /// (see [`RpcError::status`]).
+    #[error("error {code}: {message}")]
+    Status {
+        code: u32,
+        message: String,
+        details: Option<Vec<u8>>,
+    },
+}
+
+/// Serialized form of an [`RpcError`], as returned by `__guest_error` and `__host_error`.
+/// The envelope is encoded as json so that it is also readable in host logs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorEnvelope {
+    /// Numeric error code
+    pub code: u32,
+    /// Error message, which is the string parameter of the variant, or empty
+    #[serde(default)]
+    pub message: String,
+    /// Optional serialized details
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<u8>>,
+}
+
+impl RpcError {
+    /// Smallest code for application-defined errors
+    pub const APPLICATION_CODE_MIN: u32 = 1000;
+
+    /// Constructs an application-defined error. A reserved code (below
+    /// [`RpcError::APPLICATION_CODE_MIN`]) gives `Other`, with the code in the message,
+    /// so that it can't be mistaken for another variant, for example a retryable `HostError`.
+    pub fn status<S: Into<String>>(code: u32, message: S) -> RpcError {
+        let message = message.into();
+        if code < Self::APPLICATION_CODE_MIN {
+            RpcError::Other(format!("error {}: {}", code, message))
+        } else {
+            RpcError::Status {
+                code,
+                message,
+                details: None,
+            }
+        }
+    }
+
+    /// Returns the numeric code of the error
+    pub fn code(&self) -> u32 {
+        match self {
+            RpcError::DeadlineExceeded => 1,
+            RpcError::NotInitialized => 2,
+            RpcError::Invalid(_) => 3,
+            RpcError::MethodNotHandled(_) => 4,
+            RpcError::NotImplemented => 5,
+            RpcError::HostError(_) => 6,
+            RpcError::Deser(_) => 7,
+            RpcError::Ser(_) => 8,
+            RpcError::InvalidParameter(_) => 9,
+            RpcError::ActorHandler(_) => 10,
+            RpcError::Other(_) => 11,
+            RpcError::PermissionDenied(_) => 12,
+            // reserved codes are treated as Other
+            RpcError::Status { code, .. } if *code < Self::APPLICATION_CODE_MIN => 11,
+            RpcError::Status { code, .. } => *code,
+        }
+    }
+
+    /// Returns the details payload, if any
+    pub fn details(&self) -> Option<&[u8]> {
+        match self {
+            RpcError::Status {
+                details: Some(details),
+                ..
+            } => Some(details),
+            _ => None,
+        }
+    }
+
+    /// Converts the error to its envelope
+    pub fn to_envelope(&self) -> ErrorEnvelope {
+        let (message, details) = match self {
+            RpcError::DeadlineExceeded | RpcError::NotInitialized | RpcError::NotImplemented => {
+                (String::new(), None)
+            }
+            RpcError::Invalid(s)
+            | RpcError::MethodNotHandled(s)
+            | RpcError::HostError(s)
+            | RpcError::Deser(s)
+            | RpcError::Ser(s)
+            | RpcError::InvalidParameter(s)
+            | RpcError::ActorHandler(s)
+            | RpcError::Other(s)
+            | RpcError::PermissionDenied(s) => (s.clone(), None),
+            // encoded as Other, which has no details
+            RpcError::Status { code, .. } if *code < Self::APPLICATION_CODE_MIN => {
+                (self.to_string(), None)
+            }
+            RpcError::Status {
+                message, details, ..
+            } => (message.clone(), details.clone()),
+        };
+        ErrorEnvelope {
+            code: self.code(),
+            message,
+            details,
+        }
+    }
+
+    /// Returns the serialized envelope of the error
+    pub fn to_envelope_bytes(&self) -> Vec<u8> {
+        // serializing a struct of strings and numbers can't fail
+        serde_json::to_vec(&self.to_envelope()).unwrap_or_default()
+    }
+
+    /// Decodes an error returned by the host or by an actor. If the bytes are not
+    /// an error envelope (for example, a text message from the host), returns `HostError`.
+    pub fn from_error_bytes(buf: &[u8]) -> RpcError {
+        match serde_json::from_slice::<ErrorEnvelope>(buf) {
+            Ok(envelope) => envelope.into(),
+            Err(_) => RpcError::HostError(String::from_utf8_lossy(buf).to_string()),
+        }
+    }
+}
+
+impl From<ErrorEnvelope> for RpcError {
+    fn from(e: ErrorEnvelope) -> RpcError {
+        match e.code {
+            1 => RpcError::DeadlineExceeded,
+            2 => RpcError::NotInitialized,
+            3 => RpcError::Invalid(e.message),
+            4 => RpcError::MethodNotHandled(e.message),
+            5 => RpcError::NotImplemented,
+            6 => RpcError::HostError(e.message),
+            7 => RpcError::Deser(e.message),
+            8 => RpcError::Ser(e.message),
+            9 => RpcError::InvalidParameter(e.message),
+            10 => RpcError::ActorHandler(e.message),
+            11 => RpcError::Other(e.message),
+            12 => RpcError::PermissionDenied(e.message),
+            code => RpcError::Status {
+                code,
+                message: e.message,
+                details: e.details,
+            },
+        }
+    }
 }
 
 #[async_trait]
@@ -190,3 +587,211 @@ pub trait MessageDispatch {
 //
 //    };
 //}
+
+#[cfg(test)]
+mod test {
+    use super::{client, context, ErrorEnvelope, Message, RpcError, Transport};
+    use client::RetryPolicy;
+    use futures::executor::block_on;
+    use std::{
+        borrow::Cow,
+        sync::atomic::Ordering,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn error_envelope_round_trip() {
+        let errors = vec![
+            RpcError::DeadlineExceeded,
+            RpcError::Deser("bad input".into()),
+            RpcError::ActorHandler("failed".into()),
+            RpcError::PermissionDenied("Store.Get".into()),
+            RpcError::Status {
+                code: 1001,
+                message: "quota".into(),
+                details: Some(vec![1, 2, 3]),
+            },
+        ];
+        for err in errors.into_iter() {
+            let decoded = RpcError::from_error_bytes(&err.to_envelope_bytes());
+            assert_eq!(decoded.code(), err.code());
+            assert_eq!(decoded.to_string(), err.to_string());
+            assert_eq!(decoded.details(), err.details());
+        }
+    }
+
+    #[test]
+    fn reserved_status_code() {
+        assert!(matches!(
+            RpcError::status(1001, "quota"),
+            RpcError::Status { code: 1001, .. }
+        ));
+        let err = RpcError::status(6, "no host");
+        assert_eq!(err.to_string(), "error 6: no host");
+        assert_eq!(err.code(), 11);
+
+        // a Status with a reserved code is sent as Other, and isn't retried as HostError
+        let err = RpcError::Status {
+            code: 6,
+            message: "no host".into(),
+            details: Some(vec![1]),
+        };
+        assert_eq!(err.code(), 11);
+        assert!(!RetryPolicy::default().is_retryable(&err));
+        let decoded = RpcError::from_error_bytes(&err.to_envelope_bytes());
+        assert!(matches!(decoded, RpcError::Other(ref s) if s == "error 6: no host"));
+    }
+
+    /// transport that fails with `err` for the first `failures` calls
+    struct Flaky {
+        failures: u32,
+        err: RpcError,
+        calls: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for Flaky {
+        async fn send(
+            &self,
+            _ctx: &context::Context<'_>,
+            _config: &client::ClientConfig,
+            _req: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst);
+            if n < self.failures {
+                Err(self.err.clone())
+            } else {
+                Ok(Message {
+                    method: "_reply",
+                    arg: Cow::Owned(Vec::new()),
+                })
+            }
+        }
+    }
+
+    fn flaky(failures: u32, err: RpcError) -> Flaky {
+        Flaky {
+            failures,
+            err,
+            calls: Default::default(),
+        }
+    }
+
+    fn send(t: &Flaky, ctx: &context::Context<'_>, policy: RetryPolicy) -> Result<(), RpcError> {
+        let config =
+            client::ClientConfig::target("x").with_retry(policy.with_sleep(|_| Box::pin(async {})));
+        block_on(client::send_with_retry(t, ctx, &config, "Get", b"")).map(|_| ())
+    }
+
+    #[test]
+    fn retry_policy() {
+        let ctx = context::Context::default();
+        let t = flaky(2, RpcError::HostError("link down".into()));
+        assert!(send(&t, &ctx, RetryPolicy::new(3)).is_ok());
+        assert_eq!(t.calls.load(Ordering::SeqCst), 3);
+
+        // attempts are limited
+        let t = flaky(5, RpcError::HostError("link down".into()));
+        assert!(send(&t, &ctx, RetryPolicy::new(3)).is_err());
+        assert_eq!(t.calls.load(Ordering::SeqCst), 3);
+
+        // errors that aren't retryable are returned immediately
+        let t = flaky(1, RpcError::Deser("bad".into()));
+        assert!(send(&t, &ctx, RetryPolicy::new(3)).is_err());
+        assert_eq!(t.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn retry_deadline() {
+        let t = flaky(5, RpcError::HostError("link down".into()));
+        let ctx = context::Context {
+            deadline: Some(Instant::now() + Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(1),
+            ..RetryPolicy::new(5)
+        };
+        // the first backoff would pass the deadline, so the error is returned
+        assert!(matches!(
+            send(&t, &ctx, policy),
+            Err(RpcError::HostError(_))
+        ));
+        assert_eq!(t.calls.load(Ordering::SeqCst), 1);
+
+        let ctx = context::Context {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        let res = send(&t, &ctx, RetryPolicy::new(5));
+        assert!(matches!(res, Err(RpcError::DeadlineExceeded)));
+    }
+
+    #[test]
+    fn retry_without_timer() {
+        // outside a tokio runtime, there is no default timer, so the call isn't retried
+        let ctx = context::Context::default();
+        let t = flaky(2, RpcError::HostError("link down".into()));
+        let config = client::ClientConfig::target("x").with_retry(RetryPolicy::new(3));
+        let res = block_on(client::send_with_retry(&t, &ctx, &config, "Get", b""));
+        assert!(res.is_err());
+        assert_eq!(t.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+    #[test]
+    fn retry_tokio_timer() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let ctx = context::Context::default();
+        let t = flaky(2, RpcError::HostError("link down".into()));
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..RetryPolicy::new(3)
+        };
+        let config = client::ClientConfig::target("x").with_retry(policy);
+        let res = runtime.block_on(client::send_with_retry(&t, &ctx, &config, "Get", b""));
+        assert!(res.is_ok());
+        assert_eq!(t.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn backoff_increases() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(20), Duration::from_secs(5));
+        let jittered = RetryPolicy::default().backoff(2);
+        assert!(jittered <= Duration::from_millis(200) && jittered >= Duration::from_millis(160));
+    }
+
+    #[test]
+    fn backoff_invalid_factors() {
+        let policy = |multiplier, jitter| RetryPolicy {
+            multiplier,
+            jitter,
+            ..Default::default()
+        };
+        assert_eq!(policy(-2.0, 0.0).backoff(2), Duration::from_millis(100));
+        assert_eq!(policy(f64::NAN, 0.0).backoff(3), Duration::from_millis(100));
+        assert_eq!(
+            policy(f64::INFINITY, 0.0).backoff(3),
+            Duration::from_secs(5)
+        );
+        assert_eq!(policy(2.0, f64::NAN).backoff(2), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn error_from_text() {
+        let err = RpcError::from_error_bytes(b"link not found");
+        assert!(matches!(err, RpcError::HostError(ref s) if s == "link not found"));
+
+        let envelope: ErrorEnvelope = serde_json::from_str(r#"{"code":7,"message":"x"}"#).unwrap();
+        assert!(matches!(RpcError::from(envelope), RpcError::Deser(_)));
+    }
+}
@@ -0,0 +1,154 @@
+//! Console logging for actors
+//!
+//! Messages are written to the host with the wapc `__console_log` import.
+//! When compiled for a native target (for example, when running actor unit tests),
+//! messages are written to stderr instead.
+//!
+//! [`console_debug!`](crate::console_debug), [`console_info!`](crate::console_info),
+//! [`console_warn!`](crate::console_warn) and [`console_error!`](crate::console_error)
+//! write a line prefixed with its level, in the same format as the `log` backend.
+//!
+//! With the `log` feature enabled, [`init_logger`] installs a `log` backend that forwards
+//! each record, with its level and target, to the console. Crates using `tracing` can
+//! reach the same backend by enabling the `log` feature of `tracing`.
+
+/// Writes a line to the host console
+pub fn log(msg: &str) {
+    #[cfg(target_arch = "wasm32")]
+    unsafe {
+        crate::actor::prelude::__console_log(msg.as_ptr(), msg.len());
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{}", msg);
+}
+
+/// Writes a formatted line to the host console, with the same syntax as `println!`
+/// ```rust
+///   use frodobuf::console_log;
+///   let actor = "counter";
+///   console_log!("{} started", actor);
+/// ```
+#[macro_export]
+macro_rules! console_log {
+    ($($arg:tt)*) => {
+        $crate::console::log(&format!($($arg)*))
+    };
+}
+
+/// Formats a line as "LEVEL message", for the level macros
+#[doc(hidden)]
+pub fn with_level(level: &str, args: std::fmt::Arguments<'_>) -> String {
+    format!("{} {}", level, args)
+}
+
+/// Writes a formatted line at level DEBUG to the host console, with the same syntax as `println!`
+#[macro_export]
+macro_rules! console_debug {
+    ($($arg:tt)*) => {
+        $crate::console::log(&$crate::console::with_level("DEBUG", format_args!($($arg)*)))
+    };
+}
+
+/// Writes a formatted line at level INFO to the host console, with the same syntax as `println!`
+/// ```rust
+///   use frodobuf::console_info;
+///   let actor = "counter";
+///   console_info!("{} started", actor);
+/// ```
+#[macro_export]
+macro_rules! console_info {
+    ($($arg:tt)*) => {
+        $crate::console::log(&$crate::console::with_level("INFO", format_args!($($arg)*)))
+    };
+}
+
+/// Writes a formatted line at level WARN to the host console, with the same syntax as `println!`
+#[macro_export]
+macro_rules! console_warn {
+    ($($arg:tt)*) => {
+        $crate::console::log(&$crate::console::with_level("WARN", format_args!($($arg)*)))
+    };
+}
+
+/// Writes a formatted line at level ERROR to the host console, with the same syntax as `println!`
+#[macro_export]
+macro_rules! console_error {
+    ($($arg:tt)*) => {
+        $crate::console::log(&$crate::console::with_level("ERROR", format_args!($($arg)*)))
+    };
+}
+
+/// `log` backend that writes to the host console
+#[cfg(feature = "log")]
+#[derive(Debug, Default)]
+pub struct ConsoleLogger {}
+
+#[cfg(feature = "log")]
+static LOGGER: ConsoleLogger = ConsoleLogger {};
+
+#[cfg(feature = "log")]
+impl ConsoleLogger {
+    /// Formats the record as "LEVEL target: message"
+    pub fn format(record: &log::Record) -> String {
+        if record.target().is_empty() {
+            format!("{} {}", record.level(), record.args())
+        } else {
+            format!("{} {}: {}", record.level(), record.target(), record.args())
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            log(&Self::format(record));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the console logger as the `log` backend, with the maximum level to forward.
+/// Returns an error if a logger was already installed, which is expected if this
+/// is called on every guest call; the error can be ignored.
+#[cfg(feature = "log")]
+pub fn init_logger(level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn level_macros() {
+        assert_eq!(
+            super::with_level("WARN", format_args!("value {}", 3)),
+            "WARN value 3"
+        );
+        crate::console_debug!("debug {}", 1);
+        crate::console_info!("info");
+        crate::console_warn!("warn {:?}", "x");
+        crate::console_error!("error");
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn format_record() {
+        use super::ConsoleLogger;
+
+        let line = ConsoleLogger::format(
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("counter")
+                .args(format_args!("value {}", 3))
+                .build(),
+        );
+        assert_eq!(line, "WARN counter: value 3");
+    }
+}
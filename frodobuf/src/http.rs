@@ -0,0 +1,684 @@
+//! HTTP/JSON gateway for services
+//!
+//! A [`Gateway`] maps HTTP requests with JSON bodies onto a [`MessageDispatch`]
+//! implementation, converting the JSON body to the method's msgpack argument, and the
+//! msgpack response back to JSON. The conversions use the generated types of the service,
+//! through the [`Route`]s returned by the generated `xxx_http_routes()` function.
+//!
+//! By default, each non-streaming method is available as `POST /{package}/{Service}/{method}`.
+//! The path and HTTP method can be changed with an attribute on the rpc method:
+//! ```text
+//!   @http(method="GET", path="/v1/count")
+//!   rpc count() returns uint32;
+//! ```
+//! A method without parameters accepts an empty body. A successful call returns `200`
+//! and the JSON response (`null` for methods that return nothing). Errors declared with
+//! `throws` return `422` with the JSON error, as in `{"NotFound":{"key":"x"}}`, and other
+//! errors return a status from [`status_code`] with the JSON [`ErrorEnvelope`](crate::ErrorEnvelope).
+//!
+//! Calls are dispatched with `Context::actor` set to the principal returned by the function
+//! given to [`Gateway::with_principal`], so `@auth` requirements can be checked. The function
+//! receives the request headers, and may verify a token or read a header set by a trusted proxy.
+//! Without it, the caller is unknown, and methods with requirements are denied by
+//! [`RoleAuthorizer`](crate::auth::RoleAuthorizer).
+//!
+//! `Gateway::handle` can be called from any HTTP server. With the `net` feature,
+//! `Gateway::serve` runs a minimal HTTP/1.1 server on a tokio tcp listener. It allows
+//! cross-origin requests only from the origins given to [`Gateway::with_cors_origins`].
+use crate::{context, deserialize, serialize, Message, MessageDispatch, RpcError};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{borrow::Cow, fmt, sync::Arc};
+
+type Decode = fn(&[u8]) -> Result<Vec<u8>, RpcError>;
+type Encode = fn(&[u8]) -> Result<(u16, Vec<u8>), RpcError>;
+/// returns the caller of a request, from its headers
+type Principal = Arc<dyn Fn(&[(&str, &str)]) -> Option<String> + Send + Sync>;
+
+/// HTTP route for one rpc method
+#[derive(Clone)]
+pub struct Route {
+    http_method: String,
+    path: String,
+    rpc_method: String,
+    /// converts the JSON request body to the serialized argument
+    decode: Decode,
+    /// converts the serialized response to (status, JSON body)
+    encode: Encode,
+}
+
+impl fmt::Debug for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Route")
+            .field("http_method", &self.http_method)
+            .field("path", &self.path)
+            .field("rpc_method", &self.rpc_method)
+            .finish()
+    }
+}
+
+impl Route {
+    /// Constructs a route for a method with argument type `I` and return type `O`
+    pub fn new<I, O>(http_method: &str, path: &str, rpc_method: &str) -> Route
+    where
+        I: Serialize + DeserializeOwned,
+        O: Serialize + DeserializeOwned,
+    {
+        Route {
+            http_method: http_method.to_ascii_uppercase(),
+            path: path.to_string(),
+            rpc_method: rpc_method.to_string(),
+            decode: json_to_msgpack::<I>,
+            encode: |buf| Ok((200, msgpack_to_json::<O>(buf)?)),
+        }
+    }
+
+    /// Constructs a route for a method declared with `throws`, with argument type `I`,
+    /// return type `O`, and error type `E`
+    pub fn with_errors<I, O, E>(http_method: &str, path: &str, rpc_method: &str) -> Route
+    where
+        I: Serialize + DeserializeOwned,
+        O: Serialize + DeserializeOwned,
+        E: Serialize + DeserializeOwned,
+    {
+        Route {
+            encode: |buf| match deserialize::<Result<O, E>>(buf)? {
+                Ok(value) => Ok((200, to_json(&value)?)),
+                Err(e) => Ok((422, to_json(&e)?)),
+            },
+            ..Route::new::<I, O>(http_method, path, rpc_method)
+        }
+    }
+
+    /// Returns the HTTP method, in upper case
+    pub fn http_method(&self) -> &str {
+        &self.http_method
+    }
+
+    /// Returns the path
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the method name passed to `dispatch`, as `Service.Method`
+    pub fn rpc_method(&self) -> &str {
+        &self.rpc_method
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, RpcError> {
+    serde_json::to_vec(value).map_err(|e| RpcError::Ser(e.to_string()))
+}
+
+fn json_to_msgpack<T: Serialize + DeserializeOwned>(body: &[u8]) -> Result<Vec<u8>, RpcError> {
+    let body = if body.iter().all(u8::is_ascii_whitespace) {
+        &b"null"[..]
+    } else {
+        body
+    };
+    let value: T = serde_json::from_slice(body)
+        .map_err(|e| RpcError::InvalidParameter(format!("invalid json: {}", e)))?;
+    serialize(&value)
+}
+
+fn msgpack_to_json<T: Serialize + DeserializeOwned>(buf: &[u8]) -> Result<Vec<u8>, RpcError> {
+    // oneway methods return an empty response
+    if buf.is_empty() {
+        return Ok(b"null".to_vec());
+    }
+    to_json(&deserialize::<T>(buf)?)
+}
+
+/// Returns the HTTP status for an error returned by dispatch
+pub fn status_code(err: &RpcError) -> u16 {
+    match err {
+        RpcError::MethodNotHandled(_) => 404,
+        RpcError::Invalid(_) | RpcError::InvalidParameter(_) | RpcError::Deser(_) => 400,
+        RpcError::PermissionDenied(_) => 403,
+        RpcError::NotImplemented => 501,
+        RpcError::DeadlineExceeded => 504,
+        RpcError::NotInitialized => 503,
+        _ => 500,
+    }
+}
+
+/// Response to an HTTP request. The body is JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// JSON body
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn error(err: &RpcError) -> HttpResponse {
+        HttpResponse {
+            status: status_code(err),
+            body: err.to_envelope_bytes(),
+        }
+    }
+}
+
+/// Maps HTTP requests onto the methods of a service
+#[derive(Clone)]
+pub struct Gateway {
+    routes: Vec<Route>,
+    handler: Arc<dyn MessageDispatch + Send + Sync>,
+    principal: Option<Principal>,
+    /// origins allowed to make cross-origin requests
+    cors_origins: Vec<String>,
+}
+
+impl fmt::Debug for Gateway {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gateway")
+            .field("routes", &self.routes)
+            .field("principal", &self.principal.is_some())
+            .field("cors_origins", &self.cors_origins)
+            .finish()
+    }
+}
+
+impl Gateway {
+    /// Constructs a gateway with no routes that sends requests to the handler
+    pub fn new<D: MessageDispatch + Send + Sync + 'static>(handler: Arc<D>) -> Gateway {
+        Gateway {
+            routes: Vec::new(),
+            handler,
+            principal: None,
+            cors_origins: Vec::new(),
+        }
+    }
+
+    /// Allows cross-origin requests from browsers on the origins, such as
+    /// `https://app.example.com`, for `Gateway::serve`. Origins are compared exactly,
+    /// and other origins get no CORS headers. By default, no origins are allowed.
+    pub fn with_cors_origins<I, S>(mut self, origins: I) -> Gateway
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.cors_origins
+            .extend(origins.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns the CORS response headers for a request from the origin,
+    /// which are empty unless the origin is allowed
+    pub fn cors_headers(&self, origin: Option<&str>) -> Vec<(&'static str, String)> {
+        match origin {
+            Some(origin) if self.cors_origins.iter().any(|o| o == origin) => vec![
+                ("Access-Control-Allow-Origin", origin.to_string()),
+                (
+                    "Access-Control-Allow-Methods",
+                    "GET, POST, PUT, DELETE, OPTIONS".to_string(),
+                ),
+                (
+                    "Access-Control-Allow-Headers",
+                    "Content-Type, Authorization".to_string(),
+                ),
+                ("Vary", "Origin".to_string()),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Sets the function that identifies the caller of a request from its headers,
+    /// as (name, value) pairs. The caller is passed to the handler in `Context::actor`.
+    /// Return `None` if the caller is unknown.
+    pub fn with_principal<F>(mut self, principal: F) -> Gateway
+    where
+        F: Fn(&[(&str, &str)]) -> Option<String> + Send + Sync + 'static,
+    {
+        self.principal = Some(Arc::new(principal));
+        self
+    }
+
+    /// Adds routes, usually from a generated `xxx_http_routes()` function
+    pub fn with_routes(mut self, routes: Vec<Route>) -> Gateway {
+        self.routes.extend(routes);
+        self
+    }
+
+    /// Returns all routes
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Handles one HTTP request without headers. The query string, if any, is ignored.
+    pub async fn handle(&self, http_method: &str, path: &str, body: &[u8]) -> HttpResponse {
+        self.handle_request(http_method, path, &[], body).await
+    }
+
+    /// Handles one HTTP request. The headers are passed to the principal function.
+    /// The query string, if any, is ignored.
+    pub async fn handle_request(
+        &self,
+        http_method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> HttpResponse {
+        let path = path.split('?').next().unwrap_or_default();
+        let path = match path.trim_end_matches('/') {
+            "" => "/",
+            p => p,
+        };
+        let route = match self.find_route(http_method, path) {
+            Ok(route) => route,
+            Err(resp) => return resp,
+        };
+        let principal = self.principal.as_ref().and_then(|p| p(headers));
+        match self.call(route, principal.as_deref(), body).await {
+            Ok((status, body)) => HttpResponse { status, body },
+            Err(e) => HttpResponse::error(&e),
+        }
+    }
+
+    fn find_route(&self, http_method: &str, path: &str) -> Result<&Route, HttpResponse> {
+        let mut matching = self.routes.iter().filter(|r| r.path == path).peekable();
+        if matching.peek().is_none() {
+            return Err(HttpResponse::error(&RpcError::MethodNotHandled(
+                path.to_string(),
+            )));
+        }
+        matching
+            .find(|r| r.http_method.eq_ignore_ascii_case(http_method))
+            .ok_or_else(|| HttpResponse {
+                status: 405,
+                body: RpcError::InvalidParameter(format!(
+                    "method {} not allowed for {}",
+                    http_method, path
+                ))
+                .to_envelope_bytes(),
+            })
+    }
+
+    async fn call(
+        &self,
+        route: &Route,
+        principal: Option<&str>,
+        body: &[u8],
+    ) -> Result<(u16, Vec<u8>), RpcError> {
+        let arg = (route.decode)(body)?;
+        let ctx = context::Context {
+            actor: principal,
+            ..Default::default()
+        };
+        let resp = self
+            .handler
+            .dispatch(
+                &ctx,
+                Message {
+                    method: &route.rpc_method,
+                    arg: Cow::Owned(arg),
+                },
+            )
+            .await?;
+        (route.encode)(resp.arg.as_ref())
+    }
+}
+
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+mod server {
+    use super::{Gateway, HttpResponse};
+    use std::{io, time::Duration};
+    use tokio::{
+        io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+        time::timeout,
+    };
+
+    /// Largest request body accepted, in bytes
+    const MAX_BODY_LEN: usize = 16 * 1024 * 1024;
+
+    /// Largest request line or header line accepted, in bytes
+    const MAX_LINE_LEN: usize = 8 * 1024;
+
+    /// Largest number of request headers accepted
+    const MAX_HEADERS: usize = 100;
+
+    /// Time allowed to receive the request head, and then the request body
+    const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+    fn reason(status: u16) -> &'static str {
+        match status {
+            200 => "OK",
+            204 => "No Content",
+            400 => "Bad Request",
+            404 => "Not Found",
+            403 => "Forbidden",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            413 => "Payload Too Large",
+            422 => "Unprocessable Entity",
+            431 => "Request Header Fields Too Large",
+            501 => "Not Implemented",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            _ => "Internal Server Error",
+        }
+    }
+
+    /// request line and headers
+    struct Head {
+        method: String,
+        path: String,
+        headers: Vec<(String, String)>,
+        content_length: usize,
+    }
+
+    /// Reads a line of at most `MAX_LINE_LEN` bytes, or returns None if it is longer
+    async fn read_line<R: AsyncBufRead + Unpin>(
+        reader: &mut R,
+        line: &mut String,
+    ) -> io::Result<Option<usize>> {
+        line.clear();
+        let n = (&mut *reader)
+            .take(MAX_LINE_LEN as u64 + 1)
+            .read_line(line)
+            .await?;
+        if n > MAX_LINE_LEN {
+            return Ok(None);
+        }
+        Ok(Some(n))
+    }
+
+    /// Reads the request line and headers, or returns the status of the error response
+    async fn read_head<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Result<Head, u16>> {
+        let mut line = String::new();
+        if read_line(reader, &mut line).await?.is_none() {
+            return Ok(Err(431));
+        }
+        let mut parts = line.split_whitespace();
+        let (method, path) = match (parts.next(), parts.next()) {
+            (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+            _ => return Ok(Err(400)),
+        };
+        let mut content_length = 0usize;
+        let mut headers = Vec::new();
+        loop {
+            match read_line(reader, &mut line).await? {
+                None => return Ok(Err(431)),
+                Some(0) => break,
+                Some(_) if line.trim().is_empty() => break,
+                Some(_) => {}
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if headers.len() >= MAX_HEADERS {
+                    return Ok(Err(431));
+                }
+                let (name, value) = (name.trim(), value.trim());
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.parse().unwrap_or(0);
+                }
+                headers.push((name.to_string(), value.to_string()));
+            }
+        }
+        Ok(Ok(Head {
+            method,
+            path,
+            headers,
+            content_length,
+        }))
+    }
+
+    impl Gateway {
+        /// Serves HTTP requests on the listener until an error occurs.
+        /// Each connection handles one request, and is closed after the response.
+        /// Cross-origin requests are allowed from the origins set with `with_cors_origins`.
+        pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+            let gateway = std::sync::Arc::new(self);
+            loop {
+                let (stream, _) = listener.accept().await?;
+                let gateway = gateway.clone();
+                tokio::spawn(async move {
+                    let _ = gateway.serve_connection(stream).await;
+                });
+            }
+        }
+
+        async fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+            let mut reader = BufReader::new(stream);
+            let head = match timeout(READ_TIMEOUT, read_head(&mut reader)).await {
+                Ok(head) => head?,
+                Err(_) => Err(408),
+            };
+            let head = match head {
+                Ok(head) => head,
+                Err(status) => {
+                    let resp = HttpResponse {
+                        status,
+                        body: Vec::new(),
+                    };
+                    return write_response(reader.into_inner(), resp, &[]).await;
+                }
+            };
+            let origin = head
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("origin"))
+                .map(|(_, value)| value.clone());
+            let cors = self.cors_headers(origin.as_deref());
+            let resp = if head.method.eq_ignore_ascii_case("OPTIONS") && !cors.is_empty() {
+                // cors preflight
+                HttpResponse {
+                    status: 204,
+                    body: Vec::new(),
+                }
+            } else if head.content_length > MAX_BODY_LEN {
+                HttpResponse {
+                    status: 413,
+                    body: Vec::new(),
+                }
+            } else {
+                // the body is read as it arrives, rather than allocated from content-length
+                let mut body = Vec::new();
+                let mut limited = (&mut reader).take(head.content_length as u64);
+                match timeout(READ_TIMEOUT, limited.read_to_end(&mut body)).await {
+                    Ok(n) => {
+                        if n? < head.content_length {
+                            return Err(io::ErrorKind::UnexpectedEof.into());
+                        }
+                        let headers: Vec<(&str, &str)> = head
+                            .headers
+                            .iter()
+                            .map(|(name, value)| (name.as_str(), value.as_str()))
+                            .collect();
+                        self.handle_request(&head.method, &head.path, &headers, &body)
+                            .await
+                    }
+                    Err(_) => HttpResponse {
+                        status: 408,
+                        body: Vec::new(),
+                    },
+                }
+            };
+            write_response(reader.into_inner(), resp, &cors).await
+        }
+    }
+
+    async fn write_response(
+        mut stream: TcpStream,
+        resp: HttpResponse,
+        cors: &[(&'static str, String)],
+    ) -> io::Result<()> {
+        let mut header = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+            resp.status,
+            reason(resp.status),
+            resp.body.len()
+        );
+        for (name, value) in cors.iter() {
+            header.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        header.push_str("Connection: close\r\n\r\n");
+        stream.write_all(header.as_bytes()).await?;
+        stream.write_all(&resp.body).await?;
+        stream.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+
+    /// "Svc.Add" adds a pair of numbers, "Svc.Div" returns `Result<u32, String>`
+    struct Calc {}
+
+    #[async_trait]
+    impl MessageDispatch for Calc {
+        async fn dispatch(
+            &self,
+            _ctx: &context::Context<'_>,
+            message: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            let (a, b): (u32, u32) = deserialize(message.arg.as_ref())?;
+            let buf = match message.method {
+                "Svc.Add" => serialize(&(a + b))?,
+                "Svc.Div" if b == 0 => serialize(&Err::<u32, String>("divide by zero".into()))?,
+                "Svc.Div" => serialize(&Ok::<u32, String>(a / b))?,
+                _ => return Err(RpcError::MethodNotHandled(message.method.to_string())),
+            };
+            Ok(Message {
+                method: "_reply",
+                arg: Cow::Owned(buf),
+            })
+        }
+    }
+
+    fn gateway() -> Gateway {
+        Gateway::new(Arc::new(Calc {})).with_routes(vec![
+            Route::new::<(u32, u32), u32>("POST", "/calc/Svc/add", "Svc.Add"),
+            Route::with_errors::<(u32, u32), u32, String>("PUT", "/v1/div", "Svc.Div"),
+            Route::new::<(u32, u32), u32>("POST", "/calc/Svc/missing", "Svc.Missing"),
+        ])
+    }
+
+    #[test]
+    fn json_routes() {
+        let g = gateway();
+        let resp = block_on(g.handle("POST", "/calc/Svc/add", b"[2, 3]"));
+        assert_eq!((resp.status, resp.body), (200, b"5".to_vec()));
+
+        let resp = block_on(g.handle("put", "/v1/div/?x=1", b"[8,2]"));
+        assert_eq!((resp.status, resp.body), (200, b"4".to_vec()));
+        let resp = block_on(g.handle("PUT", "/v1/div", b"[8,0]"));
+        assert_eq!(resp.status, 422);
+        assert_eq!(resp.body, b"\"divide by zero\"".to_vec());
+    }
+
+    #[test]
+    fn errors() {
+        let g = gateway();
+        assert_eq!(block_on(g.handle("POST", "/nope", b"")).status, 404);
+        assert_eq!(block_on(g.handle("GET", "/calc/Svc/add", b"")).status, 405);
+        assert_eq!(
+            block_on(g.handle("POST", "/calc/Svc/add", b"{")).status,
+            400
+        );
+        let resp = block_on(g.handle("POST", "/calc/Svc/missing", b"[1,1]"));
+        assert_eq!(resp.status, 404);
+        let envelope: crate::ErrorEnvelope = serde_json::from_slice(&resp.body).unwrap();
+        assert_eq!(envelope.code, 4);
+    }
+
+    /// "Svc.Whoami" returns the caller, and requires role "admin"
+    struct Whoami {}
+
+    #[async_trait]
+    impl MessageDispatch for Whoami {
+        async fn dispatch(
+            &self,
+            ctx: &context::Context<'_>,
+            _message: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            let auth = crate::auth::RoleAuthorizer::new().grant("admin", "alice");
+            let call = crate::auth::AuthCall {
+                service: "Svc",
+                method: "Whoami",
+                requirements: &[("role", "admin")],
+            };
+            crate::auth::check(Some(Arc::new(auth)), ctx, &call)?;
+            Ok(Message {
+                method: "_reply",
+                arg: Cow::Owned(serialize(&ctx.actor)?),
+            })
+        }
+    }
+
+    #[test]
+    fn principal() {
+        let g = Gateway::new(Arc::new(Whoami {}))
+            .with_routes(vec![Route::new::<(), Option<String>>(
+                "GET",
+                "/whoami",
+                "Svc.Whoami",
+            )])
+            .with_principal(|headers| {
+                headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("x-user"))
+                    .map(|(_, value)| value.to_string())
+            });
+        let resp = block_on(g.handle_request("GET", "/whoami", &[("X-User", "alice")], b""));
+        assert_eq!((resp.status, resp.body), (200, b"\"alice\"".to_vec()));
+        let resp = block_on(g.handle_request("GET", "/whoami", &[("X-User", "bob")], b""));
+        assert_eq!(resp.status, 403);
+        assert_eq!(block_on(g.handle("GET", "/whoami", b"")).status, 403);
+    }
+
+    #[test]
+    fn cors() {
+        assert!(gateway().cors_headers(Some("https://a.example")).is_empty());
+        let g = gateway().with_cors_origins(vec!["https://a.example"]);
+        let headers = g.cors_headers(Some("https://a.example"));
+        assert!(headers.contains(&("Access-Control-Allow-Origin", "https://a.example".into())));
+        assert!(g.cors_headers(Some("https://b.example")).is_empty());
+        assert!(g.cors_headers(None).is_empty());
+    }
+
+    /// Sends a raw request to a gateway served on localhost, and returns the response
+    #[cfg(feature = "net")]
+    fn exchange(request: Vec<u8>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(gateway().serve(listener));
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&request).await.unwrap();
+            let mut resp = String::new();
+            stream.read_to_string(&mut resp).await.unwrap();
+            resp
+        })
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn serve_limits() {
+        let body = "[2, 3]";
+        let resp = exchange(
+            format!(
+                "POST /calc/Svc/add HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .into_bytes(),
+        );
+        assert!(resp.starts_with("HTTP/1.1 200 "), "{}", resp);
+        assert!(resp.ends_with("\r\n\r\n5"), "{}", resp);
+
+        // a long header line, or too many headers, are refused
+        let long = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "x".repeat(10_000));
+        assert!(exchange(long.into_bytes()).starts_with("HTTP/1.1 431 "));
+        let many = format!("GET / HTTP/1.1\r\n{}\r\n", "X: x\r\n".repeat(200));
+        assert!(exchange(many.into_bytes()).starts_with("HTTP/1.1 431 "));
+    }
+}
@@ -6,15 +6,36 @@
 
 mod common;
 pub use common::{
-    client, context, deserialize, serialize, Message, MessageDispatch, RpcError, Transport,
-    WasmHost,
+    client, context, deserialize, serialize, ErrorEnvelope, Message, MessageDispatch, RpcError,
+    Transport, WasmHost,
 };
+// used by FrodobufActor derive
+#[doc(hidden)]
+pub use lazy_static;
+
+/// Access control for service methods
+pub mod auth;
+/// Sending several requests in one transport call
+pub mod batch;
 /// Code generation
 #[cfg(not(target_arch = "wasm32"))]
 pub mod codegen;
+/// Console logging for actors
+pub mod console;
+/// HTTP/JSON gateway for services
+pub mod http;
+/// Per-method call metrics
+pub mod metrics;
+/// Support for generated service mocks
+pub mod mock;
+/// Tcp and unix socket transports
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+pub mod net;
 /// Template rendering, for code generation
 #[cfg(not(target_arch = "wasm32"))]
 pub mod render;
+/// Streaming rpc methods
+pub mod stream;
 
 /// Version number of this api. The current value of this api is used
 #[doc(hidden)]
@@ -31,6 +52,9 @@ pub mod actor {
 
     pub mod prelude {
         pub use crate::common::{client, context, Message, MessageDispatch, RpcError, WasmHost};
+        pub use crate::{
+            console, console_debug, console_error, console_info, console_log, console_warn,
+        };
         // re-export async_trait
         pub use async_trait::async_trait;
         pub use frodobuf_derive::FrodobufActor;
@@ -56,19 +80,39 @@ pub mod actor {
             pub fn __guest_response(ptr: *const u8, len: usize);
             pub fn __guest_error(ptr: *const u8, len: usize);
             pub fn __guest_request(op_ptr: *const u8, ptr: *const u8);
+            pub fn __guest_caller_len() -> usize;
+            pub fn __guest_caller(ptr: *const u8);
         }
     }
-}
 
-pub mod provider {
+    use crate::{context, Message, MessageDispatch, RpcError};
 
-    pub mod prelude {
-        pub use crate::{client, context, Message, MessageDispatch, RpcError, WasmHost};
-        pub use async_trait::async_trait;
-        pub use frodobuf_derive::FrodobufProvider;
+    /// Dispatches a call received by an actor's `__guest_call`. `caller` is the public key
+    /// of the calling actor, from the host's `__guest_caller`, and is passed to the handler
+    /// in `Context::actor`, so that `@auth` requirements can be checked.
+    pub fn guest_dispatch<D: MessageDispatch + ?Sized>(
+        actor: &D,
+        caller: Option<&str>,
+        method: &str,
+        arg: &[u8],
+    ) -> Result<Message<'static>, RpcError> {
+        let ctx = context::Context {
+            actor: caller,
+            ..Default::default()
+        };
+        futures::executor::block_on(actor.dispatch(
+            &ctx,
+            Message {
+                method,
+                arg: std::borrow::Cow::Borrowed(arg),
+            },
+        ))
     }
 }
 
+/// Capability provider support
+pub mod provider;
+
 /// The function through which all host calls (from actors) take place.
 //#[cfg(target_arch = "wasm32")]
 pub fn host_call(
@@ -98,9 +142,7 @@ pub fn host_call(
             actor::prelude::__host_error(retptr);
             std::slice::from_raw_parts(retptr as _, errlen as _)
         };
-        Err(crate::common::RpcError::HostError(
-            String::from_utf8_lossy(&slice.to_vec()).to_string(),
-        ))
+        Err(crate::common::RpcError::from_error_bytes(slice))
     } else {
         // call succeeded
         let len = unsafe { actor::prelude::__host_response_len() };
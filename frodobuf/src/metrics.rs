@@ -0,0 +1,399 @@
+//! Per-method call metrics
+//!
+//! Generated servers (`XxxServer::dispatch`) and clients (`XxxClient`) report each call to the
+//! [`MetricsRecorder`] registered with [`set_recorder`]: the method name, as `Service.Method`,
+//! request and response sizes, duration, and the name of the error variant if the call failed.
+//! For methods declared with `throws`, the error is the name of the declared type.
+//! When no recorder is registered, calls are not measured.
+//!
+//! [`InMemoryRecorder`] keeps totals per method, for tests, and formats them
+//! in the Prometheus text format with [`InMemoryRecorder::to_prometheus`].
+//! With the `net` feature, [`serve_prometheus`] serves them to a Prometheus scraper.
+//!
+//! Servers report calls to methods they don't have as [`UNKNOWN_METHOD`], so that
+//! a caller can't create an unbounded number of method names in the recorder.
+//!
+//! Durations are not measured on wasm32, where they are always zero.
+use crate::RpcError;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+/// Method name reported by servers for calls to methods they don't have
+pub const UNKNOWN_METHOD: &str = "unknown";
+
+/// Which end of the call is reporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Side {
+    /// Generated client
+    Client,
+    /// Generated server dispatch
+    Server,
+}
+
+impl Side {
+    /// Returns "client" or "server"
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Client => "client",
+            Side::Server => "server",
+        }
+    }
+}
+
+/// One completed call
+#[derive(Debug, Clone)]
+pub struct CallEvent<'a> {
+    /// Side reporting the call
+    pub side: Side,
+    /// Service name
+    pub service: &'a str,
+    /// Method name
+    pub method: &'a str,
+    /// Size of the serialized request, in bytes. Zero for streams.
+    pub request_bytes: usize,
+    /// Size of the serialized response, in bytes. Zero for streams.
+    pub response_bytes: usize,
+    /// Time from the start of the call until the result was available
+    pub duration: Duration,
+    /// Name of the error variant, if the call failed
+    pub error: Option<&'a str>,
+}
+
+/// Receives call metrics
+pub trait MetricsRecorder: Send + Sync {
+    /// Records one call
+    fn record(&self, event: &CallEvent<'_>);
+}
+
+/// Name of an error variant, used as a metrics label
+pub trait ErrorKind {
+    /// Returns the name of the variant
+    fn error_kind(&self) -> &'static str;
+}
+
+impl ErrorKind for RpcError {
+    fn error_kind(&self) -> &'static str {
+        match self {
+            RpcError::DeadlineExceeded => "DeadlineExceeded",
+            RpcError::NotInitialized => "NotInitialized",
+            RpcError::Invalid(_) => "Invalid",
+            RpcError::MethodNotHandled(_) => "MethodNotHandled",
+            RpcError::NotImplemented => "NotImplemented",
+            RpcError::HostError(_) => "HostError",
+            RpcError::Deser(_) => "Deser",
+            RpcError::Ser(_) => "Ser",
+            RpcError::InvalidParameter(_) => "InvalidParameter",
+            RpcError::ActorHandler(_) => "ActorHandler",
+            RpcError::Other(_) => "Other",
+            RpcError::PermissionDenied(_) => "PermissionDenied",
+            RpcError::Status { .. } => "Status",
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref RECORDER: RwLock<Option<Arc<dyn MetricsRecorder>>> = RwLock::new(None);
+}
+
+/// Sets the recorder for all generated clients and servers
+pub fn set_recorder<R: MetricsRecorder + 'static>(recorder: R) {
+    *RECORDER.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(recorder));
+}
+
+/// Removes the recorder set with `set_recorder`
+pub fn clear_recorder() {
+    *RECORDER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Returns the recorder set with `set_recorder`
+pub fn recorder() -> Option<Arc<dyn MetricsRecorder>> {
+    RECORDER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Sizes and declared error of a call in progress, filled in by generated code
+#[derive(Debug, Default)]
+pub struct CallInfo {
+    request_bytes: AtomicUsize,
+    response_bytes: AtomicUsize,
+    error: Mutex<Option<&'static str>>,
+}
+
+impl CallInfo {
+    /// Constructs a CallInfo with the request size
+    pub fn new(request_bytes: usize) -> Self {
+        let info = CallInfo::default();
+        info.set_request(request_bytes);
+        info
+    }
+
+    /// Sets the request size
+    pub fn set_request(&self, bytes: usize) {
+        self.request_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Sets the response size
+    pub fn set_response(&self, bytes: usize) {
+        self.response_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Sets the error of a call whose declared error is returned in the response
+    pub fn set_error(&self, kind: &'static str) {
+        *self.error.lock().unwrap_or_else(|e| e.into_inner()) = Some(kind);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> Option<std::time::Instant> {
+    Some(std::time::Instant::now())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now() -> Option<std::time::Instant> {
+    None
+}
+
+/// Runs the call and reports it to the recorder, if there is one.
+/// Used by generated code.
+pub async fn observe<T, E, F>(
+    side: Side,
+    service: &str,
+    method: &str,
+    info: &CallInfo,
+    call: F,
+) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: ErrorKind,
+{
+    let recorder = match recorder() {
+        Some(recorder) => recorder,
+        None => return call.await,
+    };
+    let start = now();
+    let result = call.await;
+    let declared = *info.error.lock().unwrap_or_else(|e| e.into_inner());
+    recorder.record(&CallEvent {
+        side,
+        service,
+        method,
+        request_bytes: info.request_bytes.load(Ordering::Relaxed),
+        response_bytes: info.response_bytes.load(Ordering::Relaxed),
+        duration: start.map(|s| s.elapsed()).unwrap_or_default(),
+        error: result.as_ref().err().map(E::error_kind).or(declared),
+    });
+    result
+}
+
+/// Totals for one method
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MethodStats {
+    /// Number of calls
+    pub calls: u64,
+    /// Number of failed calls, by error variant
+    pub errors: BTreeMap<String, u64>,
+    /// Total size of requests
+    pub request_bytes: u64,
+    /// Total size of responses
+    pub response_bytes: u64,
+    /// Total duration of all calls
+    pub duration: Duration,
+    /// Longest call
+    pub max_duration: Duration,
+}
+
+impl MethodStats {
+    /// Returns the total number of failed calls
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+/// Recorder that keeps totals per method in memory.
+/// Clones share the same totals, so one clone can be registered
+/// with `set_recorder` and another used to read the results.
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryRecorder {
+    stats: Arc<Mutex<BTreeMap<(Side, String), MethodStats>>>,
+}
+
+impl InMemoryRecorder {
+    /// Constructs an empty recorder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<(Side, String), MethodStats>> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the totals for a method, named `Service.Method`
+    pub fn stats(&self, side: Side, method: &str) -> Option<MethodStats> {
+        self.lock().get(&(side, method.to_string())).cloned()
+    }
+
+    /// Returns the totals for all methods
+    pub fn snapshot(&self) -> BTreeMap<(Side, String), MethodStats> {
+        self.lock().clone()
+    }
+
+    /// Clears all totals
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    /// Formats the totals in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let stats = self.snapshot();
+        let mut out = String::new();
+        let mut metric =
+            |name: &str, kind: &str, help: &str, value: &dyn Fn(&MethodStats) -> f64| {
+                let _ = writeln!(out, "# HELP {} {}", name, help);
+                let _ = writeln!(out, "# TYPE {} {}", name, kind);
+                for ((side, method), s) in stats.iter() {
+                    let _ = writeln!(
+                        out,
+                        "{}{{side=\"{}\",method=\"{}\"}} {}",
+                        name,
+                        side.as_str(),
+                        escape(method),
+                        value(s)
+                    );
+                }
+            };
+        metric(
+            "frodobuf_calls_total",
+            "counter",
+            "Number of rpc calls",
+            &|s| s.calls as f64,
+        );
+        metric(
+            "frodobuf_request_bytes_total",
+            "counter",
+            "Total size of requests",
+            &|s| s.request_bytes as f64,
+        );
+        metric(
+            "frodobuf_response_bytes_total",
+            "counter",
+            "Total size of responses",
+            &|s| s.response_bytes as f64,
+        );
+        metric(
+            "frodobuf_call_duration_seconds_total",
+            "counter",
+            "Total duration of calls",
+            &|s| s.duration.as_secs_f64(),
+        );
+        metric(
+            "frodobuf_call_duration_seconds_max",
+            "gauge",
+            "Longest call",
+            &|s| s.max_duration.as_secs_f64(),
+        );
+        let _ = writeln!(out, "# HELP frodobuf_errors_total Number of failed calls");
+        let _ = writeln!(out, "# TYPE frodobuf_errors_total counter");
+        for ((side, method), s) in stats.iter() {
+            for (error, count) in s.errors.iter() {
+                let _ = writeln!(
+                    out,
+                    "frodobuf_errors_total{{side=\"{}\",method=\"{}\",error=\"{}\"}} {}",
+                    side.as_str(),
+                    escape(method),
+                    escape(error),
+                    count
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Escapes a Prometheus label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl MetricsRecorder for InMemoryRecorder {
+    fn record(&self, event: &CallEvent<'_>) {
+        let key = (event.side, format!("{}.{}", event.service, event.method));
+        let mut stats = self.lock();
+        let s = stats.entry(key).or_default();
+        s.calls += 1;
+        s.request_bytes += event.request_bytes as u64;
+        s.response_bytes += event.response_bytes as u64;
+        s.duration += event.duration;
+        s.max_duration = s.max_duration.max(event.duration);
+        if let Some(error) = event.error {
+            *s.errors.entry(error.to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// Serves the recorder's totals in the Prometheus text format to every HTTP request
+/// on the listener, until an error occurs.
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+pub async fn serve_prometheus(
+    recorder: InMemoryRecorder,
+    listener: tokio::net::TcpListener,
+) -> std::io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let body = recorder.to_prometheus();
+        tokio::spawn(async move {
+            // the request is not needed, but is read so the client doesn't see a reset
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(header.as_bytes()).await;
+            let _ = stream.write_all(body.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn in_memory() {
+        let rec = InMemoryRecorder::new();
+        let event = |error| CallEvent {
+            side: Side::Server,
+            service: "Store",
+            method: "Get",
+            request_bytes: 10,
+            response_bytes: 4,
+            duration: Duration::from_millis(5),
+            error,
+        };
+        rec.record(&event(None));
+        rec.record(&event(Some("NotFound")));
+        let s = rec.stats(Side::Server, "Store.Get").unwrap();
+        assert_eq!((s.calls, s.request_bytes, s.error_count()), (2, 20, 1));
+        assert_eq!(s.duration, Duration::from_millis(10));
+        assert!(rec.stats(Side::Client, "Store.Get").is_none());
+
+        let text = rec.to_prometheus();
+        assert!(text.contains("frodobuf_calls_total{side=\"server\",method=\"Store.Get\"} 2\n"));
+        assert!(text.contains(
+            "frodobuf_errors_total{side=\"server\",method=\"Store.Get\",error=\"NotFound\"} 1\n"
+        ));
+    }
+}
@@ -0,0 +1,379 @@
+//! Runtime support for generated service mocks
+//!
+//! For each service, the code generator emits a `MockXxx` struct (when the interface crate
+//! is built with the `mock` feature) containing one [`MockMethod`] per rpc method.
+//! Tests configure responses and expectations on those fields, and the mock records
+//! each call so arguments can be checked afterwards.
+//!
+//! ```rust
+//!   use frodobuf::mock::MockMethod;
+//!   let get: MockMethod<String, u32> = MockMethod::new("Store.get");
+//!   get.returns(42).times(1);
+//!   assert_eq!(get.call("x".to_string()).unwrap(), 42);
+//!   get.assert_called_with(&"x".to_string());
+//!   assert!(get.verify().is_ok());
+//! ```
+use crate::{common::context, Message, MessageDispatch, RpcError, Transport};
+use async_trait::async_trait;
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+// shared, so they can be called without holding the lock
+type Responder<A, R, E> = Arc<dyn Fn(&A) -> Result<R, E> + Send + Sync>;
+type Matcher<A> = Arc<dyn Fn(&A) -> bool + Send + Sync>;
+
+struct MockState<A, R, E> {
+    /// arguments of all calls, in order received
+    calls: Vec<A>,
+    /// responses used once each, before falling back to `always`
+    once: VecDeque<Responder<A, R, E>>,
+    /// response used after `once` is exhausted
+    always: Option<Responder<A, R, E>>,
+    /// optional argument check
+    matcher: Option<Matcher<A>>,
+    /// expected number of calls, checked by verify()
+    expected_calls: Option<usize>,
+    /// descriptions of calls that failed an expectation
+    failures: Vec<String>,
+}
+
+impl<A, R, E> Default for MockState<A, R, E> {
+    fn default() -> Self {
+        MockState {
+            calls: Vec::new(),
+            once: VecDeque::new(),
+            always: None,
+            matcher: None,
+            expected_calls: None,
+            failures: Vec::new(),
+        }
+    }
+}
+
+/// Expectations, canned responses, and recorded calls for one mocked rpc method.
+/// `A` is the method's argument type (`()` if it takes no parameters),
+/// `R` is its return type, and `E` is its error type, which is an enum of
+/// the declared error types for methods with `throws`.
+pub struct MockMethod<A, R, E = RpcError> {
+    /// Method name, in the form 'Service.method', used in error messages
+    name: &'static str,
+    state: Mutex<MockState<A, R, E>>,
+}
+
+impl<A, R, E> fmt::Debug for MockMethod<A, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockMethod")
+            .field("name", &self.name)
+            .field("calls", &self.call_count())
+            .finish()
+    }
+}
+
+impl<A, R, E> MockMethod<A, R, E> {
+    /// Constructs a mock method with no responses or expectations
+    pub fn new(name: &'static str) -> Self {
+        MockMethod {
+            name,
+            state: Mutex::new(MockState::default()),
+        }
+    }
+
+    /// Returns the method name
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    // a panic in a test closure shouldn't make the mock unusable
+    fn lock(&self) -> MutexGuard<'_, MockState<A, R, E>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns `value` for every call that isn't handled by a one-time response
+    pub fn returns(&self, value: R) -> &Self
+    where
+        R: Clone + Send + Sync + 'static,
+    {
+        self.lock().always = Some(Arc::new(move |_| Ok(value.clone())));
+        self
+    }
+
+    /// Returns `value` for the next call only. One-time responses are used in the order
+    /// they were added, before any response set with `returns`, `fails`, or `returning`.
+    pub fn returns_once(&self, value: R) -> &Self
+    where
+        R: Send + Sync + 'static,
+        E: From<RpcError>,
+    {
+        let value = Mutex::new(Some(value));
+        self.lock().once.push_back(Arc::new(move |_| {
+            value
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take()
+                .ok_or_else(|| RpcError::Other("mock response already used".into()).into())
+        }));
+        self
+    }
+
+    /// Returns the error for every call that isn't handled by a one-time response
+    pub fn fails(&self, err: E) -> &Self
+    where
+        E: Clone + Send + Sync + 'static,
+    {
+        self.lock().always = Some(Arc::new(move |_| Err(err.clone())));
+        self
+    }
+
+    /// Returns the error for the next call only
+    pub fn fails_once(&self, err: E) -> &Self
+    where
+        E: Clone + Send + Sync + 'static,
+    {
+        self.lock()
+            .once
+            .push_back(Arc::new(move |_| Err(err.clone())));
+        self
+    }
+
+    /// Computes the response from the call argument
+    pub fn returning<F>(&self, f: F) -> &Self
+    where
+        F: Fn(&A) -> Result<R, E> + Send + Sync + 'static,
+    {
+        self.lock().always = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets an argument expectation. Calls whose argument does not satisfy the predicate
+    /// return `RpcError::InvalidParameter` and are reported by `verify`.
+    pub fn with<F>(&self, matcher: F) -> &Self
+    where
+        F: Fn(&A) -> bool + Send + Sync + 'static,
+    {
+        self.lock().matcher = Some(Arc::new(matcher));
+        self
+    }
+
+    /// Sets the number of calls expected before `verify`
+    pub fn times(&self, n: usize) -> &Self {
+        self.lock().expected_calls = Some(n);
+        self
+    }
+
+    /// Expects that the method is never called
+    pub fn never(&self) -> &Self {
+        self.times(0)
+    }
+
+    /// Records the call and returns the configured response.
+    /// This is invoked by the generated mock's trait implementation.
+    /// The matcher and responder run without holding the mock's lock,
+    /// so they may use the mock, for example to count calls.
+    pub fn call(&self, arg: A) -> Result<R, E>
+    where
+        E: From<RpcError>,
+    {
+        let matcher = self.lock().matcher.clone();
+        let matches = matcher.map(|m| m(&arg)).unwrap_or(true);
+        let responder = {
+            let mut state = self.lock();
+            if !matches {
+                let msg = format!(
+                    "{}: call {} argument did not match expectation",
+                    self.name,
+                    state.calls.len() + 1
+                );
+                state.failures.push(msg.clone());
+                state.calls.push(arg);
+                return Err(RpcError::InvalidParameter(msg).into());
+            }
+            state.once.pop_front().or_else(|| state.always.clone())
+        };
+        let result = match responder {
+            Some(responder) => responder(&arg),
+            None => Err(RpcError::NotImplemented.into()),
+        };
+        self.lock().calls.push(arg);
+        result
+    }
+
+    /// Returns the number of calls received
+    pub fn call_count(&self) -> usize {
+        self.lock().calls.len()
+    }
+
+    /// Returns a copy of the arguments of all calls received, in order
+    pub fn calls(&self) -> Vec<A>
+    where
+        A: Clone,
+    {
+        self.lock().calls.clone()
+    }
+
+    /// Returns the argument of the most recent call, or None if there were no calls
+    pub fn last_call(&self) -> Option<A>
+    where
+        A: Clone,
+    {
+        self.lock().calls.last().cloned()
+    }
+
+    /// Panics unless at least one call was received with an argument equal to `expected`
+    pub fn assert_called_with(&self, expected: &A)
+    where
+        A: PartialEq + fmt::Debug,
+    {
+        let state = self.lock();
+        if !state.calls.iter().any(|a| a == expected) {
+            panic!(
+                "{}: expected call with {:?}, received {:?}",
+                self.name, expected, state.calls
+            );
+        }
+    }
+
+    /// Panics unless at least one call was received with an argument satisfying the predicate.
+    /// Useful for generated message types, which do not implement PartialEq.
+    pub fn assert_called_matching<F>(&self, pred: F)
+    where
+        F: Fn(&A) -> bool,
+    {
+        let state = self.lock();
+        if !state.calls.iter().any(pred) {
+            panic!(
+                "{}: no call matched, out of {} received",
+                self.name,
+                state.calls.len()
+            );
+        }
+    }
+
+    /// Checks that the expected number of calls were received
+    /// and that all arguments met expectations
+    pub fn verify(&self) -> Result<(), String> {
+        let state = self.lock();
+        let mut errors = state.failures.clone();
+        if let Some(expected) = state.expected_calls {
+            if expected != state.calls.len() {
+                errors.push(format!(
+                    "{}: expected {} call(s), received {}",
+                    self.name,
+                    expected,
+                    state.calls.len()
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Clears recorded calls, responses, and expectations
+    pub fn reset(&self) {
+        *self.lock() = MockState::default();
+    }
+}
+
+/// Transport that delivers messages directly to a local `MessageDispatch` implementation,
+/// such as a generated mock, so that a generated client can be used in tests
+/// without a host.
+/// Clones share the same handler.
+#[derive(Debug, Default)]
+pub struct LoopbackTransport<D> {
+    handler: Arc<D>,
+}
+
+impl<D> Clone for LoopbackTransport<D> {
+    fn clone(&self) -> Self {
+        LoopbackTransport {
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+impl<D> LoopbackTransport<D> {
+    /// Constructs a transport that sends all messages to the handler
+    pub fn new(handler: D) -> Self {
+        LoopbackTransport {
+            handler: Arc::new(handler),
+        }
+    }
+
+    /// Returns a reference to the handler
+    pub fn handler(&self) -> &D {
+        &self.handler
+    }
+}
+
+#[async_trait]
+impl<D: MessageDispatch + Send + Sync> Transport for LoopbackTransport<D> {
+    async fn send(
+        &self,
+        ctx: &context::Context<'_>,
+        _config: &crate::client::ClientConfig,
+        req: Message<'_>,
+    ) -> std::result::Result<Message<'static>, RpcError> {
+        self.handler.dispatch(ctx, req).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn responses_in_order() {
+        let m: MockMethod<u32, String> = MockMethod::new("Svc.get");
+        m.returns_once("first".into()).returns("rest".into());
+        assert_eq!(m.call(1).unwrap(), "first");
+        assert_eq!(m.call(2).unwrap(), "rest");
+        assert_eq!(m.call(3).unwrap(), "rest");
+        assert_eq!(m.calls(), vec![1, 2, 3]);
+        m.assert_called_with(&2);
+    }
+
+    #[test]
+    fn unconfigured_and_failures() {
+        let m: MockMethod<(), ()> = MockMethod::new("Svc.ping");
+        assert!(matches!(m.call(()), Err(RpcError::NotImplemented)));
+        m.fails_once(RpcError::DeadlineExceeded);
+        assert!(matches!(m.call(()), Err(RpcError::DeadlineExceeded)));
+    }
+
+    #[test]
+    fn expectations() {
+        let m: MockMethod<u32, u32> = MockMethod::new("Svc.double");
+        m.returning(|x| Ok(x * 2)).with(|x| *x < 10).times(2);
+        assert_eq!(m.call(4).unwrap(), 8);
+        assert!(m.verify().is_err(), "too few calls");
+        assert!(matches!(m.call(11), Err(RpcError::InvalidParameter(_))));
+        let err = m.verify().unwrap_err();
+        assert!(err.contains("did not match"), "{}", err);
+
+        m.reset();
+        assert_eq!(m.call_count(), 0);
+        assert!(m.verify().is_ok());
+    }
+
+    #[test]
+    fn reentrant_responder() {
+        let m: Arc<MockMethod<u32, usize>> = Arc::new(MockMethod::new("Svc.count"));
+        // the responders and matcher can use the mock without deadlocking
+        let (once, always, matcher) = (m.clone(), m.clone(), m.clone());
+        m.with(move |_| matcher.verify().is_ok())
+            .returning(move |_| Ok(always.call_count()));
+        m.returns_once(0);
+        m.lock()
+            .once
+            .push_back(Arc::new(move |_| Ok(once.calls().len() + 100)));
+        assert_eq!(m.call(1).unwrap(), 0);
+        assert_eq!(m.call(2).unwrap(), 101);
+        assert_eq!(m.call(3).unwrap(), 2);
+        assert_eq!(m.calls(), vec![1, 2, 3]);
+    }
+}
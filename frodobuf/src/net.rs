@@ -0,0 +1,688 @@
+//! Tcp and unix socket transports, for services running as native processes
+//!
+//! [`Server`] listens on an [`Endpoint`] and dispatches each request to a
+//! [`MessageDispatch`] implementation, such as a generated `XxxServer` or mock.
+//! [`NetTransport`] is a [`Transport`] for generated clients that sends requests to a server.
+//! Both require a tokio runtime.
+//!
+//! Each message is a frame containing a 4-byte big-endian length followed by
+//! the serialized request or response. Requests carry an id, so a connection may have
+//! many requests in flight, and responses may arrive in any order. The client keeps a pool
+//! of connections and spreads requests over them, reconnecting when a connection is closed.
+//!
+//! ```ignore
+//!   let server = Server::bind(&Endpoint::tcp("127.0.0.1:0")).await?;
+//!   let endpoint = server.endpoint()?;
+//!   tokio::spawn(server.serve(Arc::new(MockStore::new())));
+//!
+//!   let client = StoreClient::new(ClientConfig::target("store"), NetTransport::new(endpoint));
+//!   let count = client.count(&Context::default()).await?;
+//! ```
+use crate::{
+    client, context, deserialize, serialize, ErrorEnvelope, Message, MessageDispatch, RpcError,
+    Transport,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt, io,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, oneshot, Mutex, Semaphore},
+};
+
+/// Largest frame accepted, in bytes
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Default number of connections kept by a `NetTransport`
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// Largest number of requests the server dispatches at once for one connection.
+/// When it is reached, the server stops reading requests from the connection
+/// until one of them completes.
+pub const MAX_CONNECTION_REQUESTS: usize = 64;
+
+/// Time the server waits after an error accepting a connection
+pub const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// Address of a server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Tcp address, as `host:port`
+    Tcp(String),
+    /// Path of a unix domain socket
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+impl Endpoint {
+    /// Constructs a tcp endpoint
+    pub fn tcp<A: Into<String>>(addr: A) -> Endpoint {
+        Endpoint::Tcp(addr.into())
+    }
+
+    /// Constructs a unix socket endpoint
+    #[cfg(unix)]
+    pub fn unix<P: Into<std::path::PathBuf>>(path: P) -> Endpoint {
+        Endpoint::Unix(path.into())
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "tcp://{}", addr),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    id: u64,
+    method: String,
+    #[serde(with = "serde_bytes")]
+    arg: Vec<u8>,
+    /// milliseconds remaining before the caller's deadline
+    timeout_ms: Option<u64>,
+    /// true if the server should not send a response
+    oneway: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    id: u64,
+    result: Result<serde_bytes::ByteBuf, ErrorEnvelope>,
+}
+
+/// Writes one length-prefixed frame
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, buf: &[u8]) -> io::Result<()> {
+    if buf.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame of {} bytes exceeds limit", buf.len()),
+        ));
+    }
+    writer.write_u32(buf.len() as u32).await?;
+    writer.write_all(buf).await?;
+    writer.flush().await
+}
+
+/// Reads one length-prefixed frame. Returns None if the stream ended between frames.
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let len = match reader.read_u32().await {
+        Ok(len) => len as usize,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes exceeds limit", len),
+        ));
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(Some(buf))
+}
+
+fn io_error(e: io::Error) -> RpcError {
+    RpcError::HostError(e.to_string())
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, std::path::PathBuf),
+}
+
+/// Server that accepts connections on an endpoint and dispatches requests
+pub struct Server {
+    listener: Listener,
+}
+
+impl Server {
+    /// Listens on the endpoint. For tcp, use port 0 to listen on any free port,
+    /// and `endpoint()` to get the bound address.
+    pub async fn bind(endpoint: &Endpoint) -> io::Result<Server> {
+        let listener = match endpoint {
+            Endpoint::Tcp(addr) => Listener::Tcp(TcpListener::bind(addr).await?),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                Listener::Unix(tokio::net::UnixListener::bind(path)?, path.clone())
+            }
+        };
+        Ok(Server { listener })
+    }
+
+    /// Returns the endpoint the server is listening on
+    pub fn endpoint(&self) -> io::Result<Endpoint> {
+        match &self.listener {
+            Listener::Tcp(l) => Ok(Endpoint::Tcp(l.local_addr()?.to_string())),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => Ok(Endpoint::Unix(path.clone())),
+        }
+    }
+
+    /// Accepts connections until the task is dropped. Each connection is served on its own task,
+    /// and each request is dispatched on its own task, so slow requests don't delay others.
+    /// Errors accepting a connection (such as running out of file descriptors) are logged,
+    /// and the server pauses for [`ACCEPT_ERROR_DELAY`] before accepting again.
+    pub async fn serve<D>(self, handler: Arc<D>) -> io::Result<()>
+    where
+        D: MessageDispatch + Send + Sync + 'static,
+    {
+        loop {
+            let accepted = match &self.listener {
+                Listener::Tcp(l) => l.accept().await.map(|(stream, _)| {
+                    // nodelay is only a latency hint
+                    let _ = stream.set_nodelay(true);
+                    tokio::spawn(serve_connection(stream, handler.clone()));
+                }),
+                #[cfg(unix)]
+                Listener::Unix(l, _) => l.accept().await.map(|(stream, _)| {
+                    tokio::spawn(serve_connection(stream, handler.clone()));
+                }),
+            };
+            if let Err(e) = accepted {
+                log::warn!("accepting connection failed: {}", e);
+                tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Reads requests from the connection until it is closed
+async fn serve_connection<S, D>(stream: S, handler: Arc<D>)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+    D: MessageDispatch + Send + Sync + 'static,
+{
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(MAX_CONNECTION_REQUESTS);
+    let requests = Arc::new(Semaphore::new(MAX_CONNECTION_REQUESTS));
+    let write_task = tokio::spawn(async move {
+        while let Some(frame) = rx.recv().await {
+            if write_frame(&mut writer, &frame).await.is_err() {
+                break;
+            }
+        }
+    });
+    loop {
+        // the next request is read when there is room to dispatch it
+        let permit = match requests.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => break,
+        };
+        let frame = match read_frame(&mut reader).await {
+            Ok(Some(frame)) => frame,
+            _ => break,
+        };
+        let req: Request = match deserialize(&frame) {
+            Ok(req) => req,
+            // the request id is unknown, so the client can't be told which request failed
+            Err(_) => break,
+        };
+        let handler = handler.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let ctx = context::Context {
+                deadline: req
+                    .timeout_ms
+                    .map(|ms| Instant::now() + Duration::from_millis(ms)),
+                ..Default::default()
+            };
+            let result = handler
+                .dispatch(
+                    &ctx,
+                    Message {
+                        method: &req.method,
+                        arg: Cow::Borrowed(&req.arg),
+                    },
+                )
+                .await;
+            if req.oneway {
+                return;
+            }
+            let resp = Response {
+                id: req.id,
+                result: result
+                    .map(|m| serde_bytes::ByteBuf::from(m.arg.into_owned()))
+                    .map_err(|e| e.to_envelope()),
+            };
+            if let Ok(buf) = serialize(&resp) {
+                let _ = tx.send(buf).await;
+            }
+            drop(permit);
+        });
+    }
+    drop(tx);
+    let _ = write_task.await;
+}
+
+type PendingSender = oneshot::Sender<Result<Vec<u8>, RpcError>>;
+
+/// Callers waiting for a response. `closed` is kept under the same lock as the callers,
+/// so a caller can't be added after the reader task has dropped them.
+#[derive(Default)]
+struct Pending {
+    calls: HashMap<u64, PendingSender>,
+    closed: bool,
+}
+
+type PendingMap = std::sync::Mutex<Pending>;
+
+/// One client connection, with a task reading responses
+struct Connection {
+    writer: Mutex<Box<dyn AsyncWrite + Send + Unpin>>,
+    pending: Arc<PendingMap>,
+    next_id: AtomicU64,
+}
+
+impl Connection {
+    async fn connect(endpoint: &Endpoint) -> io::Result<Connection> {
+        match endpoint {
+            Endpoint::Tcp(addr) => {
+                let stream = TcpStream::connect(addr).await?;
+                stream.set_nodelay(true)?;
+                Ok(Connection::start(stream))
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Ok(Connection::start(
+                tokio::net::UnixStream::connect(path).await?,
+            )),
+        }
+    }
+
+    fn start<S>(stream: S) -> Connection
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (mut reader, writer) = tokio::io::split(stream);
+        let pending: Arc<PendingMap> = Arc::new(Default::default());
+        let task_pending = pending.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(frame)) = read_frame(&mut reader).await {
+                let resp: Response = match deserialize(&frame) {
+                    Ok(resp) => resp,
+                    Err(_) => break,
+                };
+                let sender = lock(&task_pending).calls.remove(&resp.id);
+                if let Some(sender) = sender {
+                    let _ = sender.send(
+                        resp.result
+                            .map(|buf| buf.into_vec())
+                            .map_err(RpcError::from),
+                    );
+                }
+            }
+            let mut pending = lock(&task_pending);
+            pending.closed = true;
+            // dropping the senders wakes all waiting callers
+            pending.calls.clear();
+        });
+        Connection {
+            writer: Mutex::new(Box::new(writer)),
+            pending,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        lock(&self.pending).closed
+    }
+
+    async fn write(&self, req: &Request) -> Result<(), RpcError> {
+        let buf = serialize(req)?;
+        let mut writer = self.writer.lock().await;
+        write_frame(&mut *writer, &buf).await.map_err(|e| {
+            lock(&self.pending).closed = true;
+            io_error(e)
+        })
+    }
+
+    async fn call(
+        &self,
+        method: &str,
+        arg: &[u8],
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u8>, RpcError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut pending = lock(&self.pending);
+            if pending.closed {
+                return Err(connection_closed());
+            }
+            pending.calls.insert(id, tx);
+        }
+        let req = Request {
+            id,
+            method: method.to_string(),
+            arg: arg.to_vec(),
+            timeout_ms: remaining_ms(deadline)?,
+            oneway: false,
+        };
+        if let Err(e) = self.write(&req).await {
+            lock(&self.pending).calls.remove(&id);
+            return Err(e);
+        }
+        let response = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), rx).await {
+                Ok(response) => response,
+                Err(_) => {
+                    lock(&self.pending).calls.remove(&id);
+                    return Err(RpcError::DeadlineExceeded);
+                }
+            },
+            None => rx.await,
+        };
+        response.map_err(|_| connection_closed())?
+    }
+}
+
+fn connection_closed() -> RpcError {
+    RpcError::HostError("connection closed".into())
+}
+
+fn lock(pending: &PendingMap) -> std::sync::MutexGuard<'_, Pending> {
+    pending.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Returns the time until the deadline, or `DeadlineExceeded` if it has passed
+fn remaining_ms(deadline: Option<Instant>) -> Result<Option<u64>, RpcError> {
+    match deadline {
+        Some(deadline) => {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RpcError::DeadlineExceeded);
+            }
+            Ok(Some((deadline - now).as_millis() as u64))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Transport that sends requests to a [`Server`] over tcp or a unix socket.
+/// Connections are opened when first needed, and requests are spread over
+/// up to `pool_size` connections. Clones share the same connections.
+#[derive(Clone)]
+pub struct NetTransport {
+    inner: Arc<Pool>,
+}
+
+struct Pool {
+    endpoint: Endpoint,
+    slots: Vec<Mutex<Option<Arc<Connection>>>>,
+    next: AtomicUsize,
+}
+
+impl fmt::Debug for NetTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetTransport")
+            .field("endpoint", &self.inner.endpoint)
+            .field("pool_size", &self.inner.slots.len())
+            .finish()
+    }
+}
+
+impl NetTransport {
+    /// Constructs a transport with the default pool size
+    pub fn new(endpoint: Endpoint) -> NetTransport {
+        NetTransport::with_pool_size(endpoint, DEFAULT_POOL_SIZE)
+    }
+
+    /// Constructs a transport that uses up to `size` connections
+    pub fn with_pool_size(endpoint: Endpoint, size: usize) -> NetTransport {
+        NetTransport {
+            inner: Arc::new(Pool {
+                endpoint,
+                slots: (0..size.max(1)).map(|_| Mutex::new(None)).collect(),
+                next: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Returns the server endpoint
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.inner.endpoint
+    }
+
+    /// Returns an open connection, connecting if the next slot is empty or closed
+    async fn connection(&self) -> Result<Arc<Connection>, RpcError> {
+        let pool = &self.inner;
+        let index = pool.next.fetch_add(1, Ordering::Relaxed) % pool.slots.len();
+        let mut slot = pool.slots[index].lock().await;
+        match slot.as_ref() {
+            Some(conn) if !conn.is_closed() => Ok(conn.clone()),
+            _ => {
+                let conn = Arc::new(
+                    Connection::connect(&pool.endpoint)
+                        .await
+                        .map_err(io_error)?,
+                );
+                *slot = Some(conn.clone());
+                Ok(conn)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for NetTransport {
+    async fn send(
+        &self,
+        ctx: &context::Context<'_>,
+        _config: &client::ClientConfig,
+        req: Message<'_>,
+    ) -> std::result::Result<Message<'static>, RpcError> {
+        let conn = self.connection().await?;
+        let arg = conn
+            .call(req.method, req.arg.as_ref(), ctx.deadline)
+            .await?;
+        Ok(Message {
+            method: "_reply",
+            arg: Cow::Owned(arg),
+        })
+    }
+
+    async fn send_oneway(
+        &self,
+        ctx: &context::Context<'_>,
+        _config: &client::ClientConfig,
+        req: Message<'_>,
+    ) -> std::result::Result<(), RpcError> {
+        let conn = self.connection().await?;
+        conn.write(&Request {
+            id: 0,
+            method: req.method.to_string(),
+            arg: req.arg.into_owned(),
+            timeout_ms: remaining_ms(ctx.deadline)?,
+            oneway: true,
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::ClientConfig;
+
+    /// echoes the argument; "Sleep" waits for the number of milliseconds in the argument
+    struct Echo {}
+
+    #[async_trait]
+    impl MessageDispatch for Echo {
+        async fn dispatch(
+            &self,
+            _ctx: &context::Context<'_>,
+            message: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            match message.method {
+                "Echo" => Ok(Message {
+                    method: "Echo",
+                    arg: Cow::Owned(message.arg.into_owned()),
+                }),
+                "Sleep" => {
+                    let ms: u64 = deserialize(message.arg.as_ref())?;
+                    tokio::time::sleep(Duration::from_millis(ms)).await;
+                    Ok(Message {
+                        method: "Sleep",
+                        arg: Cow::Owned(serialize(&ms)?),
+                    })
+                }
+                _ => Err(RpcError::MethodNotHandled(message.method.to_string())),
+            }
+        }
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    async fn start(endpoint: Endpoint) -> NetTransport {
+        let server = Server::bind(&endpoint).await.unwrap();
+        let endpoint = server.endpoint().unwrap();
+        tokio::spawn(server.serve(Arc::new(Echo {})));
+        NetTransport::with_pool_size(endpoint, 2)
+    }
+
+    async fn call(t: &NetTransport, method: &str, arg: &[u8]) -> Result<Vec<u8>, RpcError> {
+        let ctx = context::Context::default();
+        let req = Message {
+            method,
+            arg: Cow::Borrowed(arg),
+        };
+        Ok(t.send(&ctx, &ClientConfig::target("x"), req)
+            .await?
+            .arg
+            .into_owned())
+    }
+
+    #[test]
+    fn tcp_concurrent() {
+        runtime().block_on(async {
+            let t = start(Endpoint::tcp("127.0.0.1:0")).await;
+            // a slow request doesn't hold up the others on the same connection
+            let slow = tokio::spawn({
+                let t = t.clone();
+                async move { call(&t, "Sleep", &serialize(&200u64).unwrap()).await }
+            });
+            let started = Instant::now();
+            for i in 0..20u8 {
+                assert_eq!(call(&t, "Echo", &[i]).await.unwrap(), vec![i]);
+            }
+            assert!(started.elapsed() < Duration::from_millis(200));
+            assert!(slow.await.unwrap().is_ok());
+            assert!(matches!(
+                call(&t, "Nope", b"").await,
+                Err(RpcError::MethodNotHandled(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn deadline() {
+        runtime().block_on(async {
+            let t = start(Endpoint::tcp("127.0.0.1:0")).await;
+            let ctx = context::Context {
+                deadline: Some(Instant::now() + Duration::from_millis(50)),
+                ..Default::default()
+            };
+            let arg = serialize(&1000u64).unwrap();
+            let req = Message {
+                method: "Sleep",
+                arg: Cow::Borrowed(&arg),
+            };
+            let res = t.send(&ctx, &ClientConfig::target("x"), req).await;
+            assert!(matches!(res, Err(RpcError::DeadlineExceeded)));
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket() {
+        let path = std::env::temp_dir().join(format!("frodobuf-net-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        runtime().block_on(async {
+            let t = start(Endpoint::unix(&path)).await;
+            assert_eq!(call(&t, "Echo", b"hi").await.unwrap(), b"hi".to_vec());
+        });
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn closed_connection() {
+        runtime().block_on(async {
+            let (client, server) = tokio::io::duplex(1024);
+            let conn = Connection::start(client);
+            drop(server);
+            while !conn.is_closed() {
+                tokio::task::yield_now().await;
+            }
+            // a call without a deadline fails instead of waiting forever
+            let res = conn.call("Echo", b"", None).await;
+            assert!(matches!(res, Err(RpcError::HostError(_))));
+        });
+    }
+
+    /// counts the requests it receives, and never completes them
+    struct Stall {
+        started: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl MessageDispatch for Stall {
+        async fn dispatch(
+            &self,
+            _ctx: &context::Context<'_>,
+            _message: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            self.started.fetch_add(1, Ordering::SeqCst);
+            futures::future::pending().await
+        }
+    }
+
+    #[test]
+    fn connection_request_limit() {
+        runtime().block_on(async {
+            let (mut client, server) = tokio::io::duplex(1024 * 1024);
+            let started = Arc::new(AtomicUsize::new(0));
+            tokio::spawn(serve_connection(
+                server,
+                Arc::new(Stall {
+                    started: started.clone(),
+                }),
+            ));
+            for id in 0..MAX_CONNECTION_REQUESTS as u64 + 10 {
+                let req = Request {
+                    id,
+                    method: "Stall".to_string(),
+                    arg: Vec::new(),
+                    timeout_ms: None,
+                    oneway: false,
+                };
+                write_frame(&mut client, &serialize(&req).unwrap())
+                    .await
+                    .unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            // requests past the limit are left unread
+            assert_eq!(started.load(Ordering::SeqCst), MAX_CONNECTION_REQUESTS);
+        });
+    }
+}
@@ -0,0 +1,166 @@
+//! Capability provider support
+//!
+//! A provider deriving `FrodobufProvider` must have a field named `dispatcher`
+//! of type [`DispatcherHandle`], and must implement [`ProviderLifecycle`].
+//! When the host calls `configure_dispatch`, the host's dispatcher is stored in the handle.
+//! The provider can then call actors (for example, to deliver subscribed messages)
+//! with any generated client, using a [`ProviderDispatcherTransport`]:
+//!
+//! ```rust,ignore
+//!   let transport = ProviderDispatcherTransport::new(self.dispatcher.clone());
+//!   let actor = ActorClient::new(ClientConfig::actor(actor_id), transport);
+//!   let status = actor.health_request(&ctx, &HealthCheckRequest {}).await?;
+//! ```
+use crate::{client, context, Message, RpcError, Transport};
+use async_trait::async_trait;
+use std::{
+    borrow::Cow,
+    sync::{Arc, RwLock},
+};
+
+pub mod prelude {
+    pub use super::{DispatcherHandle, ProviderDispatcherTransport, ProviderLifecycle};
+    pub use crate::{client, context, Message, MessageDispatch, RpcError, WasmHost};
+    pub use async_trait::async_trait;
+    pub use frodobuf_derive::FrodobufProvider;
+}
+
+/// Result of a call through the host dispatcher
+pub type DispatchResult = Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Sends a message from a provider to an actor.
+/// This has the same signature as the wasmcloud host `Dispatcher`, so that the
+/// `FrodobufProvider` derive can wrap the dispatcher passed to `configure_dispatch`.
+pub trait ActorDispatcher: Send + Sync {
+    /// Sends the serialized message `msg` for operation `op` to the actor
+    fn dispatch(&self, actor: &str, op: &str, msg: &[u8]) -> DispatchResult;
+}
+
+impl<F> ActorDispatcher for F
+where
+    F: Fn(&str, &str, &[u8]) -> DispatchResult + Send + Sync,
+{
+    fn dispatch(&self, actor: &str, op: &str, msg: &[u8]) -> DispatchResult {
+        self(actor, op, msg)
+    }
+}
+
+/// Holds the host dispatcher once it has been configured.
+/// Clones share the same dispatcher, so transports created before `configure_dispatch`
+/// is called will use the dispatcher when it is set.
+#[derive(Clone, Default)]
+pub struct DispatcherHandle {
+    inner: Arc<RwLock<Option<Arc<dyn ActorDispatcher>>>>,
+}
+
+impl std::fmt::Debug for DispatcherHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DispatcherHandle")
+            .field("configured", &self.is_configured())
+            .finish()
+    }
+}
+
+impl DispatcherHandle {
+    /// Stores the dispatcher, replacing any previous one
+    pub fn set<D: ActorDispatcher + 'static>(&self, dispatcher: D) {
+        *self.inner.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(dispatcher));
+    }
+
+    /// Stores a dispatch function, replacing any previous dispatcher
+    pub fn set_fn<F>(&self, dispatch: F)
+    where
+        F: Fn(&str, &str, &[u8]) -> DispatchResult + Send + Sync + 'static,
+    {
+        self.set(dispatch)
+    }
+
+    /// Returns the dispatcher, or None if it has not been configured
+    pub fn get(&self) -> Option<Arc<dyn ActorDispatcher>> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns true if the dispatcher has been configured
+    pub fn is_configured(&self) -> bool {
+        self.get().is_some()
+    }
+}
+
+/// Transport for calling actors from a capability provider, through the host dispatcher.
+/// The recipient actor is the `target` of the client config.
+#[derive(Clone, Debug)]
+pub struct ProviderDispatcherTransport {
+    dispatcher: DispatcherHandle,
+}
+
+impl ProviderDispatcherTransport {
+    /// Constructs a transport using the provider's dispatcher handle
+    pub fn new(dispatcher: DispatcherHandle) -> Self {
+        ProviderDispatcherTransport { dispatcher }
+    }
+}
+
+#[async_trait]
+impl Transport for ProviderDispatcherTransport {
+    async fn send(
+        &self,
+        _ctx: &context::Context<'_>,
+        config: &client::ClientConfig,
+        req: Message<'_>,
+    ) -> std::result::Result<Message<'static>, RpcError> {
+        let dispatcher = self.dispatcher.get().ok_or(RpcError::NotInitialized)?;
+        let res = dispatcher
+            .dispatch(&config.target, req.method, req.arg.as_ref())
+            .map_err(|e| RpcError::from_error_bytes(e.to_string().as_bytes()))?;
+        Ok(Message {
+            method: "_reply",
+            arg: Cow::Owned(res),
+        })
+    }
+}
+
+/// Lifecycle methods of a capability provider, called by the host through the
+/// `CapabilityProvider` implementation generated by the `FrodobufProvider` derive.
+pub trait ProviderLifecycle {
+    /// Called when the provider is being removed from the host, to release
+    /// resources and stop any running threads. This must not panic, and should not
+    /// write to stdout, as the host process may be terminating.
+    fn stop(&self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn send(transport: &ProviderDispatcherTransport) -> Result<Message<'static>, RpcError> {
+        futures::executor::block_on(transport.send(
+            &context::Context::default(),
+            &client::ClientConfig::actor("MACTOR"),
+            Message {
+                method: "Ping",
+                arg: Cow::Borrowed(b"hi"),
+            },
+        ))
+    }
+
+    #[test]
+    fn dispatch_after_configure() {
+        let handle = DispatcherHandle::default();
+        let transport = ProviderDispatcherTransport::new(handle.clone());
+        assert!(matches!(send(&transport), Err(RpcError::NotInitialized)));
+
+        handle.set_fn(|actor: &str, op: &str, msg: &[u8]| {
+            Ok(format!("{} {} {}", actor, op, String::from_utf8_lossy(msg)).into_bytes())
+        });
+        let reply = send(&transport).unwrap();
+        assert_eq!(reply.arg.as_ref(), b"MACTOR Ping hi");
+    }
+
+    #[test]
+    fn dispatch_error() {
+        let handle = DispatcherHandle::default();
+        handle.set_fn(|_: &str, _: &str, _: &[u8]| Err("no such actor".into()));
+        let res = send(&ProviderDispatcherTransport::new(handle));
+        assert!(matches!(res, Err(RpcError::HostError(ref e)) if e == "no such actor"));
+    }
+}
@@ -1,7 +1,10 @@
 //! Code generation
 //!
 use crate::codegen::CodegenError;
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 //use chrono::DateTime;
 use handlebars::{Handlebars, JsonValue};
 //use serde_json::Value as JsonValue;
@@ -18,6 +21,11 @@ pub type Template<'template> = (&'template str, &'template str);
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum OutputLanguage {
     Rust,
+    AssemblyScript,
+    TinyGo,
+    C,
+    TypeScript,
+    Python,
 }
 
 impl Default for OutputLanguage {
@@ -32,6 +40,11 @@ impl std::str::FromStr for OutputLanguage {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "rust" => Ok(OutputLanguage::Rust),
+            "assemblyscript" | "as" => Ok(OutputLanguage::AssemblyScript),
+            "tinygo" | "go" => Ok(OutputLanguage::TinyGo),
+            "c" => Ok(OutputLanguage::C),
+            "typescript" | "ts" => Ok(OutputLanguage::TypeScript),
+            "python" | "py" => Ok(OutputLanguage::Python),
             _ => Err(CodegenError::InvalidParameter(format!(
                 "Unsupported language {}",
                 s
@@ -50,6 +63,13 @@ pub struct RenderConfig<'render> {
 
     /// Output language
     pub language: OutputLanguage,
+
+    /// Optional directory of user templates. Each `<name>.hbs` file is registered
+    /// as template `<name>`, replacing the built-in template of that name (e.g. `rust-service.hbs`),
+    /// or adding a partial. Templates in subdirectories are named by relative path (`dir/name`).
+    /// Files in the `output` subdirectory are extra output files: `output/<path>.hbs` is rendered
+    /// to `<path>` in the output directory, along with the schema code.
+    pub template_dir: Option<PathBuf>,
 }
 
 impl<'render> Default for RenderConfig<'render> {
@@ -58,6 +78,7 @@ impl<'render> Default for RenderConfig<'render> {
             templates: Vec::new(),
             strict_mode: true,
             language: OutputLanguage::Rust,
+            template_dir: None,
         }
     }
 }
@@ -71,6 +92,9 @@ pub struct Renderer<'gen> {
 
     /// lanaguage for codegen
     language: OutputLanguage,
+
+    /// extra output files from the template directory, relative to the output dir
+    outputs: Vec<String>,
 }
 
 impl<'gen> Default for Renderer<'gen> {
@@ -89,11 +113,8 @@ impl<'gen> Renderer<'gen> {
         hb.set_strict_mode(config.strict_mode);
         hb.register_escape_fn(handlebars::no_escape); //html escaping is the default and cause issue0
 
-        // add common helpers and templates
+        // add common helpers
         add_base_helpers(&mut hb);
-        for t in &config.templates {
-            hb.register_template_string(t.0, t.1)?;
-        }
 
         // add language-specific helpers and templates
         match &config.language {
@@ -101,12 +122,54 @@ impl<'gen> Renderer<'gen> {
                 crate::codegen::rust::add_helpers(&mut hb)?;
                 crate::codegen::rust::add_templates(&mut hb)?;
             }
+            OutputLanguage::AssemblyScript => {
+                crate::codegen::assemblyscript::add_helpers(&mut hb)?;
+                crate::codegen::assemblyscript::add_templates(&mut hb)?;
+            }
+            OutputLanguage::TinyGo => {
+                crate::codegen::tinygo::add_helpers(&mut hb)?;
+                crate::codegen::tinygo::add_templates(&mut hb)?;
+            }
+            OutputLanguage::C => {
+                crate::codegen::c::add_helpers(&mut hb)?;
+                crate::codegen::c::add_templates(&mut hb)?;
+            }
+            OutputLanguage::TypeScript => {
+                crate::codegen::typescript::add_helpers(&mut hb)?;
+                crate::codegen::typescript::add_templates(&mut hb)?;
+            }
+            OutputLanguage::Python => {
+                crate::codegen::python::add_helpers(&mut hb)?;
+                crate::codegen::python::add_templates(&mut hb)?;
+            }
+        }
+
+        // user templates are added last, so they replace built-in templates with the same name
+        for t in &config.templates {
+            hb.register_template_string(t.0, t.1)?;
+        }
+        let mut outputs = Vec::new();
+        if let Some(dir) = &config.template_dir {
+            for (name, path) in find_templates(dir, Path::new(""))? {
+                let template = std::fs::read_to_string(&path).map_err(|e| {
+                    CodegenError::InvalidParameter(format!(
+                        "reading template '{}': {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                if let Some(file) = name.strip_prefix("output/") {
+                    outputs.push(file.to_string());
+                }
+                hb.register_template_string(&name, template)?;
+            }
         }
 
         let renderer = Self {
             hb,
             vars: VarMap::default(),
             language: config.language,
+            outputs,
         };
         Ok(renderer)
     }
@@ -155,11 +218,76 @@ impl<'gen> Renderer<'gen> {
             OutputLanguage::Rust => {
                 crate::codegen::rust::codegen_schema_rust(self, schema, output_dir)?;
             }
+            OutputLanguage::AssemblyScript => {
+                crate::codegen::assemblyscript::codegen_schema_assemblyscript(
+                    self, schema, output_dir,
+                )?;
+            }
+            OutputLanguage::TinyGo => {
+                crate::codegen::tinygo::codegen_schema_tinygo(self, schema, output_dir)?;
+            }
+            OutputLanguage::C => {
+                crate::codegen::c::codegen_schema_c(self, schema, output_dir)?;
+            }
+            OutputLanguage::TypeScript => {
+                crate::codegen::typescript::codegen_schema_typescript(self, schema, output_dir)?;
+            }
+            OutputLanguage::Python => {
+                crate::codegen::python::codegen_schema_python(self, schema, output_dir)?;
+            }
+        }
+        for file in self.outputs.iter() {
+            let path = output_dir.join(file);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut out = std::fs::File::create(&path)?;
+            self.render(&format!("output/{}", file), &mut out)?;
         }
         Ok(())
     }
 }
 
+/// Lists the `.hbs` files in a template directory and its subdirectories, in name order.
+/// Returns the template name, which is the path relative to the template directory,
+/// without the `.hbs` extension and with '/' separators, and the path of the file.
+/// Symbolic links to directories are skipped, since they could form a loop.
+fn find_templates(dir: &Path, prefix: &Path) -> Result<Vec<(String, PathBuf)>, CodegenError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        CodegenError::InvalidParameter(format!(
+            "reading template directory '{}': {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    let mut paths = entries
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    paths.sort();
+    let mut templates = Vec::new();
+    for path in paths {
+        let file_name = match path.file_name() {
+            Some(name) => Path::new(name),
+            None => continue,
+        };
+        let file_type = std::fs::symlink_metadata(&path)?.file_type();
+        if file_type.is_dir() {
+            templates.extend(find_templates(&path, &prefix.join(file_name))?);
+        } else if file_type.is_symlink() && path.is_dir() {
+            continue;
+        } else if path.extension().map(|ext| ext == "hbs").unwrap_or_default() {
+            let name = prefix.join(file_name).with_extension("");
+            let name = name
+                .iter()
+                .map(|c| c.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            templates.push((name, path));
+        }
+    }
+    Ok(templates)
+}
+
 /// Convert Value to string without adding quotes around strings
 fn json_value_to_string(v: &JsonValue) -> String {
     match v {
@@ -199,8 +327,14 @@ pub fn ident_to_string(v: &JsonValue) -> Result<String, handlebars::RenderError>
 
 /// Add template helpers functions
 ///  'join-csv' turns array of values into comma-separate list
+///  'len' returns the length of an array
+///  'has-attribute' and 'attribute-value' query attributes of a model object
 ///  'format-date' rewrites an ISO8601-formatted date into another format
 fn add_base_helpers(hb: &mut Handlebars) {
+    use crate::{
+        codegen::{get_attributes, param},
+        strings::unquote,
+    };
     use handlebars::{Context, Helper, HelperResult, Output, RenderContext, RenderError};
 
     // "snake-case" converts a simple identifier to snake_case
@@ -326,6 +460,77 @@ fn add_base_helpers(hb: &mut Handlebars) {
             },
         ),
     );
+    // "len" outputs the number of items in an array: {{ len fields }}
+    hb.register_helper(
+        "len",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let len = param(h, 0)?
+                    .as_array()
+                    .ok_or_else(|| RenderError::new("expected array"))?
+                    .len();
+                out.write(&len.to_string())?;
+                Ok(())
+            },
+        ),
+    );
+
+    // "has-attribute" outputs "true" if the object has the attribute,
+    // for use in conditionals: {{#if (has-attribute this "idempotent")}}
+    hb.register_helper(
+        "has-attribute",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let obj = param(h, 0)?;
+                let key = param(h, 1)?
+                    .as_str()
+                    .ok_or_else(|| RenderError::new("attribute name must be a string"))?;
+                if get_attributes(obj)?.iter().any(|a| a.key.name == key) {
+                    out.write("true")?;
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    // "attribute-value" outputs the value of a name=value pair of an attribute, or nothing:
+    // {{attribute-value this "http" "path"}}
+    hb.register_helper(
+        "attribute-value",
+        Box::new(
+            |h: &Helper,
+             _r: &Handlebars,
+             _: &Context,
+             _rc: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let obj = param(h, 0)?;
+                let (key, name) = match (param(h, 1)?.as_str(), param(h, 2)?.as_str()) {
+                    (Some(key), Some(name)) => (key, name),
+                    _ => return Err(RenderError::new("attribute and name must be strings")),
+                };
+                if let Some(value) = get_attributes(obj)?
+                    .iter()
+                    .filter(|a| a.key.name == key)
+                    .find_map(|a| a.get(name))
+                {
+                    out.write(unquote(&value.to_string()))?;
+                }
+                Ok(())
+            },
+        ),
+    );
+
     //
     // format-date: strftime-like function to reformat date
     hb.register_helper(
@@ -379,3 +584,67 @@ fn initializers() {
     );
     assert!(true);
 }
+
+#[test]
+fn template_dir_overrides() {
+    let dir = std::env::temp_dir().join(format!("frodobuf-templates-{}", std::process::id()));
+    let templates = dir.join("templates");
+    let output = dir.join("out");
+    std::fs::create_dir_all(templates.join("output/docs")).unwrap();
+    std::fs::create_dir_all(&output).unwrap();
+    std::fs::write(
+        templates.join("banner.hbs"),
+        "# package {{ schema.namespace.name }}",
+    )
+    .unwrap();
+    std::fs::write(templates.join("py-service.hbs"), "{{> banner}}\n# custom").unwrap();
+    std::fs::write(
+        templates.join("output/docs/README.md.hbs"),
+        "{{ schema.namespace.name }} docs",
+    )
+    .unwrap();
+    std::fs::write(templates.join("notes.txt"), "not a template").unwrap();
+
+    let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+    let idl = std::fs::read_to_string(testdata.join("store.midl")).unwrap();
+    let schema = midl_parser::parse_string(&idl).unwrap().schema;
+    let mut r = Renderer::init(&RenderConfig {
+        language: OutputLanguage::Python,
+        template_dir: Some(templates),
+        ..Default::default()
+    })
+    .unwrap();
+    r.set("schema", serde_json::to_value(&schema).unwrap());
+    r.codegen_for_schema(&schema, &output).unwrap();
+
+    let read = |file: &str| std::fs::read_to_string(output.join(file)).unwrap();
+    assert_eq!(read("store.py"), "# package store\n# custom");
+    assert!(read("frodobuf.py").contains("class Client"));
+    assert_eq!(read("docs/README.md"), "store docs");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let missing = Renderer::init(&RenderConfig {
+        template_dir: Some(dir.join("missing")),
+        ..Default::default()
+    });
+    assert!(matches!(missing, Err(CodegenError::InvalidParameter(_))));
+}
+
+#[cfg(unix)]
+#[test]
+fn template_dir_symlink_loop() {
+    let dir = std::env::temp_dir().join(format!("frodobuf-symlinks-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("sub/page.hbs"), "page").unwrap();
+    std::os::unix::fs::symlink(&dir, dir.join("sub/loop")).unwrap();
+    std::os::unix::fs::symlink(dir.join("sub/page.hbs"), dir.join("link.hbs")).unwrap();
+
+    let names = find_templates(&dir, Path::new(""))
+        .unwrap()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    // linked files are found, but not linked directories
+    assert_eq!(names, vec!["link", "sub/page"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,1036 @@
+//! Streaming rpc support
+//!
+//! Streaming methods are declared in midl with the `stream` keyword:
+//! ```text
+//!   rpc watch(Filter) returns (stream Event);      // server streaming
+//!   rpc upload(stream Chunk) returns (Summary);    // client streaming
+//!   rpc chat(stream Note) returns (stream Note);   // bidirectional
+//! ```
+//! Streams are carried over any [`Transport`] as a sequence of request/response messages,
+//! each with the method name of the streaming method. The client sends a [`StreamFrame`]
+//! and the server answers with a [`StreamReply`]:
+//!
+//! - `Open` starts the call, and carries the request of a server-streaming method.
+//!   The server replies `Opened` with a stream id.
+//! - `Data` sends one item of the request stream. The server buffers it and replies `Ack`.
+//! - `End` marks the end of the request stream, and the server invokes the handler.
+//!   A client-streaming method replies with `Response`; a bidirectional method replies `Ack`.
+//! - `Next` pulls one item of the response stream. The server replies `Item`,
+//!   or `End` when the stream is finished.
+//! - `Cancel` discards the call on the server, and is sent by [`ItemStream::cancel`],
+//!   or when a response stream is dropped before its end.
+//!
+//! Frames after `Open` are accepted only from the caller (`ctx.actor`) that opened the call,
+//! and for the same method. The server keeps at most [`MAX_STREAMS`] calls, and
+//! [`MAX_CALLER_STREAMS`] calls per caller; further calls are refused until one ends.
+//! The request items of a call are limited by [`MAX_STREAM_ITEMS`] and [`MAX_STREAM_INPUT_LEN`].
+//!
+//! Bidirectional methods are half-duplex: the request stream is sent completely
+//! before the handler is invoked and the response stream starts.
+//!
+use crate::{client, context, deserialize, serialize, Message, RpcError, Transport};
+use futures::{
+    future::BoxFuture,
+    stream::{BoxStream, Stream, StreamExt},
+    Future,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+/// Message sent by a client to a streaming method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StreamFrame {
+    /// Starts a call. Contains the serialized request of a server-streaming method,
+    /// and is empty for methods with a request stream.
+    Open(#[serde(with = "serde_bytes")] Vec<u8>),
+    /// One serialized item of the request stream
+    Data {
+        id: u64,
+        #[serde(with = "serde_bytes")]
+        item: Vec<u8>,
+    },
+    /// End of the request stream
+    End { id: u64 },
+    /// Requests the next item of the response stream
+    Next { id: u64 },
+    /// Cancels the call
+    Cancel { id: u64 },
+}
+
+/// Message returned by the server of a streaming method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StreamReply {
+    /// The call was started, with the id used for subsequent frames
+    Opened { id: u64 },
+    /// The frame was accepted
+    Ack,
+    /// One serialized item of the response stream
+    Item(#[serde(with = "serde_bytes")] Vec<u8>),
+    /// Serialized response of a client-streaming method
+    Response(#[serde(with = "serde_bytes")] Vec<u8>),
+    /// End of the response stream
+    End,
+}
+
+type CancelFn = Box<dyn FnOnce() -> BoxFuture<'static, Result<(), RpcError>> + Send>;
+
+/// A stream of items used for the input or output of streaming methods
+pub struct ItemStream<T> {
+    inner: BoxStream<'static, Result<T, RpcError>>,
+    cancel: Option<CancelFn>,
+}
+
+impl<T> std::fmt::Debug for ItemStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ItemStream")
+            .field("remote", &self.cancel.is_some())
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> ItemStream<T> {
+    /// Constructs an ItemStream from any stream of results
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<T, RpcError>> + Send + 'static,
+    {
+        ItemStream {
+            inner: stream.boxed(),
+            cancel: None,
+        }
+    }
+
+    /// Constructs an ItemStream that yields each of the items
+    pub fn from_items<I>(items: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'static,
+    {
+        Self::new(futures::stream::iter(items.into_iter().map(Ok)))
+    }
+
+    /// Constructs an ItemStream with no items
+    pub fn empty() -> Self {
+        Self::new(futures::stream::empty())
+    }
+}
+
+impl<T> ItemStream<T> {
+    /// Stops the stream. If the stream is the response of a remote call,
+    /// the server is told to discard the call.
+    pub async fn cancel(mut self) -> Result<(), RpcError> {
+        match self.cancel.take() {
+            Some(cancel) => cancel().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T> Stream for ItemStream<T> {
+    type Item = Result<T, RpcError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = this.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(None) = item {
+            // the server has discarded the call
+            this.cancel = None;
+        }
+        item
+    }
+}
+
+impl<T> Drop for ItemStream<T> {
+    /// Cancels a remote stream that was dropped before its end. The cancel frame is sent
+    /// in a tokio task if it doesn't complete immediately, or else is abandoned
+    /// (the server discards idle calls, see [`STREAM_IDLE_FRAMES`]).
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            use futures::FutureExt as _;
+            let mut cancel = cancel();
+            if (&mut cancel).now_or_never().is_none() {
+                #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+                if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                    runtime.spawn(cancel);
+                }
+            }
+        }
+    }
+}
+
+/// Collects all items of the stream, stopping at the first error
+pub async fn collect<T>(mut stream: ItemStream<T>) -> Result<Vec<T>, RpcError> {
+    let mut items = Vec::new();
+    while let Some(item) = stream.next().await {
+        items.push(item?);
+    }
+    Ok(items)
+}
+
+//
+// Server side
+//
+
+/// Largest number of streaming calls kept by the server. When it is reached,
+/// new calls are refused.
+pub const MAX_STREAMS: usize = 4096;
+
+/// Largest number of streaming calls kept for one caller (`ctx.actor`).
+pub const MAX_CALLER_STREAMS: usize = 64;
+
+/// Largest number of request items buffered for one call
+pub const MAX_STREAM_ITEMS: usize = 65536;
+
+/// Largest total size, in bytes, of the request items buffered for one call
+pub const MAX_STREAM_INPUT_LEN: usize = 16 * 1024 * 1024;
+
+/// Number of stream frames, of any caller, after which an unused call is considered
+/// abandoned. Abandoned calls are discarded when a limit would refuse a new call.
+pub const STREAM_IDLE_FRAMES: u64 = 1 << 20;
+
+/// state of a streaming call on the server
+enum StreamState {
+    /// request items received so far, and their total size
+    Input { items: Vec<Vec<u8>>, len: usize },
+    /// serialized response items
+    Output(BoxStream<'static, Result<Vec<u8>, RpcError>>),
+    /// response stream is being polled
+    Busy,
+}
+
+impl StreamState {
+    fn input() -> Self {
+        StreamState::Input {
+            items: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+/// a streaming call on the server
+struct ServerStream {
+    /// qualified name of the method that opened the call
+    method: String,
+    /// caller that opened the call
+    caller: Option<String>,
+    /// value of `clock` when the call was last used
+    last_used: u64,
+    state: StreamState,
+}
+
+#[derive(Default)]
+struct Streams {
+    calls: HashMap<u64, ServerStream>,
+    /// counter for stream ids, which are hashed so they can't be guessed
+    next: u64,
+    /// logical clock, advanced by each frame
+    clock: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref STREAMS: Mutex<Streams> = Mutex::new(Streams::default());
+    static ref ID_KEYS: RandomState = RandomState::new();
+}
+
+fn streams() -> std::sync::MutexGuard<'static, Streams> {
+    STREAMS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Streams {
+    /// Adds a call, and returns its id. The call is refused if the server,
+    /// or the caller, has too many calls in progress.
+    fn insert(
+        &mut self,
+        ctx: &context::Context<'_>,
+        method: &str,
+        state: StreamState,
+    ) -> Result<u64, RpcError> {
+        if self.calls.len() >= MAX_STREAMS || self.caller_streams(ctx) >= MAX_CALLER_STREAMS {
+            self.discard_idle();
+            if self.calls.len() >= MAX_STREAMS {
+                return Err(RpcError::Invalid("too many streams".to_string()));
+            }
+            if self.caller_streams(ctx) >= MAX_CALLER_STREAMS {
+                return Err(RpcError::Invalid(
+                    "too many streams for the caller".to_string(),
+                ));
+            }
+        }
+        let id = loop {
+            self.next += 1;
+            let mut hasher = ID_KEYS.build_hasher();
+            hasher.write_u64(self.next);
+            let id = hasher.finish();
+            if id != 0 && !self.calls.contains_key(&id) {
+                break id;
+            }
+        };
+        self.clock += 1;
+        self.calls.insert(
+            id,
+            ServerStream {
+                method: method.to_string(),
+                caller: ctx.actor.map(String::from),
+                last_used: self.clock,
+                state,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Returns the number of calls opened by the caller
+    fn caller_streams(&self, ctx: &context::Context<'_>) -> usize {
+        self.calls
+            .values()
+            .filter(|call| call.caller.as_deref() == ctx.actor)
+            .count()
+    }
+
+    /// Discards calls that haven't been used in [`STREAM_IDLE_FRAMES`] frames
+    fn discard_idle(&mut self) {
+        let clock = self.clock;
+        self.calls
+            .retain(|_, call| clock.saturating_sub(call.last_used) < STREAM_IDLE_FRAMES);
+    }
+
+    /// Returns the state of call `id`, if it was opened by the same caller and method
+    fn get_mut(
+        &mut self,
+        ctx: &context::Context<'_>,
+        method: &str,
+        id: u64,
+    ) -> Result<&mut StreamState, RpcError> {
+        self.clock += 1;
+        let clock = self.clock;
+        match self.calls.get_mut(&id) {
+            Some(call) if call.method == method && call.caller.as_deref() == ctx.actor => {
+                call.last_used = clock;
+                Ok(&mut call.state)
+            }
+            // a call of another caller is reported as unknown
+            _ => Err(unknown_stream(id)),
+        }
+    }
+
+    /// Removes call `id`, if it was opened by the same caller and method
+    fn remove(
+        &mut self,
+        ctx: &context::Context<'_>,
+        method: &str,
+        id: u64,
+    ) -> Result<StreamState, RpcError> {
+        self.get_mut(ctx, method, id)?;
+        Ok(self.calls.remove(&id).map(|call| call.state).unwrap())
+    }
+}
+
+fn unknown_stream(id: u64) -> RpcError {
+    RpcError::Invalid(format!("unknown stream {}", id))
+}
+
+fn stream_busy(id: u64) -> RpcError {
+    RpcError::Invalid(format!("stream {} is busy", id))
+}
+
+fn serialize_output<T: Serialize + Send + 'static>(
+    output: ItemStream<T>,
+) -> BoxStream<'static, Result<Vec<u8>, RpcError>> {
+    output.map(|item| item.and_then(|v| serialize(&v))).boxed()
+}
+
+fn input_stream<T: DeserializeOwned + Send + 'static>(items: Vec<Vec<u8>>) -> ItemStream<T> {
+    ItemStream::new(futures::stream::iter(
+        items.into_iter().map(|buf| deserialize(&buf)),
+    ))
+}
+
+/// Removes the buffered request items of stream `id`, and returns them as a stream
+fn take_input<T: DeserializeOwned + Send + 'static>(
+    ctx: &context::Context<'_>,
+    method: &str,
+    id: u64,
+) -> Result<ItemStream<T>, RpcError> {
+    let mut streams = streams();
+    if !matches!(streams.get_mut(ctx, method, id)?, StreamState::Input { .. }) {
+        return Err(stream_busy(id));
+    }
+    match streams.remove(ctx, method, id)? {
+        StreamState::Input { items, .. } => Ok(input_stream(items)),
+        _ => unreachable!(),
+    }
+}
+
+/// Pulls the next item of the response stream of call `id`
+async fn next_item(
+    ctx: &context::Context<'_>,
+    method: &str,
+    id: u64,
+) -> Result<StreamReply, RpcError> {
+    let mut output = {
+        let mut streams = streams();
+        let state = streams.get_mut(ctx, method, id)?;
+        // the call is left as is if it isn't ready for Next
+        if !matches!(state, StreamState::Output(_)) {
+            return Err(stream_busy(id));
+        }
+        match std::mem::replace(state, StreamState::Busy) {
+            StreamState::Output(output) => output,
+            _ => unreachable!(),
+        }
+    };
+    let item = output.next().await;
+    let mut streams = streams();
+    match item {
+        Some(Ok(item)) => {
+            // put it back unless it was cancelled while polling
+            if let Ok(state) = streams.get_mut(ctx, method, id) {
+                *state = StreamState::Output(output);
+            }
+            Ok(StreamReply::Item(item))
+        }
+        Some(Err(e)) => {
+            let _ = streams.remove(ctx, method, id);
+            Err(e)
+        }
+        None => {
+            let _ = streams.remove(ctx, method, id);
+            Ok(StreamReply::End)
+        }
+    }
+}
+
+/// Handles frames that don't invoke the handler
+async fn serve_frame(
+    ctx: &context::Context<'_>,
+    method: &str,
+    frame: StreamFrame,
+) -> Result<StreamReply, RpcError> {
+    match frame {
+        StreamFrame::Data { id, item } => match streams().get_mut(ctx, method, id)? {
+            StreamState::Input { items, len } => {
+                if items.len() >= MAX_STREAM_ITEMS {
+                    return Err(RpcError::Invalid(format!(
+                        "stream {} has more than {} items",
+                        id, MAX_STREAM_ITEMS
+                    )));
+                }
+                if *len + item.len() > MAX_STREAM_INPUT_LEN {
+                    return Err(RpcError::Invalid(format!(
+                        "stream {} has more than {} bytes",
+                        id, MAX_STREAM_INPUT_LEN
+                    )));
+                }
+                *len += item.len();
+                items.push(item);
+                Ok(StreamReply::Ack)
+            }
+            _ => Err(stream_busy(id)),
+        },
+        StreamFrame::Next { id } => next_item(ctx, method, id).await,
+        StreamFrame::Cancel { id } => {
+            streams().remove(ctx, method, id)?;
+            Ok(StreamReply::Ack)
+        }
+        StreamFrame::Open(_) | StreamFrame::End { .. } => {
+            Err(RpcError::Invalid("unexpected stream frame".to_string()))
+        }
+    }
+}
+
+/// Server dispatch for a server-streaming method. `method` is the qualified method name,
+/// `arg` is the serialized [`StreamFrame`], and the handler is called with the request
+/// when the stream is opened. Frames after `Open` are accepted only from the same caller
+/// (`ctx.actor`) and method. Returns the serialized [`StreamReply`].
+pub async fn serve_server_streaming<Req, Resp, F, Fut>(
+    ctx: &context::Context<'_>,
+    method: &str,
+    arg: &[u8],
+    handler: F,
+) -> Result<Vec<u8>, RpcError>
+where
+    Req: DeserializeOwned,
+    Resp: Serialize + Send + 'static,
+    F: FnOnce(Req) -> Fut,
+    Fut: Future<Output = Result<ItemStream<Resp>, RpcError>>,
+{
+    let reply = match deserialize(arg)? {
+        StreamFrame::Open(req) => {
+            let output = handler(deserialize(&req)?).await?;
+            let id =
+                streams().insert(ctx, method, StreamState::Output(serialize_output(output)))?;
+            StreamReply::Opened { id }
+        }
+        frame => serve_frame(ctx, method, frame).await?,
+    };
+    serialize(&reply)
+}
+
+/// Server dispatch for a client-streaming method. `method` is the qualified method name,
+/// `arg` is the serialized [`StreamFrame`], and the handler is called with the request stream
+/// when it has been received. Returns the serialized [`StreamReply`].
+pub async fn serve_client_streaming<Req, Resp, F, Fut>(
+    ctx: &context::Context<'_>,
+    method: &str,
+    arg: &[u8],
+    handler: F,
+) -> Result<Vec<u8>, RpcError>
+where
+    Req: DeserializeOwned + Send + 'static,
+    Resp: Serialize,
+    F: FnOnce(ItemStream<Req>) -> Fut,
+    Fut: Future<Output = Result<Resp, RpcError>>,
+{
+    let reply = match deserialize(arg)? {
+        StreamFrame::Open(_) => StreamReply::Opened {
+            id: streams().insert(ctx, method, StreamState::input())?,
+        },
+        StreamFrame::End { id } => {
+            let resp = handler(take_input(ctx, method, id)?).await?;
+            StreamReply::Response(serialize(&resp)?)
+        }
+        frame => serve_frame(ctx, method, frame).await?,
+    };
+    serialize(&reply)
+}
+
+/// Server dispatch for a bidirectional streaming method. `method` is the qualified method name,
+/// `arg` is the serialized [`StreamFrame`], and the handler is called with the request stream
+/// when it has been received. The call is half-duplex: the response stream is available
+/// only after the whole request stream. Returns the serialized [`StreamReply`].
+pub async fn serve_bidi_streaming<Req, Resp, F, Fut>(
+    ctx: &context::Context<'_>,
+    method: &str,
+    arg: &[u8],
+    handler: F,
+) -> Result<Vec<u8>, RpcError>
+where
+    Req: DeserializeOwned + Send + 'static,
+    Resp: Serialize + Send + 'static,
+    F: FnOnce(ItemStream<Req>) -> Fut,
+    Fut: Future<Output = Result<ItemStream<Resp>, RpcError>>,
+{
+    let reply = match deserialize(arg)? {
+        StreamFrame::Open(_) => StreamReply::Opened {
+            id: streams().insert(ctx, method, StreamState::input())?,
+        },
+        StreamFrame::End { id } => {
+            // the call is marked busy while the handler runs
+            let items = {
+                let mut streams = streams();
+                let state = streams.get_mut(ctx, method, id)?;
+                match std::mem::replace(state, StreamState::Busy) {
+                    StreamState::Input { items, .. } => items,
+                    other => {
+                        *state = other;
+                        return Err(stream_busy(id));
+                    }
+                }
+            };
+            match handler(input_stream(items)).await {
+                Ok(output) => {
+                    // unless it was cancelled while the handler ran
+                    if let Ok(state) = streams().get_mut(ctx, method, id) {
+                        *state = StreamState::Output(serialize_output(output));
+                    }
+                }
+                Err(e) => {
+                    let _ = streams().remove(ctx, method, id);
+                    return Err(e);
+                }
+            }
+            StreamReply::Ack
+        }
+        frame => serve_frame(ctx, method, frame).await?,
+    };
+    serialize(&reply)
+}
+
+//
+// Client side
+//
+
+/// Sends one frame to the streaming method
+async fn send_frame<T: Transport>(
+    transport: &T,
+    ctx: &context::Context<'_>,
+    config: &client::ClientConfig,
+    method: &str,
+    frame: &StreamFrame,
+) -> Result<StreamReply, RpcError> {
+    let arg = serialize(frame)?;
+    let resp = transport
+        .send(
+            ctx,
+            config,
+            Message {
+                method,
+                arg: Cow::Borrowed(&arg),
+            },
+        )
+        .await?;
+    deserialize(resp.arg.as_ref())
+}
+
+fn unexpected(reply: StreamReply) -> RpcError {
+    RpcError::Invalid(format!("unexpected stream reply {:?}", reply))
+}
+
+/// context of the call, kept for frames sent after the call has returned
+#[derive(Clone)]
+struct StreamContext {
+    actor: Option<String>,
+    span: Option<String>,
+    deadline: Option<std::time::Instant>,
+}
+
+impl StreamContext {
+    fn new(ctx: &context::Context<'_>) -> Self {
+        StreamContext {
+            actor: ctx.actor.map(String::from),
+            span: ctx.span.clone(),
+            deadline: ctx.deadline,
+        }
+    }
+
+    fn context(&self) -> context::Context<'_> {
+        context::Context {
+            actor: self.actor.as_deref(),
+            span: self.span.clone(),
+            deadline: self.deadline,
+        }
+    }
+}
+
+/// Sends the request stream, and returns the stream id
+async fn send_input<T: Transport, Req: Serialize>(
+    transport: &T,
+    ctx: &context::Context<'_>,
+    config: &client::ClientConfig,
+    method: &str,
+    mut input: ItemStream<Req>,
+) -> Result<u64, RpcError> {
+    let id = match send_frame(
+        transport,
+        ctx,
+        config,
+        method,
+        &StreamFrame::Open(Vec::new()),
+    )
+    .await?
+    {
+        StreamReply::Opened { id } => id,
+        reply => return Err(unexpected(reply)),
+    };
+    while let Some(item) = input.next().await {
+        let item = match item.and_then(|v| serialize(&v)) {
+            Ok(item) => item,
+            Err(e) => {
+                let _ =
+                    send_frame(transport, ctx, config, method, &StreamFrame::Cancel { id }).await;
+                return Err(e);
+            }
+        };
+        match send_frame(
+            transport,
+            ctx,
+            config,
+            method,
+            &StreamFrame::Data { id, item },
+        )
+        .await?
+        {
+            StreamReply::Ack => {}
+            reply => return Err(unexpected(reply)),
+        }
+    }
+    Ok(id)
+}
+
+/// Constructs the response stream of call `id`, which pulls items from the server
+fn pull_output<T, Resp>(
+    transport: T,
+    ctx: &context::Context<'_>,
+    config: &client::ClientConfig,
+    method: &str,
+    id: u64,
+) -> ItemStream<Resp>
+where
+    T: Transport + Clone + Sync + 'static,
+    Resp: DeserializeOwned + Send + 'static,
+{
+    let state = (
+        Some(transport.clone()),
+        StreamContext::new(ctx),
+        config.clone(),
+        method.to_string(),
+    );
+    let items =
+        futures::stream::unfold(state, move |(transport, ctx, config, method)| async move {
+            // transport is None after the end of the stream or an error
+            let transport = transport?;
+            let item = match send_frame(
+                &transport,
+                &ctx.context(),
+                &config,
+                &method,
+                &StreamFrame::Next { id },
+            )
+            .await
+            {
+                Ok(StreamReply::Item(buf)) => deserialize(&buf),
+                Ok(StreamReply::End) => return None,
+                Ok(reply) => Err(unexpected(reply)),
+                Err(e) => Err(e),
+            };
+            let transport = if item.is_ok() { Some(transport) } else { None };
+            Some((item, (transport, ctx, config, method)))
+        });
+    let (ctx, config, method) = (StreamContext::new(ctx), config.clone(), method.to_string());
+    let cancel: CancelFn = Box::new(move || {
+        Box::pin(async move {
+            send_frame(
+                &transport,
+                &ctx.context(),
+                &config,
+                &method,
+                &StreamFrame::Cancel { id },
+            )
+            .await
+            .map(|_| ())
+        })
+    });
+    ItemStream {
+        inner: items.boxed(),
+        cancel: Some(cancel),
+    }
+}
+
+/// Client call to a server-streaming method
+pub async fn call_server_streaming<T, Req, Resp>(
+    transport: &T,
+    ctx: &context::Context<'_>,
+    config: &client::ClientConfig,
+    method: &str,
+    value: &Req,
+) -> Result<ItemStream<Resp>, RpcError>
+where
+    T: Transport + Clone + Sync + 'static,
+    Req: Serialize,
+    Resp: DeserializeOwned + Send + 'static,
+{
+    let frame = StreamFrame::Open(serialize(value)?);
+    match send_frame(transport, ctx, config, method, &frame).await? {
+        StreamReply::Opened { id } => Ok(pull_output(transport.clone(), ctx, config, method, id)),
+        reply => Err(unexpected(reply)),
+    }
+}
+
+/// Client call to a client-streaming method
+pub async fn call_client_streaming<T, Req, Resp>(
+    transport: &T,
+    ctx: &context::Context<'_>,
+    config: &client::ClientConfig,
+    method: &str,
+    input: ItemStream<Req>,
+) -> Result<Resp, RpcError>
+where
+    T: Transport + Sync,
+    Req: Serialize,
+    Resp: DeserializeOwned,
+{
+    let id = send_input(transport, ctx, config, method, input).await?;
+    match send_frame(transport, ctx, config, method, &StreamFrame::End { id }).await? {
+        StreamReply::Response(buf) => deserialize(&buf),
+        reply => Err(unexpected(reply)),
+    }
+}
+
+/// Client call to a bidirectional streaming method. The call is half-duplex: `input` is sent
+/// to its end before the response stream is returned.
+pub async fn call_bidi_streaming<T, Req, Resp>(
+    transport: &T,
+    ctx: &context::Context<'_>,
+    config: &client::ClientConfig,
+    method: &str,
+    input: ItemStream<Req>,
+) -> Result<ItemStream<Resp>, RpcError>
+where
+    T: Transport + Clone + Sync + 'static,
+    Req: Serialize,
+    Resp: DeserializeOwned + Send + 'static,
+{
+    let id = send_input(transport, ctx, config, method, input).await?;
+    match send_frame(transport, ctx, config, method, &StreamFrame::End { id }).await? {
+        StreamReply::Ack => Ok(pull_output(transport.clone(), ctx, config, method, id)),
+        reply => Err(unexpected(reply)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{mock::LoopbackTransport, MessageDispatch};
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+
+    /// server with a streaming method of each kind
+    struct Numbers {}
+
+    #[async_trait]
+    impl MessageDispatch for Numbers {
+        async fn dispatch(
+            &self,
+            ctx: &context::Context<'_>,
+            message: Message<'_>,
+        ) -> Result<Message<'static>, RpcError> {
+            let (method, arg) = (message.method, message.arg.as_ref());
+            let buf = match method {
+                "Count" => {
+                    serve_server_streaming(ctx, method, arg, |n: u32| async move {
+                        Ok(ItemStream::from_items(0..n))
+                    })
+                    .await?
+                }
+                "Sum" => {
+                    serve_client_streaming(ctx, method, arg, |input: ItemStream<u32>| async move {
+                        Ok(collect(input).await?.iter().sum::<u32>())
+                    })
+                    .await?
+                }
+                "Double" => {
+                    serve_bidi_streaming(ctx, method, arg, |input: ItemStream<u32>| async move {
+                        Ok(ItemStream::new(input.map(|v| v.map(|n| n * 2))))
+                    })
+                    .await?
+                }
+                _ => return Err(RpcError::MethodNotHandled(message.method.to_string())),
+            };
+            Ok(Message {
+                method: "_reply",
+                arg: Cow::Owned(buf),
+            })
+        }
+    }
+
+    fn loopback() -> LoopbackTransport<Numbers> {
+        LoopbackTransport::new(Numbers {})
+    }
+
+    #[test]
+    fn server_streaming() {
+        let (t, ctx, config) = (
+            loopback(),
+            Default::default(),
+            client::ClientConfig::target("x"),
+        );
+        let items: ItemStream<u32> =
+            block_on(call_server_streaming(&t, &ctx, &config, "Count", &4u32)).unwrap();
+        assert_eq!(block_on(collect(items)).unwrap(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn client_streaming() {
+        let (t, ctx, config) = (
+            loopback(),
+            Default::default(),
+            client::ClientConfig::target("x"),
+        );
+        let input = ItemStream::from_items(vec![1u32, 2, 3]);
+        let sum: u32 = block_on(call_client_streaming(&t, &ctx, &config, "Sum", input)).unwrap();
+        assert_eq!(sum, 6);
+    }
+
+    #[test]
+    fn bidi_streaming() {
+        let (t, ctx, config) = (
+            loopback(),
+            Default::default(),
+            client::ClientConfig::target("x"),
+        );
+        let input = ItemStream::from_items(vec![1u32, 5]);
+        let output: ItemStream<u32> =
+            block_on(call_bidi_streaming(&t, &ctx, &config, "Double", input)).unwrap();
+        assert_eq!(block_on(collect(output)).unwrap(), vec![2, 10]);
+    }
+
+    #[test]
+    fn cancel_stream() {
+        let (t, ctx, config) = (
+            loopback(),
+            Default::default(),
+            client::ClientConfig::target("x"),
+        );
+        let mut items: ItemStream<u32> =
+            block_on(call_server_streaming(&t, &ctx, &config, "Count", &100u32)).unwrap();
+        assert_eq!(block_on(items.next()).unwrap().unwrap(), 0);
+        block_on(items.cancel()).unwrap();
+
+        // after cancel, the server no longer has the stream
+        let send = |frame| block_on(send_frame(&t, &ctx, &config, "Count", &frame));
+        let open = StreamFrame::Open(serialize(&100u32).unwrap());
+        let id = match send(open).unwrap() {
+            StreamReply::Opened { id } => id,
+            reply => panic!("unexpected {:?}", reply),
+        };
+        assert!(matches!(
+            send(StreamFrame::Next { id }),
+            Ok(StreamReply::Item(_))
+        ));
+        assert!(matches!(
+            send(StreamFrame::Cancel { id }),
+            Ok(StreamReply::Ack)
+        ));
+        assert!(send(StreamFrame::Next { id }).is_err());
+    }
+
+    fn open_count(
+        t: &LoopbackTransport<Numbers>,
+        ctx: &context::Context<'_>,
+        config: &client::ClientConfig,
+    ) -> u64 {
+        let open = StreamFrame::Open(serialize(&100u32).unwrap());
+        match block_on(send_frame(t, ctx, config, "Count", &open)).unwrap() {
+            StreamReply::Opened { id } => id,
+            reply => panic!("unexpected {:?}", reply),
+        }
+    }
+
+    #[test]
+    fn stream_owner() {
+        let (t, config) = (loopback(), client::ClientConfig::target("x"));
+        let alice = context::Context {
+            actor: Some("alice"),
+            ..Default::default()
+        };
+        let mallory = context::Context {
+            actor: Some("mallory"),
+            ..Default::default()
+        };
+        let id = open_count(&t, &alice, &config);
+        let send = |ctx, method, frame| block_on(send_frame(&t, ctx, &config, method, &frame));
+
+        // another caller, or another method, can't use or cancel the stream
+        assert!(send(&mallory, "Count", StreamFrame::Next { id }).is_err());
+        assert!(send(&mallory, "Count", StreamFrame::Cancel { id }).is_err());
+        assert!(send(&alice, "Sum", StreamFrame::Cancel { id }).is_err());
+        assert!(matches!(
+            send(&alice, "Count", StreamFrame::Next { id }),
+            Ok(StreamReply::Item(_))
+        ));
+
+        // the actor is kept for frames sent after the call returns
+        let mut items: ItemStream<u32> =
+            block_on(call_server_streaming(&t, &alice, &config, "Count", &2u32)).unwrap();
+        assert_eq!(block_on(items.next()).unwrap().unwrap(), 0);
+        assert_eq!(block_on(items.next()).unwrap().unwrap(), 1);
+        assert!(block_on(items.next()).is_none());
+    }
+
+    #[test]
+    fn drop_cancels_stream() {
+        let (t, ctx, config) = (
+            loopback(),
+            Default::default(),
+            client::ClientConfig::target("x"),
+        );
+        let id = open_count(&t, &ctx, &config);
+        let items: ItemStream<u32> = pull_output(t.clone(), &ctx, &config, "Count", id);
+        drop(items);
+        assert!(block_on(send_frame(
+            &t,
+            &ctx,
+            &config,
+            "Count",
+            &StreamFrame::Next { id }
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn concurrent_next() {
+        use futures::FutureExt as _;
+
+        let ctx = context::Context::default();
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let id = streams()
+            .insert(&ctx, "Watch", StreamState::Output(rx.boxed()))
+            .unwrap();
+        let next = |id| serve_frame(&ctx, "Watch", StreamFrame::Next { id });
+
+        // the first Next waits for an item, and a second Next is rejected
+        let mut first = next(id).boxed();
+        assert!((&mut first).now_or_never().is_none());
+        assert!(block_on(next(id)).is_err());
+        assert!(block_on(serve_frame(
+            &ctx,
+            "Watch",
+            StreamFrame::Data { id, item: vec![] }
+        ))
+        .is_err());
+
+        // the stream is still there when the first Next completes
+        tx.unbounded_send(Ok(vec![1])).unwrap();
+        assert!(matches!(block_on(first), Ok(StreamReply::Item(v)) if v == vec![1]));
+        tx.unbounded_send(Ok(vec![2])).unwrap();
+        assert!(matches!(block_on(next(id)), Ok(StreamReply::Item(v)) if v == vec![2]));
+        drop(tx);
+        assert!(matches!(block_on(next(id)), Ok(StreamReply::End)));
+        assert!(block_on(next(id)).is_err());
+    }
+
+    #[test]
+    fn stream_limits() {
+        let mut streams = Streams::default();
+        let bob = context::Context {
+            actor: Some("bob"),
+            ..Default::default()
+        };
+        for _ in 0..MAX_CALLER_STREAMS {
+            streams.insert(&bob, "Sum", StreamState::input()).unwrap();
+        }
+        // the caller's calls are refused, but not those of other callers
+        assert!(streams.insert(&bob, "Sum", StreamState::input()).is_err());
+        assert!(streams
+            .insert(&Default::default(), "Sum", StreamState::input())
+            .is_ok());
+
+        // idle calls are discarded
+        streams.clock += STREAM_IDLE_FRAMES;
+        assert!(streams.insert(&bob, "Sum", StreamState::input()).is_ok());
+        assert_eq!(streams.calls.len(), 1);
+    }
+
+    #[test]
+    fn input_limits() {
+        let (t, config) = (loopback(), client::ClientConfig::target("x"));
+        let ctx = context::Context {
+            actor: Some("carol"),
+            ..Default::default()
+        };
+        let send = |frame| block_on(send_frame(&t, &ctx, &config, "Sum", &frame));
+        let id = match send(StreamFrame::Open(Vec::new())).unwrap() {
+            StreamReply::Opened { id } => id,
+            reply => panic!("unexpected {:?}", reply),
+        };
+        let item = vec![0u8; MAX_STREAM_INPUT_LEN / 2];
+        assert!(send(StreamFrame::Data {
+            id,
+            item: item.clone()
+        })
+        .is_ok());
+        assert!(send(StreamFrame::Data {
+            id,
+            item: item.clone()
+        })
+        .is_ok());
+        assert!(send(StreamFrame::Data { id, item: vec![0] }).is_err());
+        send(StreamFrame::Cancel { id }).unwrap();
+    }
+}
@@ -0,0 +1,634 @@
+// Generated by midl from package store. Do not edit.
+//
+// Messages are encoded with msgpack as arrays of fields, in declaration order,
+// which is binary-compatible with the generated rust code.
+// Streaming methods and batches are not supported in AssemblyScript.
+import { Decoder, Encoder, Sizer, Writer } from "@wapc/as-msgpack";
+import { hostCall, register, Result } from "@wapc/as-guest";
+
+/** Serializes a value with the encode function */
+function serialize<T>(value: T, encode: (encoder: Writer, value: T) => void): ArrayBuffer {
+  const sizer = new Sizer();
+  encode(sizer, value);
+  const buffer = new ArrayBuffer(sizer.length);
+  const encoder = new Encoder(buffer);
+  encode(encoder, value);
+  return buffer;
+}
+
+/** Serialized unit value, returned by methods without a return type */
+function serializeNil(): ArrayBuffer {
+  return serialize<bool>(true, (encoder: Writer, value: bool): void => {
+    encoder.writeNil();
+  });
+}
+
+/** Writes bytes as an array of u8, as rust serializes a `Vec<u8>` outside of messages */
+function writeBytesArray(encoder: Writer, value: ArrayBuffer): void {
+  const bytes = Uint8Array.wrap(value);
+  encoder.writeArraySize(bytes.length);
+  for (let i = 0; i < bytes.length; i++) {
+    encoder.writeUInt8(bytes[i]);
+  }
+}
+
+/** Reads bytes written with `writeBytesArray` */
+function readBytesArray(decoder: Decoder): ArrayBuffer {
+  const size = decoder.readArraySize();
+  const bytes = new Uint8Array(size);
+  for (let i: u32 = 0; i < size; i++) {
+    bytes[i] = decoder.readUInt8();
+  }
+  return bytes.buffer;
+}
+
+/**
+ * An item in the store
+ */
+export class Item {
+  /**
+   * Unique key
+   */
+  key: string = "";
+  count: u32 = 0;
+  updated: i64 = 0;
+  price: f64 = 0;
+  active: bool = false;
+  data: ArrayBuffer = new ArrayBuffer(0);
+  tags: Array<string> = new Array<string>();
+  sizes: Map<string, u64> = new Map<string, u64>();
+  /**
+   * Shown to customers, if set
+   */
+  note: string = "";
+
+  static decode(decoder: Decoder): Item {
+    const value = new Item();
+    const size = decoder.readArraySize();
+    if (size > 0) value.key = decoder.readString();
+    if (size > 1) value.count = decoder.readUInt32();
+    if (size > 2) value.updated = decoder.readInt64();
+    if (size > 3) value.price = decoder.readFloat64();
+    if (size > 4) value.active = decoder.readBool();
+    if (size > 5) value.data = decoder.readByteArray();
+    if (size > 6) value.tags = decoder.readArray<string>((decoder: Decoder): string => { return decoder.readString(); });
+    if (size > 7) value.sizes = decoder.readMap<string, u64>((decoder: Decoder): string => { return decoder.readString(); }, (decoder: Decoder): u64 => { return decoder.readUInt64(); });
+    if (size > 8) value.note = decoder.readString();
+    // skip fields added in later versions
+    for (let i: u32 = 9; i < size; i++) {
+      decoder.skip();
+    }
+    return value;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeArraySize(9);
+    encoder.writeString(this.key);
+    encoder.writeUInt32(this.count);
+    encoder.writeInt64(this.updated);
+    encoder.writeFloat64(this.price);
+    encoder.writeBool(this.active);
+    encoder.writeByteArray(this.data);
+    encoder.writeArray<string>(this.tags, (encoder: Writer, item: string): void => { encoder.writeString(item); });
+    encoder.writeMap<string, u64>(this.sizes, (encoder: Writer, key: string): void => { encoder.writeString(key); }, (encoder: Writer, value: u64): void => { encoder.writeUInt64(value); });
+    encoder.writeString(this.note);
+  }
+
+  toBuffer(): ArrayBuffer {
+    return serialize<Item>(this, (encoder: Writer, value: Item): void => {
+      value.encode(encoder);
+    });
+  }
+}
+
+export class Page {
+  items: Array<Item> = new Array<Item>();
+  blobs: Array<ArrayBuffer> = new Array<ArrayBuffer>();
+
+  static decode(decoder: Decoder): Page {
+    const value = new Page();
+    const size = decoder.readArraySize();
+    if (size > 0) value.items = decoder.readArray<Item>((decoder: Decoder): Item => { return Item.decode(decoder); });
+    if (size > 1) value.blobs = decoder.readArray<ArrayBuffer>((decoder: Decoder): ArrayBuffer => { return readBytesArray(decoder); });
+    // skip fields added in later versions
+    for (let i: u32 = 2; i < size; i++) {
+      decoder.skip();
+    }
+    return value;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeArraySize(2);
+    encoder.writeArray<Item>(this.items, (encoder: Writer, item: Item): void => { item.encode(encoder); });
+    encoder.writeArray<ArrayBuffer>(this.blobs, (encoder: Writer, item: ArrayBuffer): void => { writeBytesArray(encoder, item); });
+  }
+
+  toBuffer(): ArrayBuffer {
+    return serialize<Page>(this, (encoder: Writer, value: Page): void => {
+      value.encode(encoder);
+    });
+  }
+}
+
+/**
+ * Every field type
+ */
+export class Types {
+  tiny: i8 = 0;
+  count: i32 = 0;
+  total: i64 = 0;
+  flags: u8 = 0;
+  size: u32 = 0;
+  offset: u64 = 0;
+  enabled: bool = false;
+  label: string = "";
+  raw: ArrayBuffer = new ArrayBuffer(0);
+  ratio: f32 = 0;
+  score: f64 = 0;
+  created: string = "";
+  attrs: Map<string, ArrayBuffer> = new Map<string, ArrayBuffer>();
+  ids: Array<i32> = new Array<i32>();
+  item: Item = new Item();
+
+  static decode(decoder: Decoder): Types {
+    const value = new Types();
+    const size = decoder.readArraySize();
+    if (size > 0) value.tiny = decoder.readInt8();
+    if (size > 1) value.count = decoder.readInt32();
+    if (size > 2) value.total = decoder.readInt64();
+    if (size > 3) value.flags = decoder.readUInt8();
+    if (size > 4) value.size = decoder.readUInt32();
+    if (size > 5) value.offset = decoder.readUInt64();
+    if (size > 6) value.enabled = decoder.readBool();
+    if (size > 7) value.label = decoder.readString();
+    if (size > 8) value.raw = decoder.readByteArray();
+    if (size > 9) value.ratio = decoder.readFloat32();
+    if (size > 10) value.score = decoder.readFloat64();
+    if (size > 11) value.created = decoder.readString();
+    if (size > 12) value.attrs = decoder.readMap<string, ArrayBuffer>((decoder: Decoder): string => { return decoder.readString(); }, (decoder: Decoder): ArrayBuffer => { return readBytesArray(decoder); });
+    if (size > 13) value.ids = decoder.readArray<i32>((decoder: Decoder): i32 => { return decoder.readInt32(); });
+    if (size > 14) value.item = Item.decode(decoder);
+    // skip fields added in later versions
+    for (let i: u32 = 15; i < size; i++) {
+      decoder.skip();
+    }
+    return value;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeArraySize(15);
+    encoder.writeInt8(this.tiny);
+    encoder.writeInt32(this.count);
+    encoder.writeInt64(this.total);
+    encoder.writeUInt8(this.flags);
+    encoder.writeUInt32(this.size);
+    encoder.writeUInt64(this.offset);
+    encoder.writeBool(this.enabled);
+    encoder.writeString(this.label);
+    encoder.writeByteArray(this.raw);
+    encoder.writeFloat32(this.ratio);
+    encoder.writeFloat64(this.score);
+    encoder.writeString(this.created);
+    encoder.writeMap<string, ArrayBuffer>(this.attrs, (encoder: Writer, key: string): void => { encoder.writeString(key); }, (encoder: Writer, value: ArrayBuffer): void => { writeBytesArray(encoder, value); });
+    encoder.writeArray<i32>(this.ids, (encoder: Writer, item: i32): void => { encoder.writeInt32(item); });
+    this.item.encode(encoder);
+  }
+
+  toBuffer(): ArrayBuffer {
+    return serialize<Types>(this, (encoder: Writer, value: Types): void => {
+      value.encode(encoder);
+    });
+  }
+}
+
+export class NotFound {
+  key: string = "";
+
+  static decode(decoder: Decoder): NotFound {
+    const value = new NotFound();
+    const size = decoder.readArraySize();
+    if (size > 0) value.key = decoder.readString();
+    // skip fields added in later versions
+    for (let i: u32 = 1; i < size; i++) {
+      decoder.skip();
+    }
+    return value;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeArraySize(1);
+    encoder.writeString(this.key);
+  }
+
+  toBuffer(): ArrayBuffer {
+    return serialize<NotFound>(this, (encoder: Writer, value: NotFound): void => {
+      value.encode(encoder);
+    });
+  }
+}
+
+export class Denied {
+  reason: string = "";
+
+  static decode(decoder: Decoder): Denied {
+    const value = new Denied();
+    const size = decoder.readArraySize();
+    if (size > 0) value.reason = decoder.readString();
+    // skip fields added in later versions
+    for (let i: u32 = 1; i < size; i++) {
+      decoder.skip();
+    }
+    return value;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeArraySize(1);
+    encoder.writeString(this.reason);
+  }
+
+  toBuffer(): ArrayBuffer {
+    return serialize<Denied>(this, (encoder: Writer, value: Denied): void => {
+      value.encode(encoder);
+    });
+  }
+}
+
+/** Errors declared by method `get` of service Store */
+export class StoreGetError {
+  /** Position of the error in the `throws` list */
+  variant: u32 = 0;
+  notFound: NotFound | null = null;
+  denied: Denied | null = null;
+
+  static notFound(value: NotFound): StoreGetError {
+    const error = new StoreGetError();
+    error.variant = 0;
+    error.notFound = value;
+    return error;
+  }
+
+  static denied(value: Denied): StoreGetError {
+    const error = new StoreGetError();
+    error.variant = 1;
+    error.denied = value;
+    return error;
+  }
+
+  static decode(decoder: Decoder): StoreGetError {
+    const error = new StoreGetError();
+    decoder.readMapSize();
+    error.variant = decoder.readUInt32();
+    if (error.variant == 0) error.notFound = NotFound.decode(decoder);
+    if (error.variant == 1) error.denied = Denied.decode(decoder);
+    return error;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeMapSize(1);
+    encoder.writeUInt32(this.variant);
+    if (this.variant == 0) this.notFound!.encode(encoder);
+    if (this.variant == 1) this.denied!.encode(encoder);
+  }
+}
+
+/** Result of method `get` of service Store: a value, or a declared error */
+export class StoreGetResult {
+  value: Item = new Item();
+  error: StoreGetError | null = null;
+
+  static ok(value: Item): StoreGetResult {
+    const result = new StoreGetResult();
+    result.value = value;
+    return result;
+  }
+
+  static err(error: StoreGetError): StoreGetResult {
+    const result = new StoreGetResult();
+    result.error = error;
+    return result;
+  }
+
+  static decode(decoder: Decoder): StoreGetResult {
+    const result = new StoreGetResult();
+    decoder.readMapSize();
+    if (decoder.readUInt32() == 0) {
+      result.value = Item.decode(decoder);
+    } else {
+      result.error = StoreGetError.decode(decoder);
+    }
+    return result;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeMapSize(1);
+    const error = this.error;
+    if (error) {
+      encoder.writeUInt32(1);
+      error.encode(encoder);
+    } else {
+      encoder.writeUInt32(0);
+      this.value.encode(encoder);
+    }
+  }
+
+  toBuffer(): ArrayBuffer {
+    return serialize<StoreGetResult>(this, (encoder: Writer, value: StoreGetResult): void => {
+      value.encode(encoder);
+    });
+  }
+}
+
+/** Errors declared by method `delete` of service Store */
+export class StoreDeleteError {
+  /** Position of the error in the `throws` list */
+  variant: u32 = 0;
+  notFound: NotFound | null = null;
+
+  static notFound(value: NotFound): StoreDeleteError {
+    const error = new StoreDeleteError();
+    error.variant = 0;
+    error.notFound = value;
+    return error;
+  }
+
+  static decode(decoder: Decoder): StoreDeleteError {
+    const error = new StoreDeleteError();
+    decoder.readMapSize();
+    error.variant = decoder.readUInt32();
+    if (error.variant == 0) error.notFound = NotFound.decode(decoder);
+    return error;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeMapSize(1);
+    encoder.writeUInt32(this.variant);
+    if (this.variant == 0) this.notFound!.encode(encoder);
+  }
+}
+
+/** Result of method `delete` of service Store: a value, or a declared error */
+export class StoreDeleteResult {
+  error: StoreDeleteError | null = null;
+
+  static ok(): StoreDeleteResult {
+    const result = new StoreDeleteResult();
+    return result;
+  }
+
+  static err(error: StoreDeleteError): StoreDeleteResult {
+    const result = new StoreDeleteResult();
+    result.error = error;
+    return result;
+  }
+
+  static decode(decoder: Decoder): StoreDeleteResult {
+    const result = new StoreDeleteResult();
+    decoder.readMapSize();
+    if (decoder.readUInt32() == 0) {
+      decoder.skip();
+    } else {
+      result.error = StoreDeleteError.decode(decoder);
+    }
+    return result;
+  }
+
+  encode(encoder: Writer): void {
+    encoder.writeMapSize(1);
+    const error = this.error;
+    if (error) {
+      encoder.writeUInt32(1);
+      error.encode(encoder);
+    } else {
+      encoder.writeUInt32(0);
+      encoder.writeNil();
+    }
+  }
+
+  toBuffer(): ArrayBuffer {
+    return serialize<StoreDeleteResult>(this, (encoder: Writer, value: StoreDeleteResult): void => {
+      value.encode(encoder);
+    });
+  }
+}
+
+var storeGetHandler: ((arg: string) => StoreGetResult) | null = null;
+
+function storeGetWrapper(payload: ArrayBuffer): Result<ArrayBuffer> {
+  const decoder = new Decoder(payload);
+  const arg = decoder.readString();
+  const resp = storeGetHandler!(arg);
+  return Result.ok<ArrayBuffer>(resp.toBuffer());
+}
+
+var storeListHandler: (() => Page) | null = null;
+
+function storeListWrapper(payload: ArrayBuffer): Result<ArrayBuffer> {
+  const resp = storeListHandler!();
+  return Result.ok<ArrayBuffer>(serialize<Page>(resp, (encoder: Writer, value: Page): void => {
+    value.encode(encoder);
+  }));
+}
+
+var storePutHandler: ((arg: Item) => void) | null = null;
+
+function storePutWrapper(payload: ArrayBuffer): Result<ArrayBuffer> {
+  const decoder = new Decoder(payload);
+  const arg = Item.decode(decoder);
+  storePutHandler!(arg);
+  return Result.ok<ArrayBuffer>(serializeNil());
+}
+
+var storeDeleteHandler: ((arg: string) => StoreDeleteResult) | null = null;
+
+function storeDeleteWrapper(payload: ArrayBuffer): Result<ArrayBuffer> {
+  const decoder = new Decoder(payload);
+  const arg = decoder.readString();
+  const resp = storeDeleteHandler!(arg);
+  return Result.ok<ArrayBuffer>(resp.toBuffer());
+}
+
+var storeChecksumHandler: ((arg: ArrayBuffer) => u32) | null = null;
+
+function storeChecksumWrapper(payload: ArrayBuffer): Result<ArrayBuffer> {
+  const decoder = new Decoder(payload);
+  const arg = readBytesArray(decoder);
+  const resp = storeChecksumHandler!(arg);
+  return Result.ok<ArrayBuffer>(serialize<u32>(resp, (encoder: Writer, value: u32): void => {
+    encoder.writeUInt32(value);
+  }));
+}
+
+var storeTouchHandler: ((arg: string) => void) | null = null;
+
+function storeTouchWrapper(payload: ArrayBuffer): Result<ArrayBuffer> {
+  const decoder = new Decoder(payload);
+  const arg = decoder.readString();
+  // oneway: the handler's result is not sent back
+  storeTouchHandler!(arg);
+  return Result.ok<ArrayBuffer>(new ArrayBuffer(0));
+}
+
+var storeConvertHandler: ((arg: Types) => Array<Types>) | null = null;
+
+function storeConvertWrapper(payload: ArrayBuffer): Result<ArrayBuffer> {
+  const decoder = new Decoder(payload);
+  const arg = Types.decode(decoder);
+  const resp = storeConvertHandler!(arg);
+  return Result.ok<ArrayBuffer>(serialize<Array<Types>>(resp, (encoder: Writer, value: Array<Types>): void => {
+    encoder.writeArray<Types>(value, (encoder: Writer, item: Types): void => { item.encode(encoder); });
+  }));
+}
+
+/**
+ * Key-value store
+ */
+export class StoreHandlers {
+  /** Registers the handler for `get` */
+  static registerGet(handler: (arg: string) => StoreGetResult): void {
+    storeGetHandler = handler;
+    // rust clients send the method name alone; accept it with the service prefix too
+    register("Get", storeGetWrapper);
+    register("Store.Get", storeGetWrapper);
+  }
+  /** Registers the handler for `list` */
+  static registerList(handler: () => Page): void {
+    storeListHandler = handler;
+    // rust clients send the method name alone; accept it with the service prefix too
+    register("List", storeListWrapper);
+    register("Store.List", storeListWrapper);
+  }
+  /** Registers the handler for `put` */
+  static registerPut(handler: (arg: Item) => void): void {
+    storePutHandler = handler;
+    // rust clients send the method name alone; accept it with the service prefix too
+    register("Put", storePutWrapper);
+    register("Store.Put", storePutWrapper);
+  }
+  /** Registers the handler for `delete` */
+  static registerDelete(handler: (arg: string) => StoreDeleteResult): void {
+    storeDeleteHandler = handler;
+    // rust clients send the method name alone; accept it with the service prefix too
+    register("Delete", storeDeleteWrapper);
+    register("Store.Delete", storeDeleteWrapper);
+  }
+  /** Registers the handler for `checksum` */
+  static registerChecksum(handler: (arg: ArrayBuffer) => u32): void {
+    storeChecksumHandler = handler;
+    // rust clients send the method name alone; accept it with the service prefix too
+    register("Checksum", storeChecksumWrapper);
+    register("Store.Checksum", storeChecksumWrapper);
+  }
+  /** Registers the handler for `touch` */
+  static registerTouch(handler: (arg: string) => void): void {
+    storeTouchHandler = handler;
+    // rust clients send the method name alone; accept it with the service prefix too
+    register("Touch", storeTouchWrapper);
+    register("Store.Touch", storeTouchWrapper);
+  }
+  /** Registers the handler for `convert` */
+  static registerConvert(handler: (arg: Types) => Array<Types>): void {
+    storeConvertHandler = handler;
+    // rust clients send the method name alone; accept it with the service prefix too
+    register("Convert", storeConvertWrapper);
+    register("Store.Convert", storeConvertWrapper);
+  }
+}
+
+/**
+ * Client for service Store.
+ * Errors from the host are returned with the message of the rust `ErrorEnvelope`,
+ * json with the error code and message.
+ */
+export class StoreClient {
+  /** Host/link name, usually "default" for the current host */
+  host: string;
+  /** Recipient of messages, such as an actor's public key or a capability contract */
+  target: string;
+
+  constructor(host: string, target: string) {
+    this.host = host;
+    this.target = target;
+  }
+
+  /** Constructs a client for sending to an actor */
+  static actor(target: string): StoreClient {
+    return new StoreClient("default", target);
+  }
+
+  /**
+   * Returns an item
+   */
+  get(arg: string): Result<StoreGetResult> {
+    const payload = serialize<string>(arg, (encoder: Writer, value: string): void => {
+      encoder.writeString(value);
+    });
+    const result = hostCall(this.host, this.target, "Get", payload);
+    if (result.isError()) {
+      return Result.error<StoreGetResult>(result.error()!);
+    }
+    const decoder = new Decoder(result.get());
+    return Result.ok<StoreGetResult>(StoreGetResult.decode(decoder));
+  }
+
+  list(): Result<Page> {
+    const payload = new ArrayBuffer(0);
+    const result = hostCall(this.host, this.target, "List", payload);
+    if (result.isError()) {
+      return Result.error<Page>(result.error()!);
+    }
+    const decoder = new Decoder(result.get());
+    return Result.ok<Page>(Page.decode(decoder));
+  }
+
+  put(arg: Item): Error | null {
+    const payload = serialize<Item>(arg, (encoder: Writer, value: Item): void => {
+      value.encode(encoder);
+    });
+    const result = hostCall(this.host, this.target, "Put", payload);
+    return result.isError() ? result.error() : null;
+  }
+
+  delete(arg: string): Result<StoreDeleteResult> {
+    const payload = serialize<string>(arg, (encoder: Writer, value: string): void => {
+      encoder.writeString(value);
+    });
+    const result = hostCall(this.host, this.target, "Delete", payload);
+    if (result.isError()) {
+      return Result.error<StoreDeleteResult>(result.error()!);
+    }
+    const decoder = new Decoder(result.get());
+    return Result.ok<StoreDeleteResult>(StoreDeleteResult.decode(decoder));
+  }
+
+  checksum(arg: ArrayBuffer): Result<u32> {
+    const payload = serialize<ArrayBuffer>(arg, (encoder: Writer, value: ArrayBuffer): void => {
+      writeBytesArray(encoder, value);
+    });
+    const result = hostCall(this.host, this.target, "Checksum", payload);
+    if (result.isError()) {
+      return Result.error<u32>(result.error()!);
+    }
+    const decoder = new Decoder(result.get());
+    return Result.ok<u32>(decoder.readUInt32());
+  }
+
+  touch(arg: string): Error | null {
+    const payload = serialize<string>(arg, (encoder: Writer, value: string): void => {
+      encoder.writeString(value);
+    });
+    const result = hostCall(this.host, this.target, "Touch", payload);
+    return result.isError() ? result.error() : null;
+  }
+
+  convert(arg: Types): Result<Array<Types>> {
+    const payload = serialize<Types>(arg, (encoder: Writer, value: Types): void => {
+      value.encode(encoder);
+    });
+    const result = hostCall(this.host, this.target, "Convert", payload);
+    if (result.isError()) {
+      return Result.error<Array<Types>>(result.error()!);
+    }
+    const decoder = new Decoder(result.get());
+    return Result.ok<Array<Types>>(decoder.readArray<Types>((decoder: Decoder): Types => { return Types.decode(decoder); }));
+  }
+}
@@ -0,0 +1,527 @@
+/* Generated by midl from package store. Do not edit.
+ *
+ * Messages are encoded with msgpack as arrays of fields, in declaration order,
+ * which is binary-compatible with the generated rust code.
+ * Strings, bytes, arrays and maps of a decoded message are allocated with malloc;
+ * release them with the message's _free function, also after a failed decode.
+ */
+#ifndef STORE_H
+#define STORE_H
+
+#include "frodobuf.h"
+
+/** Availability of an item */
+typedef enum StoreStatus {
+    STORE_STATUS_IN_STOCK = 0,
+    STORE_STATUS_BACKORDER = 1,
+    STORE_STATUS_DISCONTINUED = -1,
+} StoreStatus;
+
+typedef struct StoreItem StoreItem;
+typedef struct StorePage StorePage;
+typedef struct StoreTypes StoreTypes;
+typedef struct StoreNotFound StoreNotFound;
+typedef struct StoreDenied StoreDenied;
+
+/** An item in the store */
+struct StoreItem {
+    /** Unique key */
+    char *key;
+    uint32_t count;
+    int64_t updated;
+    double price;
+    bool active;
+    frodobuf_bytes data;
+    struct { char **items; uint32_t len; } tags;
+    struct { char **keys; uint64_t *values; uint32_t len; } sizes;
+    /** Shown to customers, if set */
+    char *note;
+};
+
+struct StorePage {
+    struct { StoreItem *items; uint32_t len; } items;
+    struct { frodobuf_bytes *items; uint32_t len; } blobs;
+};
+
+/** Every field type */
+struct StoreTypes {
+    int8_t tiny;
+    int32_t count;
+    int64_t total;
+    uint8_t flags;
+    uint32_t size;
+    uint64_t offset;
+    bool enabled;
+    char *label;
+    frodobuf_bytes raw;
+    float ratio;
+    double score;
+    char *created;
+    struct { char **keys; frodobuf_bytes *values; uint32_t len; } attrs;
+    struct { int32_t *items; uint32_t len; } ids;
+    StoreItem item;
+};
+
+struct StoreNotFound {
+    char *key;
+};
+
+struct StoreDenied {
+    char *reason;
+};
+
+static inline void store_item_encode(frodobuf_writer *w, const StoreItem *o);
+static inline int store_item_decode(frodobuf_reader *r, StoreItem *o);
+static inline void store_item_free(StoreItem *o);
+static inline void store_page_encode(frodobuf_writer *w, const StorePage *o);
+static inline int store_page_decode(frodobuf_reader *r, StorePage *o);
+static inline void store_page_free(StorePage *o);
+static inline void store_types_encode(frodobuf_writer *w, const StoreTypes *o);
+static inline int store_types_decode(frodobuf_reader *r, StoreTypes *o);
+static inline void store_types_free(StoreTypes *o);
+static inline void store_not_found_encode(frodobuf_writer *w, const StoreNotFound *o);
+static inline int store_not_found_decode(frodobuf_reader *r, StoreNotFound *o);
+static inline void store_not_found_free(StoreNotFound *o);
+static inline void store_denied_encode(frodobuf_writer *w, const StoreDenied *o);
+static inline int store_denied_decode(frodobuf_reader *r, StoreDenied *o);
+static inline void store_denied_free(StoreDenied *o);
+
+/* Methods of service Store, sent with frodobuf_host_call */
+#define STORE_STORE_GET "Get" /* (char *) -> StoreItem */
+#define STORE_STORE_LIST "List" /* () -> StorePage */
+#define STORE_STORE_PUT "Put" /* (StoreItem) */
+#define STORE_STORE_DELETE "Delete" /* (char *) */
+#define STORE_STORE_CHECKSUM "Checksum" /* (frodobuf_bytes) -> uint32_t */
+#define STORE_STORE_TOUCH "Touch" /* (char *) */
+#define STORE_STORE_CONVERT "Convert" /* (StoreTypes) -> struct { StoreTypes *items; uint32_t len; } */
+
+/** Writes StoreItem as an array of fields */
+static inline void store_item_encode(frodobuf_writer *w, const StoreItem *o) {
+    frodobuf_write_array_size(w, 9);
+    frodobuf_write_str(w, o->key);
+    frodobuf_write_uint(w, o->count);
+    frodobuf_write_int(w, o->updated);
+    frodobuf_write_f64(w, o->price);
+    frodobuf_write_bool(w, o->active);
+    frodobuf_write_bin(w, o->data);
+    frodobuf_write_array_size(w, o->tags.len);
+    for (uint32_t i0 = 0; i0 < o->tags.len; i0++) {
+        frodobuf_write_str(w, o->tags.items[i0]);
+    }
+    frodobuf_write_map_size(w, o->sizes.len);
+    for (uint32_t i0 = 0; i0 < o->sizes.len; i0++) {
+        frodobuf_write_str(w, o->sizes.keys[i0]);
+        frodobuf_write_uint(w, o->sizes.values[i0]);
+    }
+    frodobuf_write_str(w, o->note);
+}
+
+/** Reads StoreItem. Returns 0 on success, or -1 if there is an error */
+static inline int store_item_decode(frodobuf_reader *r, StoreItem *o) {
+    uint32_t size;
+    memset(o, 0, sizeof(*o));
+    if (frodobuf_read_array_size(r, &size)) {
+        return -1;
+    }
+    for (uint32_t i = 0; i < size; i++) {
+        switch (i) {
+        case 0:
+            if (frodobuf_read_str(r, &o->key)) {
+                return -1;
+            }
+            break;
+        case 1:
+            if (frodobuf_read_u32(r, &o->count)) {
+                return -1;
+            }
+            break;
+        case 2:
+            if (frodobuf_read_i64(r, &o->updated)) {
+                return -1;
+            }
+            break;
+        case 3:
+            if (frodobuf_read_f64(r, &o->price)) {
+                return -1;
+            }
+            break;
+        case 4:
+            if (frodobuf_read_bool(r, &o->active)) {
+                return -1;
+            }
+            break;
+        case 5:
+            if (frodobuf_read_bin(r, &o->data)) {
+                return -1;
+            }
+            break;
+        case 6:
+            if (frodobuf_read_array_size(r, &o->tags.len)) {
+                return -1;
+            }
+            o->tags.items = frodobuf_calloc(o->tags.len, sizeof(*o->tags.items));
+            if (o->tags.len && !o->tags.items) {
+                o->tags.len = 0;
+                return -1;
+            }
+            for (uint32_t i0 = 0; i0 < o->tags.len; i0++) {
+                if (frodobuf_read_str(r, &o->tags.items[i0])) {
+                    return -1;
+                }
+            }
+            break;
+        case 7:
+            if (frodobuf_read_map_size(r, &o->sizes.len)) {
+                return -1;
+            }
+            o->sizes.keys = frodobuf_calloc(o->sizes.len, sizeof(*o->sizes.keys));
+            o->sizes.values = frodobuf_calloc(o->sizes.len, sizeof(*o->sizes.values));
+            if (o->sizes.len && (!o->sizes.keys || !o->sizes.values)) {
+                o->sizes.len = 0;
+                return -1;
+            }
+            for (uint32_t i0 = 0; i0 < o->sizes.len; i0++) {
+                if (frodobuf_read_str(r, &o->sizes.keys[i0])) {
+                    return -1;
+                }
+                if (frodobuf_read_u64(r, &o->sizes.values[i0])) {
+                    return -1;
+                }
+            }
+            break;
+        case 8:
+            if (frodobuf_read_str(r, &o->note)) {
+                return -1;
+            }
+            break;
+        default:
+            /* skip fields added in later versions */
+            if (frodobuf_skip(r)) {
+                return -1;
+            }
+        }
+    }
+    return 0;
+}
+
+/** Releases the memory allocated when StoreItem was decoded */
+static inline void store_item_free(StoreItem *o) {
+    free(o->key);
+    free(o->data.data);
+    for (uint32_t i0 = 0; i0 < o->tags.len; i0++) {
+        free(o->tags.items[i0]);
+    }
+    free(o->tags.items);
+    for (uint32_t i0 = 0; i0 < o->sizes.len; i0++) {
+        free(o->sizes.keys[i0]);
+    }
+    free(o->sizes.keys);
+    free(o->sizes.values);
+    free(o->note);
+}
+
+/** Writes StorePage as an array of fields */
+static inline void store_page_encode(frodobuf_writer *w, const StorePage *o) {
+    frodobuf_write_array_size(w, 2);
+    frodobuf_write_array_size(w, o->items.len);
+    for (uint32_t i0 = 0; i0 < o->items.len; i0++) {
+        store_item_encode(w, &o->items.items[i0]);
+    }
+    frodobuf_write_array_size(w, o->blobs.len);
+    for (uint32_t i0 = 0; i0 < o->blobs.len; i0++) {
+        frodobuf_write_bytes_array(w, o->blobs.items[i0]);
+    }
+}
+
+/** Reads StorePage. Returns 0 on success, or -1 if there is an error */
+static inline int store_page_decode(frodobuf_reader *r, StorePage *o) {
+    uint32_t size;
+    memset(o, 0, sizeof(*o));
+    if (frodobuf_read_array_size(r, &size)) {
+        return -1;
+    }
+    for (uint32_t i = 0; i < size; i++) {
+        switch (i) {
+        case 0:
+            if (frodobuf_read_array_size(r, &o->items.len)) {
+                return -1;
+            }
+            o->items.items = frodobuf_calloc(o->items.len, sizeof(*o->items.items));
+            if (o->items.len && !o->items.items) {
+                o->items.len = 0;
+                return -1;
+            }
+            for (uint32_t i0 = 0; i0 < o->items.len; i0++) {
+                if (store_item_decode(r, &o->items.items[i0])) {
+                    return -1;
+                }
+            }
+            break;
+        case 1:
+            if (frodobuf_read_array_size(r, &o->blobs.len)) {
+                return -1;
+            }
+            o->blobs.items = frodobuf_calloc(o->blobs.len, sizeof(*o->blobs.items));
+            if (o->blobs.len && !o->blobs.items) {
+                o->blobs.len = 0;
+                return -1;
+            }
+            for (uint32_t i0 = 0; i0 < o->blobs.len; i0++) {
+                if (frodobuf_read_bytes_array(r, &o->blobs.items[i0])) {
+                    return -1;
+                }
+            }
+            break;
+        default:
+            /* skip fields added in later versions */
+            if (frodobuf_skip(r)) {
+                return -1;
+            }
+        }
+    }
+    return 0;
+}
+
+/** Releases the memory allocated when StorePage was decoded */
+static inline void store_page_free(StorePage *o) {
+    for (uint32_t i0 = 0; i0 < o->items.len; i0++) {
+        store_item_free(&o->items.items[i0]);
+    }
+    free(o->items.items);
+    for (uint32_t i0 = 0; i0 < o->blobs.len; i0++) {
+        free(o->blobs.items[i0].data);
+    }
+    free(o->blobs.items);
+}
+
+/** Writes StoreTypes as an array of fields */
+static inline void store_types_encode(frodobuf_writer *w, const StoreTypes *o) {
+    frodobuf_write_array_size(w, 15);
+    frodobuf_write_int(w, o->tiny);
+    frodobuf_write_int(w, o->count);
+    frodobuf_write_int(w, o->total);
+    frodobuf_write_uint(w, o->flags);
+    frodobuf_write_uint(w, o->size);
+    frodobuf_write_uint(w, o->offset);
+    frodobuf_write_bool(w, o->enabled);
+    frodobuf_write_str(w, o->label);
+    frodobuf_write_bin(w, o->raw);
+    frodobuf_write_f32(w, o->ratio);
+    frodobuf_write_f64(w, o->score);
+    frodobuf_write_str(w, o->created);
+    frodobuf_write_map_size(w, o->attrs.len);
+    for (uint32_t i0 = 0; i0 < o->attrs.len; i0++) {
+        frodobuf_write_str(w, o->attrs.keys[i0]);
+        frodobuf_write_bytes_array(w, o->attrs.values[i0]);
+    }
+    frodobuf_write_array_size(w, o->ids.len);
+    for (uint32_t i0 = 0; i0 < o->ids.len; i0++) {
+        frodobuf_write_int(w, o->ids.items[i0]);
+    }
+    store_item_encode(w, &o->item);
+}
+
+/** Reads StoreTypes. Returns 0 on success, or -1 if there is an error */
+static inline int store_types_decode(frodobuf_reader *r, StoreTypes *o) {
+    uint32_t size;
+    memset(o, 0, sizeof(*o));
+    if (frodobuf_read_array_size(r, &size)) {
+        return -1;
+    }
+    for (uint32_t i = 0; i < size; i++) {
+        switch (i) {
+        case 0:
+            if (frodobuf_read_i8(r, &o->tiny)) {
+                return -1;
+            }
+            break;
+        case 1:
+            if (frodobuf_read_i32(r, &o->count)) {
+                return -1;
+            }
+            break;
+        case 2:
+            if (frodobuf_read_i64(r, &o->total)) {
+                return -1;
+            }
+            break;
+        case 3:
+            if (frodobuf_read_u8(r, &o->flags)) {
+                return -1;
+            }
+            break;
+        case 4:
+            if (frodobuf_read_u32(r, &o->size)) {
+                return -1;
+            }
+            break;
+        case 5:
+            if (frodobuf_read_u64(r, &o->offset)) {
+                return -1;
+            }
+            break;
+        case 6:
+            if (frodobuf_read_bool(r, &o->enabled)) {
+                return -1;
+            }
+            break;
+        case 7:
+            if (frodobuf_read_str(r, &o->label)) {
+                return -1;
+            }
+            break;
+        case 8:
+            if (frodobuf_read_bin(r, &o->raw)) {
+                return -1;
+            }
+            break;
+        case 9:
+            if (frodobuf_read_f32(r, &o->ratio)) {
+                return -1;
+            }
+            break;
+        case 10:
+            if (frodobuf_read_f64(r, &o->score)) {
+                return -1;
+            }
+            break;
+        case 11:
+            if (frodobuf_read_str(r, &o->created)) {
+                return -1;
+            }
+            break;
+        case 12:
+            if (frodobuf_read_map_size(r, &o->attrs.len)) {
+                return -1;
+            }
+            o->attrs.keys = frodobuf_calloc(o->attrs.len, sizeof(*o->attrs.keys));
+            o->attrs.values = frodobuf_calloc(o->attrs.len, sizeof(*o->attrs.values));
+            if (o->attrs.len && (!o->attrs.keys || !o->attrs.values)) {
+                o->attrs.len = 0;
+                return -1;
+            }
+            for (uint32_t i0 = 0; i0 < o->attrs.len; i0++) {
+                if (frodobuf_read_str(r, &o->attrs.keys[i0])) {
+                    return -1;
+                }
+                if (frodobuf_read_bytes_array(r, &o->attrs.values[i0])) {
+                    return -1;
+                }
+            }
+            break;
+        case 13:
+            if (frodobuf_read_array_size(r, &o->ids.len)) {
+                return -1;
+            }
+            o->ids.items = frodobuf_calloc(o->ids.len, sizeof(*o->ids.items));
+            if (o->ids.len && !o->ids.items) {
+                o->ids.len = 0;
+                return -1;
+            }
+            for (uint32_t i0 = 0; i0 < o->ids.len; i0++) {
+                if (frodobuf_read_i32(r, &o->ids.items[i0])) {
+                    return -1;
+                }
+            }
+            break;
+        case 14:
+            if (store_item_decode(r, &o->item)) {
+                return -1;
+            }
+            break;
+        default:
+            /* skip fields added in later versions */
+            if (frodobuf_skip(r)) {
+                return -1;
+            }
+        }
+    }
+    return 0;
+}
+
+/** Releases the memory allocated when StoreTypes was decoded */
+static inline void store_types_free(StoreTypes *o) {
+    free(o->label);
+    free(o->raw.data);
+    free(o->created);
+    for (uint32_t i0 = 0; i0 < o->attrs.len; i0++) {
+        free(o->attrs.keys[i0]);
+        free(o->attrs.values[i0].data);
+    }
+    free(o->attrs.keys);
+    free(o->attrs.values);
+    free(o->ids.items);
+    store_item_free(&o->item);
+}
+
+/** Writes StoreNotFound as an array of fields */
+static inline void store_not_found_encode(frodobuf_writer *w, const StoreNotFound *o) {
+    frodobuf_write_array_size(w, 1);
+    frodobuf_write_str(w, o->key);
+}
+
+/** Reads StoreNotFound. Returns 0 on success, or -1 if there is an error */
+static inline int store_not_found_decode(frodobuf_reader *r, StoreNotFound *o) {
+    uint32_t size;
+    memset(o, 0, sizeof(*o));
+    if (frodobuf_read_array_size(r, &size)) {
+        return -1;
+    }
+    for (uint32_t i = 0; i < size; i++) {
+        switch (i) {
+        case 0:
+            if (frodobuf_read_str(r, &o->key)) {
+                return -1;
+            }
+            break;
+        default:
+            /* skip fields added in later versions */
+            if (frodobuf_skip(r)) {
+                return -1;
+            }
+        }
+    }
+    return 0;
+}
+
+/** Releases the memory allocated when StoreNotFound was decoded */
+static inline void store_not_found_free(StoreNotFound *o) {
+    free(o->key);
+}
+
+/** Writes StoreDenied as an array of fields */
+static inline void store_denied_encode(frodobuf_writer *w, const StoreDenied *o) {
+    frodobuf_write_array_size(w, 1);
+    frodobuf_write_str(w, o->reason);
+}
+
+/** Reads StoreDenied. Returns 0 on success, or -1 if there is an error */
+static inline int store_denied_decode(frodobuf_reader *r, StoreDenied *o) {
+    uint32_t size;
+    memset(o, 0, sizeof(*o));
+    if (frodobuf_read_array_size(r, &size)) {
+        return -1;
+    }
+    for (uint32_t i = 0; i < size; i++) {
+        switch (i) {
+        case 0:
+            if (frodobuf_read_str(r, &o->reason)) {
+                return -1;
+            }
+            break;
+        default:
+            /* skip fields added in later versions */
+            if (frodobuf_skip(r)) {
+                return -1;
+            }
+        }
+    }
+    return 0;
+}
+
+/** Releases the memory allocated when StoreDenied was decoded */
+static inline void store_denied_free(StoreDenied *o) {
+    free(o->reason);
+}
+
+#endif
@@ -0,0 +1,205 @@
+# Generated by midl from package store. Do not edit.
+#
+# Messages are dataclasses, encoded by `frodobuf.py` with msgpack as arrays of fields,
+# in declaration order, which is binary-compatible with the generated rust code.
+# Streaming methods are not supported by the clients.
+"""Messages and clients of package store"""
+
+from dataclasses import dataclass, field
+from enum import IntEnum
+from typing import Dict, List
+
+from .frodobuf import Client, Method, register
+
+
+class Status(IntEnum):
+    """Availability of an item"""
+    IN_STOCK = 0
+    BACKORDER = 1
+    DISCONTINUED = -1
+
+
+@dataclass
+class Item:
+    """An item in the store"""
+    #: Unique key
+    key: str = ""
+    count: int = 0
+    updated: int = 0
+    price: float = 0.0
+    active: bool = False
+    data: bytes = b""
+    tags: List[str] = field(default_factory=list)
+    sizes: Dict[str, int] = field(default_factory=dict)
+    #: Shown to customers, if set
+    note: str = ""
+
+
+@dataclass
+class Page:
+    items: List[Item] = field(default_factory=list)
+    blobs: List[bytes] = field(default_factory=list)
+
+
+@dataclass
+class Types:
+    """Every field type"""
+    tiny: int = 0
+    count: int = 0
+    total: int = 0
+    flags: int = 0
+    size: int = 0
+    offset: int = 0
+    enabled: bool = False
+    label: str = ""
+    raw: bytes = b""
+    ratio: float = 0.0
+    score: float = 0.0
+    created: str = ""
+    attrs: Dict[str, bytes] = field(default_factory=dict)
+    ids: List[int] = field(default_factory=list)
+    item: Item = field(default_factory=lambda: Item())
+
+
+@dataclass
+class NotFound:
+    key: str = ""
+
+
+@dataclass
+class Denied:
+    reason: str = ""
+
+
+register(
+    Item,
+    [
+        ("key", "str"),
+        ("count", "int"),
+        ("updated", "int"),
+        ("price", "float64"),
+        ("active", "bool"),
+        ("data", "bin"),
+        ("tags", ("array", "str")),
+        ("sizes", ("map", "str", "int")),
+        ("note", "str"),
+    ],
+)
+
+register(
+    Page,
+    [
+        ("items", ("array", ("message", Item))),
+        ("blobs", ("array", "bytes")),
+    ],
+)
+
+register(
+    Types,
+    [
+        ("tiny", "int"),
+        ("count", "int"),
+        ("total", "int"),
+        ("flags", "int"),
+        ("size", "int"),
+        ("offset", "int"),
+        ("enabled", "bool"),
+        ("label", "str"),
+        ("raw", "bin"),
+        ("ratio", "float32"),
+        ("score", "float64"),
+        ("created", "str"),
+        ("attrs", ("map", "str", "bytes")),
+        ("ids", ("array", "int")),
+        ("item", ("message", Item)),
+    ],
+)
+
+register(
+    NotFound,
+    [
+        ("key", "str"),
+    ],
+)
+
+register(
+    Denied,
+    [
+        ("reason", "str"),
+    ],
+)
+
+store_methods = {
+    "get": Method(
+        service="Store",
+        method="Get",
+        input="str",
+        output=("message", Item),
+        errors=[("NotFound", ("message", NotFound)), ("Denied", ("message", Denied))],
+    ),
+    "list": Method(
+        service="Store",
+        method="List",
+        output=("message", Page),
+    ),
+    "put": Method(
+        service="Store",
+        method="Put",
+        input=("message", Item),
+    ),
+    "delete": Method(
+        service="Store",
+        method="Delete",
+        input="str",
+        errors=[("NotFound", ("message", NotFound))],
+    ),
+    "checksum": Method(
+        service="Store",
+        method="Checksum",
+        input="bytes",
+        output="int",
+    ),
+    "touch": Method(
+        service="Store",
+        method="Touch",
+        input="str",
+        oneway=True,
+    ),
+    "convert": Method(
+        service="Store",
+        method="Convert",
+        input=("message", Types),
+        output=("array", ("message", Types)),
+    ),
+}
+"""Methods of service Store, sent by StoreClient"""
+
+
+class StoreClient(Client):
+    """Key-value store"""
+
+    def get(self, arg: str) -> Item:
+        """Returns an item
+
+        Raises DeclaredError with NotFound or Denied
+        """
+        return self._call(store_methods["get"], arg)
+
+    def list(self) -> Page:
+        return self._call(store_methods["list"])
+
+    def put(self, arg: Item) -> None:
+        self._call(store_methods["put"], arg)
+
+    def delete(self, arg: str) -> None:
+        """Raises DeclaredError with NotFound"""
+        self._call(store_methods["delete"], arg)
+
+    def checksum(self, arg: bytes) -> int:
+        return self._call(store_methods["checksum"], arg)
+
+    def touch(self, arg: str) -> None:
+        self._call(store_methods["touch"], arg)
+
+    def convert(self, arg: Types) -> List[Types]:
+        return self._call(store_methods["convert"], arg)
@@ -0,0 +1,948 @@
+// Code generated by midl from package store. DO NOT EDIT.
+//
+// Messages are encoded with msgpack as arrays of fields, in declaration order,
+// which is binary-compatible with the generated rust code.
+// Streaming methods and batches are not supported in TinyGo.
+package store
+
+import (
+	msgpack "github.com/wapc/tinygo-msgpack"
+	wapc "github.com/wapc/wapc-guest-tinygo"
+)
+
+// toBuffer serializes a value with the encode function
+func toBuffer(encode func(encoder msgpack.Writer)) ([]byte, error) {
+	sizer := msgpack.NewSizer()
+	encode(&sizer)
+	buffer := make([]byte, sizer.Len())
+	encoder := msgpack.NewEncoder(buffer)
+	encode(&encoder)
+	return buffer, encoder.Err()
+}
+
+// writeBytesArray writes bytes as an array of uint8, as rust serializes a `Vec<u8>` outside of messages
+func writeBytesArray(encoder msgpack.Writer, value []byte) {
+	encoder.WriteArraySize(uint32(len(value)))
+	for _, b := range value {
+		encoder.WriteUint8(b)
+	}
+}
+
+// readBytesArray reads bytes written with writeBytesArray
+func readBytesArray(decoder *msgpack.Decoder) ([]byte, error) {
+	size, err := decoder.ReadArraySize()
+	if err != nil {
+		return nil, err
+	}
+	value := make([]byte, size)
+	for i := uint32(0); i < size; i++ {
+		value[i], err = decoder.ReadUint8()
+		if err != nil {
+			return nil, err
+		}
+	}
+	return value, nil
+}
+
+// An item in the store
+type Item struct {
+	// Unique key
+	Key string
+	Count uint32
+	Updated int64
+	Price float64
+	Active bool
+	Data []byte
+	Tags []string
+	Sizes map[string]uint64
+	// Shown to customers, if set
+	Note string
+}
+
+// Decode reads the fields of Item, written as an array, from the decoder
+func (o *Item) Decode(decoder *msgpack.Decoder) error {
+	size, err := decoder.ReadArraySize()
+	if err != nil {
+		return err
+	}
+	for i := uint32(0); i < size; i++ {
+		switch i {
+		case 0:
+			o.Key, err = decoder.ReadString()
+			if err != nil {
+				return err
+			}
+		case 1:
+			o.Count, err = decoder.ReadUint32()
+			if err != nil {
+				return err
+			}
+		case 2:
+			o.Updated, err = decoder.ReadInt64()
+			if err != nil {
+				return err
+			}
+		case 3:
+			o.Price, err = decoder.ReadFloat64()
+			if err != nil {
+				return err
+			}
+		case 4:
+			o.Active, err = decoder.ReadBool()
+			if err != nil {
+				return err
+			}
+		case 5:
+			o.Data, err = decoder.ReadByteArray()
+			if err != nil {
+				return err
+			}
+		case 6:
+			var size0 uint32
+			size0, err = decoder.ReadArraySize()
+			if err != nil {
+				return err
+			}
+			o.Tags = make([]string, size0)
+			for i0 := uint32(0); i0 < size0; i0++ {
+				o.Tags[i0], err = decoder.ReadString()
+				if err != nil {
+					return err
+				}
+			}
+		case 7:
+			var size0 uint32
+			size0, err = decoder.ReadMapSize()
+			if err != nil {
+				return err
+			}
+			o.Sizes = make(map[string]uint64, size0)
+			for i0 := uint32(0); i0 < size0; i0++ {
+				var k0 string
+				k0, err = decoder.ReadString()
+				if err != nil {
+					return err
+				}
+				var v0 uint64
+				v0, err = decoder.ReadUint64()
+				if err != nil {
+					return err
+				}
+				o.Sizes[k0] = v0
+			}
+		case 8:
+			o.Note, err = decoder.ReadString()
+			if err != nil {
+				return err
+			}
+		default:
+			// skip fields added in later versions
+			err = decoder.Skip()
+			if err != nil {
+				return err
+			}
+		}
+	}
+	return nil
+}
+
+// Encode writes the fields of Item to the encoder, as an array
+func (o *Item) Encode(encoder msgpack.Writer) {
+	encoder.WriteArraySize(9)
+	encoder.WriteString(o.Key)
+	encoder.WriteUint32(o.Count)
+	encoder.WriteInt64(o.Updated)
+	encoder.WriteFloat64(o.Price)
+	encoder.WriteBool(o.Active)
+	encoder.WriteByteArray(o.Data)
+	encoder.WriteArraySize(uint32(len(o.Tags)))
+	for _, v0 := range o.Tags {
+		encoder.WriteString(v0)
+	}
+	encoder.WriteMapSize(uint32(len(o.Sizes)))
+	for k0, v0 := range o.Sizes {
+		encoder.WriteString(k0)
+		encoder.WriteUint64(v0)
+	}
+	encoder.WriteString(o.Note)
+}
+
+// ToBuffer serializes Item
+func (o *Item) ToBuffer() ([]byte, error) {
+	return toBuffer(o.Encode)
+}
+
+type Page struct {
+	Items []Item
+	Blobs [][]byte
+}
+
+// Decode reads the fields of Page, written as an array, from the decoder
+func (o *Page) Decode(decoder *msgpack.Decoder) error {
+	size, err := decoder.ReadArraySize()
+	if err != nil {
+		return err
+	}
+	for i := uint32(0); i < size; i++ {
+		switch i {
+		case 0:
+			var size0 uint32
+			size0, err = decoder.ReadArraySize()
+			if err != nil {
+				return err
+			}
+			o.Items = make([]Item, size0)
+			for i0 := uint32(0); i0 < size0; i0++ {
+				err = o.Items[i0].Decode(decoder)
+				if err != nil {
+					return err
+				}
+			}
+		case 1:
+			var size0 uint32
+			size0, err = decoder.ReadArraySize()
+			if err != nil {
+				return err
+			}
+			o.Blobs = make([][]byte, size0)
+			for i0 := uint32(0); i0 < size0; i0++ {
+				o.Blobs[i0], err = readBytesArray(decoder)
+				if err != nil {
+					return err
+				}
+			}
+		default:
+			// skip fields added in later versions
+			err = decoder.Skip()
+			if err != nil {
+				return err
+			}
+		}
+	}
+	return nil
+}
+
+// Encode writes the fields of Page to the encoder, as an array
+func (o *Page) Encode(encoder msgpack.Writer) {
+	encoder.WriteArraySize(2)
+	encoder.WriteArraySize(uint32(len(o.Items)))
+	for _, v0 := range o.Items {
+		v0.Encode(encoder)
+	}
+	encoder.WriteArraySize(uint32(len(o.Blobs)))
+	for _, v0 := range o.Blobs {
+		writeBytesArray(encoder, v0)
+	}
+}
+
+// ToBuffer serializes Page
+func (o *Page) ToBuffer() ([]byte, error) {
+	return toBuffer(o.Encode)
+}
+
+// Every field type
+type Types struct {
+	Tiny int8
+	Count int32
+	Total int64
+	Flags uint8
+	Size uint32
+	Offset uint64
+	Enabled bool
+	Label string
+	Raw []byte
+	Ratio float32
+	Score float64
+	Created string
+	Attrs map[string][]byte
+	Ids []int32
+	Item Item
+}
+
+// Decode reads the fields of Types, written as an array, from the decoder
+func (o *Types) Decode(decoder *msgpack.Decoder) error {
+	size, err := decoder.ReadArraySize()
+	if err != nil {
+		return err
+	}
+	for i := uint32(0); i < size; i++ {
+		switch i {
+		case 0:
+			o.Tiny, err = decoder.ReadInt8()
+			if err != nil {
+				return err
+			}
+		case 1:
+			o.Count, err = decoder.ReadInt32()
+			if err != nil {
+				return err
+			}
+		case 2:
+			o.Total, err = decoder.ReadInt64()
+			if err != nil {
+				return err
+			}
+		case 3:
+			o.Flags, err = decoder.ReadUint8()
+			if err != nil {
+				return err
+			}
+		case 4:
+			o.Size, err = decoder.ReadUint32()
+			if err != nil {
+				return err
+			}
+		case 5:
+			o.Offset, err = decoder.ReadUint64()
+			if err != nil {
+				return err
+			}
+		case 6:
+			o.Enabled, err = decoder.ReadBool()
+			if err != nil {
+				return err
+			}
+		case 7:
+			o.Label, err = decoder.ReadString()
+			if err != nil {
+				return err
+			}
+		case 8:
+			o.Raw, err = decoder.ReadByteArray()
+			if err != nil {
+				return err
+			}
+		case 9:
+			o.Ratio, err = decoder.ReadFloat32()
+			if err != nil {
+				return err
+			}
+		case 10:
+			o.Score, err = decoder.ReadFloat64()
+			if err != nil {
+				return err
+			}
+		case 11:
+			o.Created, err = decoder.ReadString()
+			if err != nil {
+				return err
+			}
+		case 12:
+			var size0 uint32
+			size0, err = decoder.ReadMapSize()
+			if err != nil {
+				return err
+			}
+			o.Attrs = make(map[string][]byte, size0)
+			for i0 := uint32(0); i0 < size0; i0++ {
+				var k0 string
+				k0, err = decoder.ReadString()
+				if err != nil {
+					return err
+				}
+				var v0 []byte
+				v0, err = readBytesArray(decoder)
+				if err != nil {
+					return err
+				}
+				o.Attrs[k0] = v0
+			}
+		case 13:
+			var size0 uint32
+			size0, err = decoder.ReadArraySize()
+			if err != nil {
+				return err
+			}
+			o.Ids = make([]int32, size0)
+			for i0 := uint32(0); i0 < size0; i0++ {
+				o.Ids[i0], err = decoder.ReadInt32()
+				if err != nil {
+					return err
+				}
+			}
+		case 14:
+			err = o.Item.Decode(decoder)
+			if err != nil {
+				return err
+			}
+		default:
+			// skip fields added in later versions
+			err = decoder.Skip()
+			if err != nil {
+				return err
+			}
+		}
+	}
+	return nil
+}
+
+// Encode writes the fields of Types to the encoder, as an array
+func (o *Types) Encode(encoder msgpack.Writer) {
+	encoder.WriteArraySize(15)
+	encoder.WriteInt8(o.Tiny)
+	encoder.WriteInt32(o.Count)
+	encoder.WriteInt64(o.Total)
+	encoder.WriteUint8(o.Flags)
+	encoder.WriteUint32(o.Size)
+	encoder.WriteUint64(o.Offset)
+	encoder.WriteBool(o.Enabled)
+	encoder.WriteString(o.Label)
+	encoder.WriteByteArray(o.Raw)
+	encoder.WriteFloat32(o.Ratio)
+	encoder.WriteFloat64(o.Score)
+	encoder.WriteString(o.Created)
+	encoder.WriteMapSize(uint32(len(o.Attrs)))
+	for k0, v0 := range o.Attrs {
+		encoder.WriteString(k0)
+		writeBytesArray(encoder, v0)
+	}
+	encoder.WriteArraySize(uint32(len(o.Ids)))
+	for _, v0 := range o.Ids {
+		encoder.WriteInt32(v0)
+	}
+	o.Item.Encode(encoder)
+}
+
+// ToBuffer serializes Types
+func (o *Types) ToBuffer() ([]byte, error) {
+	return toBuffer(o.Encode)
+}
+
+type NotFound struct {
+	Key string
+}
+
+// Decode reads the fields of NotFound, written as an array, from the decoder
+func (o *NotFound) Decode(decoder *msgpack.Decoder) error {
+	size, err := decoder.ReadArraySize()
+	if err != nil {
+		return err
+	}
+	for i := uint32(0); i < size; i++ {
+		switch i {
+		case 0:
+			o.Key, err = decoder.ReadString()
+			if err != nil {
+				return err
+			}
+		default:
+			// skip fields added in later versions
+			err = decoder.Skip()
+			if err != nil {
+				return err
+			}
+		}
+	}
+	return nil
+}
+
+// Encode writes the fields of NotFound to the encoder, as an array
+func (o *NotFound) Encode(encoder msgpack.Writer) {
+	encoder.WriteArraySize(1)
+	encoder.WriteString(o.Key)
+}
+
+// ToBuffer serializes NotFound
+func (o *NotFound) ToBuffer() ([]byte, error) {
+	return toBuffer(o.Encode)
+}
+
+type Denied struct {
+	Reason string
+}
+
+// Decode reads the fields of Denied, written as an array, from the decoder
+func (o *Denied) Decode(decoder *msgpack.Decoder) error {
+	size, err := decoder.ReadArraySize()
+	if err != nil {
+		return err
+	}
+	for i := uint32(0); i < size; i++ {
+		switch i {
+		case 0:
+			o.Reason, err = decoder.ReadString()
+			if err != nil {
+				return err
+			}
+		default:
+			// skip fields added in later versions
+			err = decoder.Skip()
+			if err != nil {
+				return err
+			}
+		}
+	}
+	return nil
+}
+
+// Encode writes the fields of Denied to the encoder, as an array
+func (o *Denied) Encode(encoder msgpack.Writer) {
+	encoder.WriteArraySize(1)
+	encoder.WriteString(o.Reason)
+}
+
+// ToBuffer serializes Denied
+func (o *Denied) ToBuffer() ([]byte, error) {
+	return toBuffer(o.Encode)
+}
+
+// StoreGetError holds one of the errors declared by method Get of service Store
+type StoreGetError struct {
+	// Position of the error in the `throws` list
+	Variant uint32
+	NotFound *NotFound
+	Denied *Denied
+}
+
+// StoreGetNotFound returns the declared error NotFound
+func StoreGetNotFound(value NotFound) *StoreGetError {
+	return &StoreGetError{Variant: 0, NotFound: &value}
+}
+
+// StoreGetDenied returns the declared error Denied
+func StoreGetDenied(value Denied) *StoreGetError {
+	return &StoreGetError{Variant: 1, Denied: &value}
+}
+
+// Error returns the name of the declared error
+func (e *StoreGetError) Error() string {
+	switch e.Variant {
+	case 0:
+		return "Store.Get: NotFound"
+	case 1:
+		return "Store.Get: Denied"
+	}
+	return "Store.Get: unknown error"
+}
+
+// Decode reads the error, written as a map from its variant to its value, from the decoder
+func (e *StoreGetError) Decode(decoder *msgpack.Decoder) error {
+	_, err := decoder.ReadMapSize()
+	if err != nil {
+		return err
+	}
+	e.Variant, err = decoder.ReadUint32()
+	if err != nil {
+		return err
+	}
+	switch e.Variant {
+	case 0:
+		e.NotFound = &NotFound{}
+		return e.NotFound.Decode(decoder)
+	case 1:
+		e.Denied = &Denied{}
+		return e.Denied.Decode(decoder)
+	}
+	return decoder.Skip()
+}
+
+// Encode writes the error to the encoder, as a map from its variant to its value
+func (e *StoreGetError) Encode(encoder msgpack.Writer) {
+	encoder.WriteMapSize(1)
+	encoder.WriteUint32(e.Variant)
+	switch e.Variant {
+	case 0:
+		e.NotFound.Encode(encoder)
+	case 1:
+		e.Denied.Encode(encoder)
+	default:
+		encoder.WriteNil()
+	}
+}
+
+// StoreDeleteError holds one of the errors declared by method Delete of service Store
+type StoreDeleteError struct {
+	// Position of the error in the `throws` list
+	Variant uint32
+	NotFound *NotFound
+}
+
+// StoreDeleteNotFound returns the declared error NotFound
+func StoreDeleteNotFound(value NotFound) *StoreDeleteError {
+	return &StoreDeleteError{Variant: 0, NotFound: &value}
+}
+
+// Error returns the name of the declared error
+func (e *StoreDeleteError) Error() string {
+	switch e.Variant {
+	case 0:
+		return "Store.Delete: NotFound"
+	}
+	return "Store.Delete: unknown error"
+}
+
+// Decode reads the error, written as a map from its variant to its value, from the decoder
+func (e *StoreDeleteError) Decode(decoder *msgpack.Decoder) error {
+	_, err := decoder.ReadMapSize()
+	if err != nil {
+		return err
+	}
+	e.Variant, err = decoder.ReadUint32()
+	if err != nil {
+		return err
+	}
+	switch e.Variant {
+	case 0:
+		e.NotFound = &NotFound{}
+		return e.NotFound.Decode(decoder)
+	}
+	return decoder.Skip()
+}
+
+// Encode writes the error to the encoder, as a map from its variant to its value
+func (e *StoreDeleteError) Encode(encoder msgpack.Writer) {
+	encoder.WriteMapSize(1)
+	encoder.WriteUint32(e.Variant)
+	switch e.Variant {
+	case 0:
+		e.NotFound.Encode(encoder)
+	default:
+		encoder.WriteNil()
+	}
+}
+
+// Key-value store
+type Store interface {
+	// Returns an item
+	Get(arg string) (Item, error)
+	List() (Page, error)
+	Put(arg Item) error
+	Delete(arg string) error
+	Checksum(arg []byte) (uint32, error)
+	Touch(arg string) error
+	Convert(arg Types) ([]Types, error)
+}
+
+var storeHandler Store
+
+func storeGetWrapper(payload []byte) ([]byte, error) {
+	d := msgpack.NewDecoder(payload)
+	decoder := &d
+	var err error
+	var arg string
+	arg, err = decoder.ReadString()
+	if err != nil {
+		return nil, err
+	}
+	value, err := storeHandler.Get(arg)
+	if declared, ok := err.(*StoreGetError); ok && declared != nil {
+		return toBuffer(func(encoder msgpack.Writer) {
+			encoder.WriteMapSize(1)
+			encoder.WriteUint32(1)
+			declared.Encode(encoder)
+		})
+	}
+	if err != nil {
+		return nil, err
+	}
+	return toBuffer(func(encoder msgpack.Writer) {
+		encoder.WriteMapSize(1)
+		encoder.WriteUint32(0)
+		value.Encode(encoder)
+	})
+}
+
+func storeListWrapper(payload []byte) ([]byte, error) {
+	value, err := storeHandler.List()
+	if err != nil {
+		return nil, err
+	}
+	return toBuffer(func(encoder msgpack.Writer) {
+		value.Encode(encoder)
+	})
+}
+
+func storePutWrapper(payload []byte) ([]byte, error) {
+	d := msgpack.NewDecoder(payload)
+	decoder := &d
+	var err error
+	var arg Item
+	err = arg.Decode(decoder)
+	if err != nil {
+		return nil, err
+	}
+	err = storeHandler.Put(arg)
+	if err != nil {
+		return nil, err
+	}
+	return []byte{0xc0}, nil
+}
+
+func storeDeleteWrapper(payload []byte) ([]byte, error) {
+	d := msgpack.NewDecoder(payload)
+	decoder := &d
+	var err error
+	var arg string
+	arg, err = decoder.ReadString()
+	if err != nil {
+		return nil, err
+	}
+	err = storeHandler.Delete(arg)
+	if declared, ok := err.(*StoreDeleteError); ok && declared != nil {
+		return toBuffer(func(encoder msgpack.Writer) {
+			encoder.WriteMapSize(1)
+			encoder.WriteUint32(1)
+			declared.Encode(encoder)
+		})
+	}
+	if err != nil {
+		return nil, err
+	}
+	return toBuffer(func(encoder msgpack.Writer) {
+		encoder.WriteMapSize(1)
+		encoder.WriteUint32(0)
+		encoder.WriteNil()
+	})
+}
+
+func storeChecksumWrapper(payload []byte) ([]byte, error) {
+	d := msgpack.NewDecoder(payload)
+	decoder := &d
+	var err error
+	var arg []byte
+	arg, err = readBytesArray(decoder)
+	if err != nil {
+		return nil, err
+	}
+	value, err := storeHandler.Checksum(arg)
+	if err != nil {
+		return nil, err
+	}
+	return toBuffer(func(encoder msgpack.Writer) {
+		encoder.WriteUint32(value)
+	})
+}
+
+func storeTouchWrapper(payload []byte) ([]byte, error) {
+	d := msgpack.NewDecoder(payload)
+	decoder := &d
+	var err error
+	var arg string
+	arg, err = decoder.ReadString()
+	if err != nil {
+		return nil, err
+	}
+	// oneway: the handler's result is not sent back
+	storeHandler.Touch(arg)
+	return []byte{}, nil
+}
+
+func storeConvertWrapper(payload []byte) ([]byte, error) {
+	d := msgpack.NewDecoder(payload)
+	decoder := &d
+	var err error
+	var arg Types
+	err = arg.Decode(decoder)
+	if err != nil {
+		return nil, err
+	}
+	value, err := storeHandler.Convert(arg)
+	if err != nil {
+		return nil, err
+	}
+	return toBuffer(func(encoder msgpack.Writer) {
+		encoder.WriteArraySize(uint32(len(value)))
+		for _, v0 := range value {
+			v0.Encode(encoder)
+		}
+	})
+}
+
+// RegisterStore registers the handler for the methods of service Store
+func RegisterStore(handler Store) {
+	storeHandler = handler
+	// rust clients send the method name alone; accept it with the service prefix too
+	wapc.RegisterFunctions(wapc.Functions{
+		"Get": storeGetWrapper,
+		"Store.Get": storeGetWrapper,
+		"List": storeListWrapper,
+		"Store.List": storeListWrapper,
+		"Put": storePutWrapper,
+		"Store.Put": storePutWrapper,
+		"Delete": storeDeleteWrapper,
+		"Store.Delete": storeDeleteWrapper,
+		"Checksum": storeChecksumWrapper,
+		"Store.Checksum": storeChecksumWrapper,
+		"Touch": storeTouchWrapper,
+		"Store.Touch": storeTouchWrapper,
+		"Convert": storeConvertWrapper,
+		"Store.Convert": storeConvertWrapper,
+	})
+}
+
+// StoreClient sends requests for service Store.
+// Errors from the host are returned with the message of the rust `ErrorEnvelope`,
+// json with the error code and message.
+type StoreClient struct {
+	// Host/link name, usually "default" for the current host
+	Host string
+	// Recipient of messages, such as an actor's public key or a capability contract
+	Target string
+}
+
+// NewStoreClient constructs a client for sending to an actor
+func NewStoreClient(target string) *StoreClient {
+	return &StoreClient{Host: "default", Target: target}
+}
+
+// Returns an item
+func (c *StoreClient) Get(arg string) (Item, error) {
+	var value Item
+	payload, err := toBuffer(func(encoder msgpack.Writer) {
+		encoder.WriteString(arg)
+	})
+	if err != nil {
+		return value, err
+	}
+	response, err := wapc.HostCall(c.Host, c.Target, "Get", payload)
+	if err != nil {
+		return value, err
+	}
+	d := msgpack.NewDecoder(response)
+	decoder := &d
+	_, err = decoder.ReadMapSize()
+	if err != nil {
+		return value, err
+	}
+	variant, err := decoder.ReadUint32()
+	if err != nil {
+		return value, err
+	}
+	if variant != 0 {
+		declared := &StoreGetError{}
+		err = declared.Decode(decoder)
+		if err != nil {
+			return value, err
+		}
+		return value, declared
+	}
+	err = value.Decode(decoder)
+	if err != nil {
+		return value, err
+	}
+	return value, nil
+}
+
+func (c *StoreClient) List() (Page, error) {
+	var value Page
+	payload := []byte{}
+	response, err := wapc.HostCall(c.Host, c.Target, "List", payload)
+	if err != nil {
+		return value, err
+	}
+	d := msgpack.NewDecoder(response)
+	decoder := &d
+	err = value.Decode(decoder)
+	if err != nil {
+		return value, err
+	}
+	return value, nil
+}
+
+func (c *StoreClient) Put(arg Item) error {
+	payload, err := toBuffer(func(encoder msgpack.Writer) {
+		arg.Encode(encoder)
+	})
+	if err != nil {
+		return err
+	}
+	if _, err := wapc.HostCall(c.Host, c.Target, "Put", payload); err != nil {
+		return err
+	}
+	return nil
+}
+
+func (c *StoreClient) Delete(arg string) error {
+	payload, err := toBuffer(func(encoder msgpack.Writer) {
+		encoder.WriteString(arg)
+	})
+	if err != nil {
+		return err
+	}
+	response, err := wapc.HostCall(c.Host, c.Target, "Delete", payload)
+	if err != nil {
+		return err
+	}
+	d := msgpack.NewDecoder(response)
+	decoder := &d
+	_, err = decoder.ReadMapSize()
+	if err != nil {
+		return err
+	}
+	variant, err := decoder.ReadUint32()
+	if err != nil {
+		return err
+	}
+	if variant != 0 {
+		declared := &StoreDeleteError{}
+		err = declared.Decode(decoder)
+		if err != nil {
+			return err
+		}
+		return declared
+	}
+	return nil
+}
+
+func (c *StoreClient) Checksum(arg []byte) (uint32, error) {
+	var value uint32
+	payload, err := toBuffer(func(encoder msgpack.Writer) {
+		writeBytesArray(encoder, arg)
+	})
+	if err != nil {
+		return value, err
+	}
+	response, err := wapc.HostCall(c.Host, c.Target, "Checksum", payload)
+	if err != nil {
+		return value, err
+	}
+	d := msgpack.NewDecoder(response)
+	decoder := &d
+	value, err = decoder.ReadUint32()
+	if err != nil {
+		return value, err
+	}
+	return value, nil
+}
+
+func (c *StoreClient) Touch(arg string) error {
+	payload, err := toBuffer(func(encoder msgpack.Writer) {
+		encoder.WriteString(arg)
+	})
+	if err != nil {
+		return err
+	}
+	if _, err := wapc.HostCall(c.Host, c.Target, "Touch", payload); err != nil {
+		return err
+	}
+	return nil
+}
+
+func (c *StoreClient) Convert(arg Types) ([]Types, error) {
+	var value []Types
+	payload, err := toBuffer(func(encoder msgpack.Writer) {
+		arg.Encode(encoder)
+	})
+	if err != nil {
+		return value, err
+	}
+	response, err := wapc.HostCall(c.Host, c.Target, "Convert", payload)
+	if err != nil {
+		return value, err
+	}
+	d := msgpack.NewDecoder(response)
+	decoder := &d
+	var size0 uint32
+	size0, err = decoder.ReadArraySize()
+	if err != nil {
+		return value, err
+	}
+	value = make([]Types, size0)
+	for i0 := uint32(0); i0 < size0; i0++ {
+		err = value[i0].Decode(decoder)
+		if err != nil {
+			return value, err
+		}
+	}
+	return value, nil
+}
@@ -0,0 +1,201 @@
+// Generated by midl from package store. Do not edit.
+//
+// Field names are the names declared in the idl, so messages are also the JSON
+// objects of the HTTP gateway. Streaming methods are not supported by the clients.
+import { Client, Method, Type, message } from "./frodobuf";
+
+/** Availability of an item */
+export enum Status {
+  IN_STOCK = 0,
+  BACKORDER = 1,
+  DISCONTINUED = -1,
+}
+
+/** An item in the store */
+export interface Item {
+  /** Unique key */
+  key: string;
+  count: number;
+  updated: number;
+  price: number;
+  active: boolean;
+  data: Uint8Array;
+  tags: string[];
+  sizes: Record<string, number>;
+  /** Shown to customers, if set */
+  note: string;
+}
+
+export interface Page {
+  items: Item[];
+  blobs: Uint8Array[];
+}
+
+/** Every field type */
+export interface Types {
+  tiny: number;
+  count: number;
+  total: number;
+  flags: number;
+  size: number;
+  offset: number;
+  enabled: boolean;
+  label: string;
+  raw: Uint8Array;
+  ratio: number;
+  score: number;
+  created: string;
+  attrs: Record<string, Uint8Array>;
+  ids: number[];
+  item: Item;
+}
+
+export interface NotFound {
+  key: string;
+}
+
+export interface Denied {
+  reason: string;
+}
+
+/** Wire types of the messages, for `encode`, `decode`, `toJson` and `fromJson` */
+export const types: { [message: string]: Type } = {
+  Item: message(() => [
+    ["key", "string"],
+    ["count", "int"],
+    ["updated", "int"],
+    ["price", "float64"],
+    ["active", "bool"],
+    ["data", "bin"],
+    ["tags", { array: "string" }],
+    ["sizes", { map: ["string", "int"] }],
+    ["note", "string"],
+  ]),
+  Page: message(() => [
+    ["items", { array: types.Item }],
+    ["blobs", { array: "bytes" }],
+  ]),
+  Types: message(() => [
+    ["tiny", "int"],
+    ["count", "int"],
+    ["total", "int"],
+    ["flags", "int"],
+    ["size", "int"],
+    ["offset", "int"],
+    ["enabled", "bool"],
+    ["label", "string"],
+    ["raw", "bin"],
+    ["ratio", "float32"],
+    ["score", "float64"],
+    ["created", "string"],
+    ["attrs", { map: ["string", "bytes"] }],
+    ["ids", { array: "int" }],
+    ["item", types.Item],
+  ]),
+  NotFound: message(() => [
+    ["key", "string"],
+  ]),
+  Denied: message(() => [
+    ["reason", "string"],
+  ]),
+};
+
+/** Errors declared by method `get` of service Store, thrown in a `DeclaredError` */
+export type StoreGetError =
+  | { NotFound: NotFound }
+  | { Denied: Denied };
+
+/** Errors declared by method `delete` of service Store, thrown in a `DeclaredError` */
+export type StoreDeleteError =
+  | { NotFound: NotFound };
+
+/** Methods of service Store, sent by StoreClient */
+export const storeMethods: { [method: string]: Method } = {
+  get: {
+    service: "Store",
+    method: "Get",
+    httpMethod: "POST",
+    path: "/store/Store/get",
+    input: "string",
+    output: types.Item,
+    errors: [["NotFound", types.NotFound], ["Denied", types.Denied]],
+  },
+  list: {
+    service: "Store",
+    method: "List",
+    httpMethod: "POST",
+    path: "/store/Store/list",
+    output: types.Page,
+  },
+  put: {
+    service: "Store",
+    method: "Put",
+    httpMethod: "POST",
+    path: "/store/Store/put",
+    input: types.Item,
+  },
+  delete: {
+    service: "Store",
+    method: "Delete",
+    httpMethod: "POST",
+    path: "/store/Store/delete",
+    input: "string",
+    errors: [["NotFound", types.NotFound]],
+  },
+  checksum: {
+    service: "Store",
+    method: "Checksum",
+    httpMethod: "POST",
+    path: "/store/Store/checksum",
+    input: "bytes",
+    output: "int",
+  },
+  touch: {
+    service: "Store",
+    method: "Touch",
+    httpMethod: "POST",
+    path: "/store/Store/touch",
+    input: "string",
+    oneway: true,
+  },
+  convert: {
+    service: "Store",
+    method: "Convert",
+    httpMethod: "POST",
+    path: "/store/Store/convert",
+    input: types.Types,
+    output: { array: types.Types },
+  },
+};
+
+/** Key-value store */
+export class StoreClient extends Client {
+  /** Returns an item */
+  async get(arg: string): Promise<Item> {
+    return (await this.call(storeMethods.get, arg)) as Item;
+  }
+
+  async list(): Promise<Page> {
+    return (await this.call(storeMethods.list)) as Page;
+  }
+
+  async put(arg: Item): Promise<void> {
+    await this.call(storeMethods.put, arg);
+  }
+
+  async delete(arg: string): Promise<void> {
+    await this.call(storeMethods.delete, arg);
+  }
+
+  async checksum(arg: Uint8Array): Promise<number> {
+    return (await this.call(storeMethods.checksum, arg)) as number;
+  }
+
+  async touch(arg: string): Promise<void> {
+    await this.call(storeMethods.touch, arg);
+  }
+
+  async convert(arg: Types): Promise<Types[]> {
+    return (await this.call(storeMethods.convert, arg)) as Types[];
+  }
+}
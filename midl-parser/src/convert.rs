@@ -0,0 +1,1516 @@
+//! Conversion of protobuf (.proto) files to MIDL
+//!
+//! The converter accepts the proto3 grammar, and the proto2 grammar except for groups
+//! and extensions, which it skips. Constructs that MIDL supports are mapped into a [Schema];
+//! the rest are dropped or transformed, and each of those is recorded in a [ConvertNote]
+//! with its location in the .proto file:
+//!
+//! - nested messages and enums are moved to the top level, prefixed with the names of
+//!   the enclosing messages (`Outer.Inner` becomes `OuterInner`)
+//! - the members of a `oneof` become optional fields
+//! - `sint`, `fixed` and `sfixed` integers become `int` or `uint` of the same size,
+//!   and map keys of type bool become strings
+//! - well-known types are converted: `google.protobuf.Timestamp` to `datetime`,
+//!   wrapper types such as `google.protobuf.StringValue` to optional fields,
+//!   and `google.protobuf.Empty` rpc parameters and results to methods without them
+//! - the `google.api.http` option of an rpc becomes an `@http` annotation
+//! - field options become `@option` annotations, and proto2 defaults `@default` annotations
+//! - imports, reserved names and numbers, extensions, groups, and custom options are dropped
+//!
+//! [write_midl] renders the schema as MIDL source.
+//!
+use crate::{
+    lexer::{Loc, Token},
+    parser::{Parser, ParserError, ParserErrorWithLocation, ParserResult},
+};
+use frodobuf_schema::model::{
+    Attribute, Constant, EnumValue, Enumeration, Field, FieldType, Ident, Message, Method, Schema,
+    Service, ATTRIBUTE_ID_OPTION, ATTRIBUTE_ID_SOURCE, ATTRIBUTE_UNNAMED,
+};
+use std::{collections::BTreeMap, fmt};
+
+/// Result of converting a .proto file
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    /// The converted schema
+    pub schema: Schema,
+    /// Constructs that were dropped or transformed, in the order they appear in the file
+    pub notes: Vec<ConvertNote>,
+}
+
+/// What happened to a construct that couldn't be converted as is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvertAction {
+    /// The construct has no equivalent in MIDL, and was omitted
+    Dropped,
+    /// The construct was converted to something different in MIDL
+    Transformed,
+}
+
+impl fmt::Display for ConvertAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertAction::Dropped => write!(f, "dropped"),
+            ConvertAction::Transformed => write!(f, "transformed"),
+        }
+    }
+}
+
+/// A construct that was dropped or transformed by the converter
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertNote {
+    /// line in the .proto file (1-based)
+    pub line: u32,
+    /// column in the .proto file (1-based)
+    pub col: u32,
+    /// whether the construct was dropped or transformed
+    pub action: ConvertAction,
+    /// description of the construct and the conversion
+    pub message: String,
+}
+
+impl fmt::Display for ConvertNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.col, self.action, self.message
+        )
+    }
+}
+
+/// Converts the text of a .proto file. The package is used if the file doesn't declare one.
+pub fn convert_proto(text: &str, package: &str) -> Result<Conversion, ParserErrorWithLocation> {
+    let mut converter = Converter {
+        parser: Parser::new(text),
+        notes: Vec::new(),
+    };
+    let file = match converter.next_file() {
+        Ok(file) => file,
+        Err(error) => {
+            let Loc { line, col } = converter.parser.tokenizer.loc();
+            return Err(ParserErrorWithLocation { error, line, col });
+        }
+    };
+    Ok(converter.into_conversion(file, package))
+}
+
+/// Value of an option: a constant, or an aggregate in protobuf text format
+#[derive(Debug, Clone)]
+enum OptionValue {
+    Constant(Constant),
+    Aggregate(Vec<(String, OptionValue)>),
+    /// list value, which isn't used by any conversion
+    List,
+}
+
+/// An option statement or field option
+#[derive(Debug, Clone)]
+struct ProtoOption {
+    loc: Loc,
+    /// option name. Custom options are in parentheses, as in `(google.api.http)`
+    name: String,
+    value: OptionValue,
+}
+
+impl ProtoOption {
+    fn is_custom(&self) -> bool {
+        self.name.starts_with('(')
+    }
+}
+
+/// Field type as declared, before resolving names
+#[derive(Debug, Clone)]
+enum ProtoType {
+    Scalar(FieldType),
+    Named(String),
+    Map(Box<(ProtoType, ProtoType)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Label {
+    None,
+    Optional,
+    Required,
+    Repeated,
+}
+
+#[derive(Debug, Clone)]
+struct ProtoField {
+    loc: Loc,
+    label: Label,
+    typ: ProtoType,
+    name: String,
+    number: u32,
+    options: Vec<ProtoOption>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProtoMessage {
+    name: String,
+    fields: Vec<ProtoField>,
+    messages: Vec<(Loc, ProtoMessage)>,
+    enums: Vec<(Loc, ProtoEnum)>,
+    options: Vec<ProtoOption>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProtoEnum {
+    name: String,
+    values: Vec<EnumValue>,
+    options: Vec<ProtoOption>,
+}
+
+#[derive(Debug, Clone)]
+struct ProtoRpc {
+    name: String,
+    input: (Loc, ProtoType),
+    output: (Loc, ProtoType),
+    client_streaming: bool,
+    server_streaming: bool,
+    options: Vec<ProtoOption>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProtoService {
+    name: String,
+    methods: Vec<ProtoRpc>,
+    options: Vec<ProtoOption>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProtoFile {
+    package: Option<String>,
+    options: Vec<ProtoOption>,
+    messages: Vec<(Loc, ProtoMessage)>,
+    enums: Vec<(Loc, ProtoEnum)>,
+    services: Vec<ProtoService>,
+}
+
+/// scalar types of protobuf, with the MIDL type, and whether the conversion changes the encoding
+const SCALARS: &[(&str, FieldType, bool)] = &[
+    ("double", FieldType::Float64, false),
+    ("float", FieldType::Float32, false),
+    ("int32", FieldType::Int32, false),
+    ("int64", FieldType::Int64, false),
+    ("uint32", FieldType::Uint32, false),
+    ("uint64", FieldType::Uint64, false),
+    ("sint32", FieldType::Int32, true),
+    ("sint64", FieldType::Int64, true),
+    ("fixed32", FieldType::Uint32, true),
+    ("fixed64", FieldType::Uint64, true),
+    ("sfixed32", FieldType::Int32, true),
+    ("sfixed64", FieldType::Int64, true),
+    ("bool", FieldType::Bool, false),
+    ("string", FieldType::String, false),
+    ("bytes", FieldType::Bytes, false),
+];
+
+struct Converter<'a> {
+    parser: Parser<'a>,
+    notes: Vec<ConvertNote>,
+}
+
+impl<'a> Converter<'a> {
+    fn note<S: Into<String>>(&mut self, loc: Loc, action: ConvertAction, message: S) {
+        self.notes.push(ConvertNote {
+            line: loc.line,
+            col: loc.col,
+            action,
+            message: message.into(),
+        });
+    }
+
+    fn loc(&mut self) -> Loc {
+        self.parser.tokenizer.lookahead_loc()
+    }
+
+    /// Skips tokens through the next ';'
+    fn skip_statement(&mut self) -> ParserResult<()> {
+        while !self.parser.tokenizer.next_symbol_if_eq(';')? {
+            self.parser.tokenizer.lookahead_some()?;
+            self.parser.tokenizer.advance()?;
+        }
+        Ok(())
+    }
+
+    /// Skips tokens through the '}' that matches the next '{'
+    fn skip_block(&mut self) -> ParserResult<()> {
+        while !self.parser.tokenizer.lookahead_is_symbol('{')? {
+            self.parser.tokenizer.advance()?;
+        }
+        let mut depth = 0;
+        loop {
+            match self.parser.tokenizer.lookahead_some()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => depth -= 1,
+                _ => {}
+            }
+            self.parser.tokenizer.advance()?;
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    // proto = [syntax] { import | package | option | topLevelDef | emptyStatement }
+    // topLevelDef = message | enum | extend | service
+    fn next_file(&mut self) -> ParserResult<ProtoFile> {
+        let mut file = ProtoFile::default();
+        while !self.parser.tokenizer.syntax_eof()? {
+            let loc = self.loc();
+            if self.parser.next_syntax_opt()?.is_some() {
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("edition")? {
+                self.skip_statement()?;
+                self.note(
+                    loc,
+                    ConvertAction::Dropped,
+                    "edition statement; the file is converted with proto3 rules",
+                );
+                continue;
+            }
+            if let Some(import) = self.parser.next_import_opt()? {
+                let message = if import.path.starts_with("google/protobuf/") {
+                    format!(
+                        "import \"{}\"; well-known types are converted to MIDL types",
+                        import.path
+                    )
+                } else {
+                    format!(
+                        "import \"{}\"; types it declares must be added to the MIDL file",
+                        import.path
+                    )
+                };
+                self.note(loc, ConvertAction::Dropped, message);
+                continue;
+            }
+            if let Some(package) = self.parser.next_package_opt()? {
+                if file.package.is_some() {
+                    return Err(ParserError::OnlyOnePackage);
+                }
+                file.package = Some(package.display());
+                continue;
+            }
+            if let Some(option) = self.next_option_opt()? {
+                file.options.push(option);
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("message")? {
+                file.messages.push((loc, self.next_message()?));
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("enum")? {
+                file.enums.push((loc, self.next_enum()?));
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("service")? {
+                file.services.push(self.next_service()?);
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("extend")? {
+                let extended = self.parser.next_full_ident()?;
+                self.skip_block()?;
+                self.note(
+                    loc,
+                    ConvertAction::Dropped,
+                    format!("extension of {}", extended),
+                );
+                continue;
+            }
+            if self.parser.tokenizer.next_symbol_if_eq(';')? {
+                continue;
+            }
+            return Err(ParserError::IncorrectInput);
+        }
+        Ok(file)
+    }
+
+    // optionName = ( ident | "(" fullIdent ")" ) { "." ident }
+    fn next_option_name(&mut self) -> ParserResult<String> {
+        let mut name = if self.parser.tokenizer.next_symbol_if_eq('(')? {
+            let leading_dot = self.parser.tokenizer.next_symbol_if_eq('.')?;
+            let ident = self.parser.next_full_ident()?;
+            self.parser.tokenizer.next_symbol_expect_eq(')')?;
+            format!("({}{})", if leading_dot { "." } else { "" }, ident)
+        } else {
+            self.parser.tokenizer.next_ident()?
+        };
+        while self.parser.tokenizer.next_symbol_if_eq('.')? {
+            name.push('.');
+            name.push_str(&self.parser.tokenizer.next_ident()?);
+        }
+        Ok(name)
+    }
+
+    // constant, or an aggregate value in protobuf text format: "{" { name [":"] value } "}"
+    fn next_option_value(&mut self) -> ParserResult<OptionValue> {
+        if self.parser.tokenizer.next_symbol_if_eq('{')? {
+            let mut fields = Vec::new();
+            while !self.parser.tokenizer.next_symbol_if_eq('}')? {
+                let name = if self.parser.tokenizer.lookahead_is_symbol('[')? {
+                    // extension or Any type url, as in [foo.bar]
+                    self.parser.tokenizer.advance()?;
+                    let name = self.parser.next_full_ident()?;
+                    while !self.parser.tokenizer.next_symbol_if_eq(']')? {
+                        self.parser.tokenizer.advance()?;
+                    }
+                    format!("[{}]", name)
+                } else {
+                    self.parser.tokenizer.next_ident()?
+                };
+                let _ = self.parser.tokenizer.next_symbol_if_eq(':')?;
+                fields.push((name, self.next_option_value()?));
+                let _ = self.parser.tokenizer.next_symbol_if_eq(',')?
+                    || self.parser.tokenizer.next_symbol_if_eq(';')?;
+            }
+            Ok(OptionValue::Aggregate(fields))
+        } else if self.parser.tokenizer.next_symbol_if_eq('[')? {
+            while !self.parser.tokenizer.next_symbol_if_eq(']')? {
+                self.next_option_value()?;
+                let _ = self.parser.tokenizer.next_symbol_if_eq(',')?;
+            }
+            Ok(OptionValue::List)
+        } else {
+            let constant = self.parser.next_constant()?;
+            // adjacent string literals are concatenated
+            if let Constant::String(mut s) = constant {
+                while let Some(next) = self.parser.tokenizer.next_token_if_map(|t| match t {
+                    Token::StrLit(lit) => Some(lit.to_string()),
+                    _ => None,
+                })? {
+                    s = format!(
+                        "{}{}",
+                        s.trim_end_matches('"'),
+                        next.trim_start_matches('"')
+                    );
+                }
+                return Ok(OptionValue::Constant(Constant::String(s)));
+            }
+            Ok(OptionValue::Constant(constant))
+        }
+    }
+
+    // option = "option" optionName "=" value ";"
+    fn next_option_opt(&mut self) -> ParserResult<Option<ProtoOption>> {
+        let loc = self.loc();
+        if self.parser.tokenizer.next_ident_if_eq("option")? {
+            let name = self.next_option_name()?;
+            self.parser.tokenizer.next_symbol_expect_eq('=')?;
+            let value = self.next_option_value()?;
+            self.parser.tokenizer.next_symbol_expect_eq(';')?;
+            Ok(Some(ProtoOption { loc, name, value }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // fieldOptions = "[" fieldOption { "," fieldOption } "]"
+    fn next_field_options(&mut self) -> ParserResult<Vec<ProtoOption>> {
+        let mut options = Vec::new();
+        if self.parser.tokenizer.next_symbol_if_eq('[')? {
+            loop {
+                let loc = self.loc();
+                let name = self.next_option_name()?;
+                self.parser.tokenizer.next_symbol_expect_eq('=')?;
+                let value = self.next_option_value()?;
+                options.push(ProtoOption { loc, name, value });
+                if !self.parser.tokenizer.next_symbol_if_eq(',')? {
+                    break;
+                }
+            }
+            self.parser.tokenizer.next_symbol_expect_eq(']')?;
+        }
+        Ok(options)
+    }
+
+    // type = scalar | messageType | enumType, where message and enum types may start with "."
+    fn next_type(&mut self) -> ParserResult<ProtoType> {
+        let loc = self.loc();
+        for (name, typ, changes_encoding) in SCALARS.iter() {
+            if self.parser.tokenizer.next_ident_if_eq(name)? {
+                if *changes_encoding {
+                    self.note(
+                        loc,
+                        ConvertAction::Transformed,
+                        format!("{} converted to {}", name, midl_type(typ)),
+                    );
+                }
+                return Ok(ProtoType::Scalar(typ.clone()));
+            }
+        }
+        let leading_dot = self.parser.tokenizer.next_symbol_if_eq('.')?;
+        let ident = self.parser.next_full_ident()?;
+        Ok(ProtoType::Named(format!(
+            "{}{}",
+            if leading_dot { "." } else { "" },
+            ident
+        )))
+    }
+
+    // field = [label] type fieldName "=" fieldNumber [ "[" fieldOptions "]" ] ";"
+    fn next_field_rest(
+        &mut self,
+        loc: Loc,
+        label: Label,
+        typ: ProtoType,
+    ) -> ParserResult<ProtoField> {
+        let name = self.parser.tokenizer.next_ident()?;
+        self.parser.tokenizer.next_symbol_expect_eq('=')?;
+        let number = self.parser.next_field_number()?;
+        let options = self.next_field_options()?;
+        self.parser.tokenizer.next_symbol_expect_eq(';')?;
+        Ok(ProtoField {
+            loc,
+            label,
+            typ,
+            name,
+            number,
+            options,
+        })
+    }
+
+    // mapField = "map" "<" keyType "," type ">" mapName "=" fieldNumber [ "[" fieldOptions "]" ] ";"
+    fn next_map_type(&mut self) -> ParserResult<ProtoType> {
+        self.parser.tokenizer.next_symbol_expect_eq('<')?;
+        let loc = self.loc();
+        let key = match self.next_type()? {
+            ProtoType::Scalar(FieldType::Bool) => {
+                self.note(
+                    loc,
+                    ConvertAction::Transformed,
+                    "map key type bool converted to string",
+                );
+                ProtoType::Scalar(FieldType::String)
+            }
+            ProtoType::Scalar(key) if key.is_integer() || key == FieldType::String => {
+                ProtoType::Scalar(key)
+            }
+            _ => return Err(ParserError::MapKeyType),
+        };
+        self.parser.tokenizer.next_symbol_expect_eq(',')?;
+        let value = self.next_type()?;
+        self.parser.tokenizer.next_symbol_expect_eq('>')?;
+        Ok(ProtoType::Map(Box::new((key, value))))
+    }
+
+    // message = "message" messageName messageBody
+    // messageBody = "{" { field | enum | message | extend | extensions | group |
+    //               option | oneof | mapField | reserved | emptyStatement } "}"
+    fn next_message(&mut self) -> ParserResult<ProtoMessage> {
+        let mut message = ProtoMessage {
+            name: self.parser.tokenizer.next_ident()?,
+            ..Default::default()
+        };
+        self.parser.tokenizer.next_symbol_expect_eq('{')?;
+        while !self.parser.tokenizer.next_symbol_if_eq('}')? {
+            let loc = self.loc();
+            if self.parser.tokenizer.next_symbol_if_eq(';')? {
+                continue;
+            }
+            if let Some(option) = self.next_option_opt()? {
+                message.options.push(option);
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("message")? {
+                message.messages.push((loc, self.next_message()?));
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("enum")? {
+                message.enums.push((loc, self.next_enum()?));
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("reserved")? {
+                self.skip_statement()?;
+                self.note(
+                    loc,
+                    ConvertAction::Dropped,
+                    format!(
+                        "reserved field names or numbers in message {}",
+                        &message.name
+                    ),
+                );
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("extensions")? {
+                self.skip_statement()?;
+                self.note(
+                    loc,
+                    ConvertAction::Dropped,
+                    format!("extension range in message {}", &message.name),
+                );
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("extend")? {
+                let extended = self.parser.next_full_ident()?;
+                self.skip_block()?;
+                self.note(
+                    loc,
+                    ConvertAction::Dropped,
+                    format!("extension of {}", extended),
+                );
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("oneof")? {
+                let name = self.parser.tokenizer.next_ident()?;
+                self.parser.tokenizer.next_symbol_expect_eq('{')?;
+                let mut members = Vec::new();
+                while !self.parser.tokenizer.next_symbol_if_eq('}')? {
+                    let field_loc = self.loc();
+                    if self.parser.tokenizer.next_symbol_if_eq(';')? {
+                        continue;
+                    }
+                    if let Some(option) = self.next_option_opt()? {
+                        self.note(
+                            option.loc,
+                            ConvertAction::Dropped,
+                            format!("option {} of oneof {}", &option.name, &name),
+                        );
+                        continue;
+                    }
+                    let typ = self.next_type()?;
+                    let field = self.next_field_rest(field_loc, Label::Optional, typ)?;
+                    members.push(field.name.clone());
+                    message.fields.push(field);
+                }
+                self.note(
+                    loc,
+                    ConvertAction::Transformed,
+                    format!(
+                        "oneof {} converted to optional fields {}; at most one should be set",
+                        name,
+                        members.join(", ")
+                    ),
+                );
+                continue;
+            }
+
+            let label = match self
+                .parser
+                .tokenizer
+                .next_ident_if_in(&["optional", "required", "repeated"])?
+                .as_deref()
+            {
+                Some("optional") => Label::Optional,
+                Some("required") => Label::Required,
+                Some("repeated") => Label::Repeated,
+                _ => Label::None,
+            };
+            if self.parser.tokenizer.next_ident_if_eq("group")? {
+                let name = self.parser.tokenizer.next_ident()?;
+                self.skip_block()?;
+                self.note(loc, ConvertAction::Dropped, format!("group {}", name));
+                continue;
+            }
+            let typ = if self.parser.tokenizer.next_ident_if_eq("map")? {
+                self.next_map_type()?
+            } else {
+                self.next_type()?
+            };
+            let field = self.next_field_rest(loc, label, typ)?;
+            message.fields.push(field);
+        }
+        Ok(message)
+    }
+
+    // enum = "enum" enumName "{" { option | enumField | reserved | emptyStatement } "}"
+    // enumField = ident "=" [ "-" ] intLit [ "[" enumValueOption { "," enumValueOption } "]" ] ";"
+    fn next_enum(&mut self) -> ParserResult<ProtoEnum> {
+        let mut e = ProtoEnum {
+            name: self.parser.tokenizer.next_ident()?,
+            ..Default::default()
+        };
+        self.parser.tokenizer.next_symbol_expect_eq('{')?;
+        while !self.parser.tokenizer.next_symbol_if_eq('}')? {
+            let loc = self.loc();
+            if self.parser.tokenizer.next_symbol_if_eq(';')? {
+                continue;
+            }
+            if let Some(option) = self.next_option_opt()? {
+                e.options.push(option);
+                continue;
+            }
+            if self.parser.tokenizer.next_ident_if_eq("reserved")? {
+                self.skip_statement()?;
+                self.note(
+                    loc,
+                    ConvertAction::Dropped,
+                    format!("reserved value names or numbers in enum {}", &e.name),
+                );
+                continue;
+            }
+            let name = self.parser.tokenizer.next_ident()?;
+            self.parser.tokenizer.next_symbol_expect_eq('=')?;
+            let number = self.parser.next_enum_value()?;
+            for option in self.next_field_options()? {
+                self.note(
+                    option.loc,
+                    ConvertAction::Dropped,
+                    format!(
+                        "option {} of enum value {}.{}",
+                        &option.name, &e.name, &name
+                    ),
+                );
+            }
+            self.parser.tokenizer.next_symbol_expect_eq(';')?;
+            e.values.push(EnumValue {
+                name,
+                number,
+                attributes: Vec::new(),
+            });
+        }
+        Ok(e)
+    }
+
+    // service = "service" serviceName "{" { option | rpc | emptyStatement } "}"
+    // rpc = "rpc" rpcName "(" [ "stream" ] messageType ")" "returns" "(" [ "stream" ]
+    //       messageType ")" (( "{" {option | emptyStatement } "}" ) | ";")
+    fn next_service(&mut self) -> ParserResult<ProtoService> {
+        let mut service = ProtoService {
+            name: self.parser.tokenizer.next_ident()?,
+            ..Default::default()
+        };
+        self.parser.tokenizer.next_symbol_expect_eq('{')?;
+        while !self.parser.tokenizer.next_symbol_if_eq('}')? {
+            if self.parser.tokenizer.next_symbol_if_eq(';')? {
+                continue;
+            }
+            if let Some(option) = self.next_option_opt()? {
+                service.options.push(option);
+                continue;
+            }
+            self.parser.tokenizer.next_ident_expect_eq("rpc")?;
+            let name = self.parser.tokenizer.next_ident()?;
+            self.parser.tokenizer.next_symbol_expect_eq('(')?;
+            let client_streaming = self.parser.tokenizer.next_ident_if_eq("stream")?;
+            let input = (self.loc(), self.next_type()?);
+            self.parser.tokenizer.next_symbol_expect_eq(')')?;
+            self.parser.tokenizer.next_ident_expect_eq("returns")?;
+            self.parser.tokenizer.next_symbol_expect_eq('(')?;
+            let server_streaming = self.parser.tokenizer.next_ident_if_eq("stream")?;
+            let output = (self.loc(), self.next_type()?);
+            self.parser.tokenizer.next_symbol_expect_eq(')')?;
+            let mut options = Vec::new();
+            if self.parser.tokenizer.next_symbol_if_eq('{')? {
+                while !self.parser.tokenizer.next_symbol_if_eq('}')? {
+                    if self.parser.tokenizer.next_symbol_if_eq(';')? {
+                        continue;
+                    }
+                    match self.next_option_opt()? {
+                        Some(option) => options.push(option),
+                        None => return Err(ParserError::IncorrectInput),
+                    }
+                }
+            } else {
+                self.parser.tokenizer.next_symbol_expect_eq(';')?;
+            }
+            service.methods.push(ProtoRpc {
+                name,
+                input,
+                output,
+                client_streaming,
+                server_streaming,
+                options,
+            });
+        }
+        Ok(service)
+    }
+
+    /// Resolves names, moves nested declarations to the top level, and builds the schema
+    fn into_conversion(mut self, file: ProtoFile, default_package: &str) -> Conversion {
+        let package = match &file.package {
+            Some(package) => package.clone(),
+            None => {
+                self.note(
+                    Loc::start(),
+                    ConvertAction::Transformed,
+                    format!("no package declaration; using package {}", default_package),
+                );
+                default_package.to_string()
+            }
+        };
+        let mut resolver = Resolver {
+            package: package.clone(),
+            names: BTreeMap::new(),
+            enums: Vec::new(),
+        };
+        resolver.add_names(&[], &file.messages, &file.enums);
+
+        let mut schema = Schema {
+            namespace: Ident::new(&package),
+            ..Default::default()
+        };
+        schema.attributes = self.options(&file.options, "file");
+        for (loc, e) in file.enums.iter() {
+            let e = self.enumeration(&resolver, &[], *loc, e);
+            schema.enums.push(e);
+        }
+        for (loc, m) in file.messages.iter() {
+            self.message(&resolver, &[], *loc, m, &mut schema);
+        }
+        for s in file.services.iter() {
+            let service = self.service(&resolver, s);
+            schema.services.push(service);
+        }
+        self.notes.sort_by_key(|n| (n.line, n.col));
+        Conversion {
+            schema,
+            notes: self.notes,
+        }
+    }
+
+    /// Converts options to option attributes, dropping custom options and aggregate values
+    fn options(&mut self, options: &[ProtoOption], target: &str) -> Vec<Attribute> {
+        let mut attributes = Vec::new();
+        for option in options.iter() {
+            match &option.value {
+                OptionValue::Constant(value) if !option.is_custom() => {
+                    attributes.push(Attribute {
+                        key: Ident::new(ATTRIBUTE_ID_OPTION),
+                        values: vec![(option.name.clone(), value.clone())],
+                    });
+                }
+                _ => self.note(
+                    option.loc,
+                    ConvertAction::Dropped,
+                    format!("option {} of {}", &option.name, target),
+                ),
+            }
+        }
+        attributes
+    }
+
+    fn enumeration(
+        &mut self,
+        resolver: &Resolver,
+        scope: &[&str],
+        loc: Loc,
+        e: &ProtoEnum,
+    ) -> Enumeration {
+        let name = resolver.flat_name(scope, &e.name);
+        if !scope.is_empty() {
+            self.note(
+                loc,
+                ConvertAction::Transformed,
+                format!(
+                    "nested enum {}.{} moved to the top level as {}",
+                    scope.join("."),
+                    &e.name,
+                    &name
+                ),
+            );
+        }
+        Enumeration {
+            name,
+            values: e.values.clone(),
+            attributes: self.options(&e.options, &format!("enum {}", &e.name)),
+        }
+    }
+
+    /// Converts the message, and adds it and its nested declarations to the schema
+    fn message(
+        &mut self,
+        resolver: &Resolver,
+        scope: &[&str],
+        loc: Loc,
+        m: &ProtoMessage,
+        schema: &mut Schema,
+    ) {
+        let name = resolver.flat_name(scope, &m.name);
+        if !scope.is_empty() {
+            self.note(
+                loc,
+                ConvertAction::Transformed,
+                format!(
+                    "nested message {}.{} moved to the top level as {}",
+                    scope.join("."),
+                    &m.name,
+                    &name
+                ),
+            );
+        }
+        let mut inner = scope.to_vec();
+        inner.push(&m.name);
+        let mut message = Message {
+            name: Ident::new(&name),
+            attributes: self.options(&m.options, &format!("message {}", &m.name)),
+            ..Default::default()
+        };
+        for f in m.fields.iter() {
+            if let Some(field) = self.field(resolver, &inner, f) {
+                message.fields.push(field);
+            }
+        }
+        schema.messages.push(message);
+        for (loc, e) in m.enums.iter() {
+            let e = self.enumeration(resolver, &inner, *loc, e);
+            schema.enums.push(e);
+        }
+        for (loc, nested) in m.messages.iter() {
+            self.message(resolver, &inner, *loc, nested, schema);
+        }
+    }
+
+    fn field(&mut self, resolver: &Resolver, scope: &[&str], f: &ProtoField) -> Option<Field> {
+        let (typ, wrapper) = match self.resolve(resolver, scope, f.loc, &f.typ) {
+            Resolved::Type(typ) => (typ, false),
+            Resolved::Wrapper(typ) => (typ, true),
+            Resolved::Empty => {
+                self.note(
+                    f.loc,
+                    ConvertAction::Dropped,
+                    format!("field {} of type google.protobuf.Empty", &f.name),
+                );
+                return None;
+            }
+        };
+        let (typ, optional) = match f.label {
+            Label::Repeated => (FieldType::Array(Box::new(typ)), false),
+            Label::Optional => (typ, true),
+            Label::None | Label::Required => (typ, wrapper),
+        };
+        let mut attributes = Vec::new();
+        for option in f.options.iter() {
+            match &option.value {
+                OptionValue::Constant(value) if option.name == "default" => {
+                    self.note(
+                        option.loc,
+                        ConvertAction::Transformed,
+                        format!("default of field {} converted to @default", &f.name),
+                    );
+                    attributes.push(Attribute::new_single_kv("default", "value", value.clone()));
+                }
+                OptionValue::Constant(value)
+                    if !option.is_custom() && !option.name.contains('.') =>
+                {
+                    self.note(
+                        option.loc,
+                        ConvertAction::Transformed,
+                        format!(
+                            "option {} of field {} converted to @option",
+                            &option.name, &f.name
+                        ),
+                    );
+                    attributes.push(Attribute::new_single_kv(
+                        ATTRIBUTE_ID_OPTION,
+                        option.name.clone(),
+                        value.clone(),
+                    ));
+                }
+                _ => self.note(
+                    option.loc,
+                    ConvertAction::Dropped,
+                    format!("option {} of field {}", &option.name, &f.name),
+                ),
+            }
+        }
+        Some(Field {
+            name: f.name.clone(),
+            optional,
+            typ,
+            number: f.number,
+            attributes,
+        })
+    }
+
+    fn service(&mut self, resolver: &Resolver, s: &ProtoService) -> Service {
+        let mut service = Service {
+            name: Ident::new(&s.name),
+            attributes: self.options(&s.options, &format!("service {}", &s.name)),
+            ..Default::default()
+        };
+        for rpc in s.methods.iter() {
+            let mut method = Method {
+                name: rpc.name.clone(),
+                input_type: self.rpc_type(resolver, &rpc.name, "parameter", &rpc.input),
+                output_type: self.rpc_type(resolver, &rpc.name, "result", &rpc.output),
+                client_streaming: rpc.client_streaming,
+                server_streaming: rpc.server_streaming,
+                errors: Vec::new(),
+                attributes: Vec::new(),
+            };
+            let mut options = Vec::new();
+            for option in rpc.options.iter() {
+                match (option.name.as_str(), &option.value) {
+                    ("(google.api.http)", OptionValue::Aggregate(rule)) => match http_rule(rule) {
+                        Some(http) => {
+                            self.note(
+                                option.loc,
+                                ConvertAction::Transformed,
+                                format!(
+                                    "option (google.api.http) of rpc {} converted to @http",
+                                    &rpc.name
+                                ),
+                            );
+                            method.attributes.push(http);
+                        }
+                        None => self.note(
+                            option.loc,
+                            ConvertAction::Dropped,
+                            format!(
+                                "option (google.api.http) of rpc {} without a path",
+                                &rpc.name
+                            ),
+                        ),
+                    },
+                    _ => options.push(option.clone()),
+                }
+            }
+            let options = self.options(&options, &format!("rpc {}", &rpc.name));
+            method.attributes.extend(options);
+            service.methods.push(method);
+        }
+        service
+    }
+
+    /// Type of an rpc parameter or result. Empty is converted to none.
+    fn rpc_type(
+        &mut self,
+        resolver: &Resolver,
+        method: &str,
+        what: &str,
+        (loc, typ): &(Loc, ProtoType),
+    ) -> Option<FieldType> {
+        match self.resolve(resolver, &[], *loc, typ) {
+            Resolved::Type(typ) | Resolved::Wrapper(typ) => Some(typ),
+            Resolved::Empty => {
+                self.note(
+                    *loc,
+                    ConvertAction::Transformed,
+                    format!(
+                        "google.protobuf.Empty {} of rpc {} converted to no {}",
+                        what, method, what
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    fn resolve(
+        &mut self,
+        resolver: &Resolver,
+        scope: &[&str],
+        loc: Loc,
+        typ: &ProtoType,
+    ) -> Resolved {
+        match typ {
+            ProtoType::Scalar(typ) => Resolved::Type(typ.clone()),
+            ProtoType::Map(kv) => {
+                let key = match self.resolve(resolver, scope, loc, &kv.0) {
+                    Resolved::Type(key) => key,
+                    _ => FieldType::String,
+                };
+                let value = match self.resolve(resolver, scope, loc, &kv.1) {
+                    Resolved::Type(value) | Resolved::Wrapper(value) => value,
+                    Resolved::Empty => FieldType::Bool,
+                };
+                Resolved::Type(FieldType::Map(Box::new((key, value))))
+            }
+            ProtoType::Named(name) => {
+                if let Some(resolved) = resolver.lookup(scope, name) {
+                    return Resolved::Type(FieldType::ObjectOrEnum(Ident::new(&resolved)));
+                }
+                let full_name = name.trim_start_matches('.');
+                if let Some((resolved, description)) = well_known_type(full_name) {
+                    // the callers describe what happens to Empty
+                    if !matches!(resolved, Resolved::Empty) {
+                        self.note(
+                            loc,
+                            ConvertAction::Transformed,
+                            format!("{} converted to {}", full_name, description),
+                        );
+                    }
+                    return resolved;
+                }
+                self.note(
+                    loc,
+                    ConvertAction::Transformed,
+                    format!(
+                        "type {} is not declared in the file; it must be added to the MIDL file",
+                        full_name
+                    ),
+                );
+                let name = full_name
+                    .strip_prefix(&format!("{}.", &resolver.package))
+                    .unwrap_or(full_name);
+                Resolved::Type(FieldType::ObjectOrEnum(Ident::new(name)))
+            }
+        }
+    }
+}
+
+/// Converted type
+enum Resolved {
+    Type(FieldType),
+    /// a wrapper type, converted to an optional value
+    Wrapper(FieldType),
+    /// google.protobuf.Empty
+    Empty,
+}
+
+/// Converts the well-known types of protobuf, returning the type and its description
+fn well_known_type(name: &str) -> Option<(Resolved, &'static str)> {
+    let name = name.strip_prefix("google.protobuf.")?;
+    let resolved = match name {
+        "Empty" => (Resolved::Empty, ""),
+        "Timestamp" => (Resolved::Type(FieldType::Datetime), "datetime"),
+        "Duration" => (
+            Resolved::Type(FieldType::String),
+            "string, in the JSON form of a Duration, such as \"1.5s\"",
+        ),
+        "Any" => (Resolved::Type(FieldType::Bytes), "bytes"),
+        "Struct" | "Value" | "ListValue" => (Resolved::Type(FieldType::String), "string of JSON"),
+        "FieldMask" => (
+            Resolved::Type(FieldType::Array(Box::new(FieldType::String))),
+            "[string] of field paths",
+        ),
+        "DoubleValue" => (Resolved::Wrapper(FieldType::Float64), "optional float64"),
+        "FloatValue" => (Resolved::Wrapper(FieldType::Float32), "optional float32"),
+        "Int64Value" => (Resolved::Wrapper(FieldType::Int64), "optional int64"),
+        "UInt64Value" => (Resolved::Wrapper(FieldType::Uint64), "optional uint64"),
+        "Int32Value" => (Resolved::Wrapper(FieldType::Int32), "optional int32"),
+        "UInt32Value" => (Resolved::Wrapper(FieldType::Uint32), "optional uint32"),
+        "BoolValue" => (Resolved::Wrapper(FieldType::Bool), "optional bool"),
+        "StringValue" => (Resolved::Wrapper(FieldType::String), "optional string"),
+        "BytesValue" => (Resolved::Wrapper(FieldType::Bytes), "optional bytes"),
+        _ => return None,
+    };
+    Some(resolved)
+}
+
+/// Converts a google.api.http rule to an `@http(method, path)` attribute
+fn http_rule(rule: &[(String, OptionValue)]) -> Option<Attribute> {
+    let string = |v: &OptionValue| match v {
+        OptionValue::Constant(Constant::String(s)) => Some(s.clone()),
+        _ => None,
+    };
+    let (method, path) = rule.iter().find_map(|(name, value)| match name.as_str() {
+        "get" | "put" | "post" | "delete" | "patch" => {
+            Some((format!("\"{}\"", name.to_uppercase()), string(value)?))
+        }
+        "custom" => match value {
+            OptionValue::Aggregate(custom) => {
+                let field = |n: &str| {
+                    custom
+                        .iter()
+                        .find(|(k, _)| k == n)
+                        .and_then(|(_, v)| string(v))
+                };
+                Some((field("kind")?, field("path")?))
+            }
+            _ => None,
+        },
+        _ => None,
+    })?;
+    Some(Attribute {
+        key: Ident::new("http"),
+        values: vec![
+            ("method".to_string(), Constant::String(method)),
+            ("path".to_string(), Constant::String(path)),
+        ],
+    })
+}
+
+/// Names of declared messages and enums
+struct Resolver {
+    package: String,
+    /// full name relative to the package (`Outer.Inner`) to top-level name (`OuterInner`)
+    names: BTreeMap<String, String>,
+    /// full names of enums
+    enums: Vec<String>,
+}
+
+impl Resolver {
+    fn add_names(
+        &mut self,
+        scope: &[&str],
+        messages: &[(Loc, ProtoMessage)],
+        enums: &[(Loc, ProtoEnum)],
+    ) {
+        for (_, e) in enums.iter() {
+            let full = full_name(scope, &e.name);
+            self.names.insert(full.clone(), self.unique(scope, &e.name));
+            self.enums.push(full);
+        }
+        for (_, m) in messages.iter() {
+            self.names
+                .insert(full_name(scope, &m.name), self.unique(scope, &m.name));
+            let mut inner = scope.to_vec();
+            inner.push(&m.name);
+            self.add_names(&inner, &m.messages, &m.enums);
+        }
+    }
+
+    /// top-level name for a declaration, which is the concatenated names of enclosing
+    /// messages and the declaration, with '_' between them if that's already used
+    fn unique(&self, scope: &[&str], name: &str) -> String {
+        let flat = format!("{}{}", scope.concat(), name);
+        if scope.is_empty() || !self.names.values().any(|n| n == &flat) {
+            flat
+        } else {
+            full_name(scope, name).replace('.', "_")
+        }
+    }
+
+    fn flat_name(&self, scope: &[&str], name: &str) -> String {
+        self.names
+            .get(&full_name(scope, name))
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Finds the declaration for a name used in the scope, following protobuf rules:
+    /// the innermost scope is searched first, and names starting with '.' are fully qualified
+    fn lookup(&self, scope: &[&str], name: &str) -> Option<String> {
+        let package_prefix = format!("{}.", &self.package);
+        if let Some(absolute) = name.strip_prefix('.') {
+            return absolute
+                .strip_prefix(&package_prefix)
+                .and_then(|rel| self.names.get(rel).cloned());
+        }
+        for n in (0..=scope.len()).rev() {
+            if let Some(found) = self.names.get(&full_name(&scope[..n], name)) {
+                return Some(found.clone());
+            }
+        }
+        name.strip_prefix(&package_prefix)
+            .and_then(|rel| self.names.get(rel).cloned())
+    }
+}
+
+fn full_name(scope: &[&str], name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope.join("."), name)
+    }
+}
+
+/// MIDL name of a data type
+fn midl_type(typ: &FieldType) -> String {
+    match typ {
+        FieldType::Int8 => "int8".to_string(),
+        FieldType::Int32 => "int32".to_string(),
+        FieldType::Int64 => "int64".to_string(),
+        FieldType::Uint8 => "uint8".to_string(),
+        FieldType::Uint32 => "uint32".to_string(),
+        FieldType::Uint64 => "uint64".to_string(),
+        FieldType::Bool => "bool".to_string(),
+        FieldType::String => "string".to_string(),
+        FieldType::Bytes => "bytes".to_string(),
+        FieldType::Float32 => "float32".to_string(),
+        FieldType::Float64 => "float64".to_string(),
+        FieldType::Datetime => "datetime".to_string(),
+        FieldType::Map(kv) => format!("map<{}, {}>", midl_type(&kv.0), midl_type(&kv.1)),
+        FieldType::Array(item) => format!("[{}]", midl_type(item)),
+        FieldType::ObjectOrEnum(ident) => ident.display(),
+    }
+}
+
+/// Renders a schema as MIDL source.
+/// Options of the file, messages and enums are written as `option` statements,
+/// and other attributes as annotations. Source locations are omitted.
+pub fn write_midl(schema: &Schema) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("package {};\n", schema.namespace.display()));
+    let (options, attributes) = split_options(&schema.attributes);
+    if !options.is_empty() {
+        out.push('\n');
+        for line in options {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    let _ = attributes; // file annotations, such as the parser version, are not written
+    for e in schema.enums.iter() {
+        out.push('\n');
+        write_enum(&mut out, e, 0);
+    }
+    for m in schema.messages.iter() {
+        out.push('\n');
+        write_message(&mut out, m, 0);
+    }
+    for s in schema.services.iter() {
+        out.push('\n');
+        write_annotations(&mut out, &s.attributes, 0);
+        out.push_str(&format!("service {} {{\n", &s.name.name));
+        for m in s.methods.iter() {
+            write_annotations(&mut out, &m.attributes, 1);
+            let param = |streaming: bool, typ: &Option<FieldType>| match typ {
+                Some(typ) if streaming => format!("stream {}", midl_type(typ)),
+                Some(typ) => midl_type(typ),
+                None => String::new(),
+            };
+            out.push_str(&format!(
+                "    rpc {}({})",
+                &m.name,
+                param(m.client_streaming, &m.input_type)
+            ));
+            if m.output_type.is_some() {
+                out.push_str(&format!(
+                    " returns ({})",
+                    param(m.server_streaming, &m.output_type)
+                ));
+            }
+            if !m.errors.is_empty() {
+                let errors = m.errors.iter().map(|e| e.display()).collect::<Vec<_>>();
+                out.push_str(&format!(" throws {}", errors.join(", ")));
+            }
+            out.push_str(";\n");
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+fn write_enum(out: &mut String, e: &Enumeration, depth: usize) {
+    let indent = "    ".repeat(depth);
+    let (options, attributes) = split_options(&e.attributes);
+    write_annotations(out, &attributes, depth);
+    out.push_str(&format!("{}enum {} {{\n", &indent, &e.name));
+    for line in options {
+        out.push_str(&format!("{}    {}\n", &indent, line));
+    }
+    for v in e.values.iter() {
+        out.push_str(&format!("{}    {} = {};\n", &indent, &v.name, v.number));
+    }
+    out.push_str(&format!("{}}}\n", &indent));
+}
+
+fn write_message(out: &mut String, m: &Message, depth: usize) {
+    let indent = "    ".repeat(depth);
+    let (options, attributes) = split_options(&m.attributes);
+    write_annotations(out, &attributes, depth);
+    out.push_str(&format!("{}message {} {{\n", &indent, &m.name.name));
+    for line in options {
+        out.push_str(&format!("{}    {}\n", &indent, line));
+    }
+    for e in m.enums.iter() {
+        write_enum(out, e, depth + 1);
+    }
+    for nested in m.messages.iter() {
+        write_message(out, nested, depth + 1);
+    }
+    for f in m.fields.iter() {
+        write_annotations(out, &f.attributes, depth + 1);
+        out.push_str(&format!(
+            "{}    {}{} {} = {};\n",
+            &indent,
+            if f.optional { "optional " } else { "" },
+            midl_type(&f.typ),
+            &f.name,
+            f.number
+        ));
+    }
+    out.push_str(&format!("{}}}\n", &indent));
+}
+
+/// Separates options, as `option` statements, from other attributes
+fn split_options(attributes: &[Attribute]) -> (Vec<String>, Vec<Attribute>) {
+    let (options, others): (Vec<&Attribute>, Vec<&Attribute>) = attributes
+        .iter()
+        .partition(|a| a.key.name == ATTRIBUTE_ID_OPTION);
+    let options = options
+        .iter()
+        .flat_map(|a| a.values.iter())
+        .map(|(name, value)| format!("option {} = {};", name, value))
+        .collect();
+    (options, others.into_iter().cloned().collect())
+}
+
+/// Writes annotations, one per line, omitting source locations
+fn write_annotations(out: &mut String, attributes: &[Attribute], depth: usize) {
+    let indent = "    ".repeat(depth);
+    for a in attributes.iter() {
+        if a.key.name == ATTRIBUTE_ID_SOURCE || a.key.name == "midl_parser_version" {
+            continue;
+        }
+        let values = a
+            .values
+            .iter()
+            .map(|(name, value)| {
+                if name == ATTRIBUTE_UNNAMED {
+                    value.to_string()
+                } else {
+                    format!("{} = {}", name, value)
+                }
+            })
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            out.push_str(&format!("{}@{}\n", &indent, a.key.display()));
+        } else {
+            out.push_str(&format!(
+                "{}@{}({})\n",
+                &indent,
+                a.key.display(),
+                values.join(", ")
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STORE_PROTO: &str = r#"syntax = "proto3";
+package store.v1;
+
+import "google/protobuf/timestamp.proto";
+import "google/protobuf/wrappers.proto";
+import "google/protobuf/empty.proto";
+
+option go_package = "example.com/store";
+
+message Order {
+  reserved 4, 8 to 10;
+  enum Status {
+    STATUS_UNSPECIFIED = 0;
+    STATUS_OPEN = 1 [deprecated = true];
+  }
+  message Line {
+    string sku = 1;
+    sint32 quantity = 2;
+  }
+  string id = 1 [json_name = "orderId"];
+  repeated Line lines = 2;
+  Status status = 3;
+  google.protobuf.Timestamp created = 5;
+  google.protobuf.StringValue note = 6;
+  map<bool, fixed64> flags = 7;
+  oneof payment {
+    string card = 11;
+    string voucher = 12;
+  }
+}
+
+service Store {
+  rpc GetOrder(Order) returns (Order) {
+    option (google.api.http) = { get: "/v1/orders/{id}" };
+  }
+  rpc Watch(google.protobuf.Empty) returns (stream Order.Line);
+}
+"#;
+
+    #[test]
+    fn convert_store() {
+        let conv = convert_proto(STORE_PROTO, "unused").unwrap();
+        let schema = &conv.schema;
+        assert_eq!(schema.namespace, "store.v1");
+        let names = schema
+            .messages
+            .iter()
+            .map(|m| m.name.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Order", "OrderLine"]);
+        assert_eq!(schema.enums[0].name, "OrderStatus");
+
+        let order = &schema.messages[0];
+        let field = |name: &str| order.get_field(name).unwrap();
+        assert_eq!(
+            field("lines").typ,
+            FieldType::Array(Box::new(FieldType::ObjectOrEnum(Ident::new("OrderLine"))))
+        );
+        assert_eq!(field("created").typ, FieldType::Datetime);
+        assert!(field("note").optional);
+        assert!(field("card").optional && field("voucher").optional);
+        assert_eq!(
+            field("flags").typ,
+            FieldType::Map(Box::new((FieldType::String, FieldType::Uint64)))
+        );
+
+        let methods = &schema.services[0].methods;
+        assert_eq!(methods[1].input_type, None);
+        assert!(methods[1].server_streaming);
+        let http = &methods[0].attributes[0];
+        assert_eq!(http.key.name, "http");
+        assert_eq!(
+            http.get("path"),
+            Some(&Constant::from("\"/v1/orders/{id}\""))
+        );
+
+        let notes = conv
+            .notes
+            .iter()
+            .map(|n| (n.line, n.action))
+            .collect::<Vec<_>>();
+        use ConvertAction::{Dropped, Transformed};
+        assert_eq!(
+            notes,
+            vec![
+                (4, Dropped),
+                (5, Dropped),
+                (6, Dropped),
+                (11, Dropped),
+                (12, Transformed),
+                (14, Dropped),
+                (16, Transformed),
+                (18, Transformed),
+                (20, Transformed),
+                (23, Transformed),
+                (24, Transformed),
+                (25, Transformed),
+                (25, Transformed),
+                (26, Transformed),
+                (34, Transformed),
+                (36, Transformed),
+            ]
+        );
+    }
+
+    #[test]
+    fn midl_round_trip() {
+        let conv = convert_proto(STORE_PROTO, "unused").unwrap();
+        let midl = write_midl(&conv.schema);
+        let parsed = crate::parse_string(&midl).unwrap().schema;
+        assert_eq!(parsed.messages.len(), 2);
+        assert_eq!(parsed.enums[0].values.len(), 2);
+        let order = &parsed.messages[0];
+        assert_eq!(order.fields.len(), conv.schema.messages[0].fields.len());
+        for (a, b) in order
+            .fields
+            .iter()
+            .zip(conv.schema.messages[0].fields.iter())
+        {
+            assert_eq!(
+                (&a.name, &a.typ, a.number, a.optional),
+                (&b.name, &b.typ, b.number, b.optional)
+            );
+        }
+        let methods = &parsed.services[0].methods;
+        assert_eq!(methods.len(), 2);
+        assert_eq!(methods[0].attributes[0].key.name, "http");
+        assert!(parsed
+            .attributes
+            .iter()
+            .any(|a| a.get("go_package") == Some(&Constant::from("\"example.com/store\""))));
+    }
+
+    #[test]
+    fn unsupported_constructs() {
+        let proto = r#"syntax = "proto2";
+message A {
+  extensions 100 to 199;
+  optional group G = 1 { optional int32 x = 2; }
+  optional int32 n = 3 [default = 7, (my.opt) = true];
+  optional Unknown u = 4;
+}
+extend A { optional string ext = 100; }
+"#;
+        let conv = convert_proto(proto, "pkg").unwrap();
+        assert_eq!(conv.schema.namespace, "pkg");
+        let fields = &conv.schema.messages[0].fields;
+        assert_eq!(fields.len(), 2);
+        assert_eq!(
+            fields[0].attributes[0].get("value"),
+            Some(&Constant::U64(7))
+        );
+        let lines = conv.notes.iter().map(|n| n.line).collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 3, 4, 5, 5, 6, 8]);
+
+        let err = convert_proto("message A {\n  int32 x = ;\n}\n", "pkg").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+}
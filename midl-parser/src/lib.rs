@@ -16,6 +16,7 @@ use amend_io_error::amend_io_error;
 use linked_hash_map::LinkedHashMap;
 
 mod amend_io_error;
+pub mod convert;
 #[doc(hidden)]
 pub mod linked_hash_map;
 mod model;
@@ -309,6 +310,7 @@ mod tests {
             bytes bb = 13;
             map<uint32,bytes> y = 14;
             [bool] ff = 15;
+            datetime dt = 16;
         }
         "#;
         let parsed = parse(proto);
@@ -340,6 +342,7 @@ mod tests {
         } else {
             assert!(false, "not an array");
         }
+        assert_eq!(message.fields[15].typ, FieldType::Datetime);
 
         Ok(())
     }
@@ -49,6 +49,9 @@ pub enum ParserError {
     InternalHash(String),
     OnlyOnePackage,
     SyntaxValue,
+    StreamThrows,
+    OnewayResult(String),
+    ProtobufOnly(String),
 }
 
 impl fmt::Display for ParserError {
@@ -86,6 +89,19 @@ impl fmt::Display for ParserError {
             ParserError::SyntaxValue => {
                 write!(f, "Expecting \"proto2\" or \"proto3\" in syntax statement")
             }
+            ParserError::StreamThrows => {
+                write!(f, "'throws' is not supported on streaming methods")
+            }
+            ParserError::OnewayResult(name) => write!(
+                f,
+                "@oneway method '{}' cannot return a value, stream, or declared errors",
+                name
+            ),
+            ParserError::ProtobufOnly(what) => write!(
+                f,
+                "'{}' is not supported in midl; use 'midl convert' to convert .proto files",
+                what
+            ),
         }
     }
 }
@@ -264,7 +280,7 @@ impl<'a> Parser<'a> {
 
     // fullIdent = ident { "." ident }*
     // Also accepts '::' as separator
-    fn next_full_ident(&mut self) -> ParserResult<Ident> {
+    pub(crate) fn next_full_ident(&mut self) -> ParserResult<Ident> {
         let mut full_ident = self.tokenizer.next_ident()?;
         let has_path_separator = |t: &Token| {
             if matches!(t, Token::DoubleColon | Token::Symbol(SYM_PERIOD)) {
@@ -350,7 +366,7 @@ impl<'a> Parser<'a> {
 
     // constant = fullIdent | ( [ "-" | "+" ] intLit ) | ( [ "-" | "+" ] floatLit ) |
     //            strLit | boolLit
-    fn next_constant(&mut self) -> ParserResult<Constant> {
+    pub(crate) fn next_constant(&mut self) -> ParserResult<Constant> {
         if let Some(lit) = self.next_lit_opt()? {
             return Ok(lit);
         }
@@ -372,7 +388,7 @@ impl<'a> Parser<'a> {
     // Import Statement
 
     // import = "import" [ "weak" | "public" ] strLit ";"
-    fn next_import_opt(&mut self) -> ParserResult<Option<Import>> {
+    pub(crate) fn next_import_opt(&mut self) -> ParserResult<Option<Import>> {
         if self.tokenizer.next_ident_if_eq("import")? {
             let vis = if self.tokenizer.next_ident_if_eq("weak")? {
                 ImportVis::Weak
@@ -392,7 +408,7 @@ impl<'a> Parser<'a> {
     // Package
 
     // package = "package" fullIdent ";"
-    fn next_package_opt(&mut self) -> ParserResult<Option<Ident>> {
+    pub(crate) fn next_package_opt(&mut self) -> ParserResult<Option<Ident>> {
         if self.tokenizer.next_ident_if_eq("package")? {
             let package = self.next_full_ident()?;
             self.tokenizer.next_symbol_expect_eq(SYM_SEMICOLON)?;
@@ -404,7 +420,7 @@ impl<'a> Parser<'a> {
 
     // syntax = "syntax" = ("proto1"|"proto3")  ";"
     // silently ignored
-    fn next_syntax_opt(&mut self) -> ParserResult<Option<ProtobufSyntax>> {
+    pub(crate) fn next_syntax_opt(&mut self) -> ParserResult<Option<ProtobufSyntax>> {
         if self.tokenizer.next_ident_if_eq("syntax")? {
             self.tokenizer.next_symbol_expect_eq(SYM_EQUALS)?;
             let syntax = match self
@@ -464,6 +480,8 @@ impl<'a> Parser<'a> {
                     self.tokenizer.next_symbol_expect_eq(SYM_EQUALS)?;
                     let value = self.next_constant()?;
                     values.push((opt_name, value));
+                    // optional comma
+                    let _ = self.tokenizer.next_symbol_if_eq(SYM_COMMA)?;
                 }
             }
             // optionally followed by ';'
@@ -491,6 +509,18 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // protobuf statements that midl doesn't support, for a better error message
+    // than a failure to parse them as fields
+    fn next_protobuf_only_opt(&mut self) -> ParserResult<Option<String>> {
+        Ok(self.tokenizer.next_ident_if_in(&[
+            "oneof",
+            "reserved",
+            "extensions",
+            "extend",
+            "group",
+        ])?)
+    }
+
     // Fields
 
     // label = "required" | "optional" | "repeated"
@@ -524,7 +554,8 @@ impl<'a> Parser<'a> {
             ("float", FieldType::Float32), // alias for float32
             ("float32", FieldType::Float32),
             ("float64", FieldType::Float64),
-            ("double", FieldType::Float64), // alias for float64
+            ("double", FieldType::Float64),    // alias for float64
+            ("datetime", FieldType::Datetime), // RFC-3339 string
         ];
 
         for &(ref n, ref t) in simple {
@@ -544,7 +575,7 @@ impl<'a> Parser<'a> {
         Ok(FieldType::ObjectOrEnum(self.next_full_ident()?))
     }
 
-    fn next_field_number(&mut self) -> ParserResult<u32> {
+    pub(crate) fn next_field_number(&mut self) -> ParserResult<u32> {
         // TODO: not all integers are valid field numbers
         self.tokenizer.next_token_check_map(|token| match *token {
             Token::IntLit(i) => Ok(i as u32),
@@ -625,7 +656,7 @@ impl<'a> Parser<'a> {
     // Enum definition
 
     // https://github.com/google/protobuf/issues/4561
-    fn next_enum_value(&mut self) -> ParserResult<i32> {
+    pub(crate) fn next_enum_value(&mut self) -> ParserResult<i32> {
         let minus = self.tokenizer.next_symbol_if_eq('-')?;
         let lit = self.next_int_lit()?;
         Ok(if minus {
@@ -728,6 +759,9 @@ impl<'a> Parser<'a> {
                 item_attributes.push(attr);
                 continue;
             }
+            if let Some(what) = self.next_protobuf_only_opt()? {
+                return Err(ParserError::ProtobufOnly(what));
+            }
             let mut field = self.next_field()?;
             field.attributes.append(&mut item_attributes);
             if field.number == 0 {
@@ -780,11 +814,13 @@ impl<'a> Parser<'a> {
 
             self.tokenizer.next_symbol_expect_eq(SYM_LPAREN)?;
 
+            let mut client_streaming = false;
             let input_type = if self.tokenizer.next_symbol_if_eq(SYM_RPAREN)? {
                 // empty args
                 None
             } else {
-                // non-empty args
+                // non-empty args, optionally preceded by "stream"
+                client_streaming = self.tokenizer.next_ident_if_eq("stream")?;
                 let arg = self.next_field_type()?;
                 self.tokenizer.next_symbol_expect_eq(SYM_RPAREN)?;
                 Some(arg)
@@ -792,6 +828,8 @@ impl<'a> Parser<'a> {
             // Return type
             // if "->" or "returns", get the return type as () or a data type
             // if omitted (method definition ends with ;), return type is also void (None)
+            // A data type preceded by "stream" is a streaming response
+            let mut server_streaming = false;
             let output_type = if self.tokenizer.next_token_if_map(has_fn_returns)?.is_some()
                 || self.tokenizer.next_ident_if_eq("returns")?
             {
@@ -799,17 +837,33 @@ impl<'a> Parser<'a> {
                     if self.tokenizer.next_symbol_if_eq(SYM_RPAREN)? {
                         None
                     } else {
+                        server_streaming = self.tokenizer.next_ident_if_eq("stream")?;
                         let ret_type = self.next_field_type()?;
                         self.tokenizer.next_symbol_expect_eq(SYM_RPAREN)?;
                         Some(ret_type)
                     }
                 } else {
+                    server_streaming = self.tokenizer.next_ident_if_eq("stream")?;
                     Some(self.next_field_type()?)
                 }
             } else {
                 None
             };
 
+            // optional list of error types: "throws" Type { "," Type }
+            let mut errors = Vec::new();
+            if self.tokenizer.next_ident_if_eq("throws")? {
+                if client_streaming || server_streaming {
+                    return Err(ParserError::StreamThrows);
+                }
+                loop {
+                    errors.push(self.next_full_ident()?);
+                    if !self.tokenizer.next_symbol_if_eq(SYM_COMMA)? {
+                        break;
+                    }
+                }
+            }
+
             // require semicolon to terminate method definition
             self.tokenizer.next_symbol_expect_eq(SYM_SEMICOLON)?;
 
@@ -817,6 +871,9 @@ impl<'a> Parser<'a> {
                 name,
                 input_type,
                 output_type,
+                client_streaming,
+                server_streaming,
+                errors,
                 attributes: Vec::new(),
             }))
         } else {
@@ -845,6 +902,14 @@ impl<'a> Parser<'a> {
             while self.tokenizer.lookahead_if_symbol()? != Some(SYM_RCURLY) {
                 if let Some(mut method) = self.next_rpc_opt()? {
                     method.attributes.append(&mut item_attributes);
+                    if method.attributes.iter().any(|a| a.key.name == "oneway")
+                        && (method.output_type.is_some()
+                            || method.client_streaming
+                            || method.server_streaming
+                            || !method.errors.is_empty())
+                    {
+                        return Err(ParserError::OnewayResult(method.name));
+                    }
                     methods.push(method);
                     continue;
                 }
@@ -955,6 +1020,10 @@ impl<'a> Parser<'a> {
                 continue;
             }
 
+            if let Some(what) = self.next_protobuf_only_opt()? {
+                return Err(ParserError::ProtobufOnly(what));
+            }
+
             return Err(ParserError::IncorrectInput);
         }
         if !inner_attributes.is_empty() {
@@ -1211,6 +1280,119 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_rpc_stream() {
+        let rpc = parse_opt("rpc watch(Filter) returns (stream Event);", |p| {
+            p.next_rpc_opt()
+        });
+        assert!(!rpc.client_streaming);
+        assert!(rpc.server_streaming);
+
+        let rpc = parse_opt("rpc upload(stream Chunk) -> Summary;", |p| p.next_rpc_opt());
+        assert!(rpc.client_streaming);
+        assert!(!rpc.server_streaming);
+
+        let rpc = parse_opt("rpc chat(stream Note) returns (stream Note);", |p| {
+            p.next_rpc_opt()
+        });
+        assert!(rpc.client_streaming && rpc.server_streaming);
+
+        let rpc = parse_opt("rpc get(Key) returns (Value);", |p| p.next_rpc_opt());
+        assert!(!rpc.client_streaming && !rpc.server_streaming);
+    }
+
+    #[test]
+    fn test_rpc_throws() {
+        let rpc = parse_opt(
+            "rpc get(Key) returns Value throws NotFound, auth.Denied;",
+            |p| p.next_rpc_opt(),
+        );
+        assert_eq!(
+            rpc.errors,
+            vec![Ident::new("NotFound"), Ident::new("auth.Denied")]
+        );
+
+        let rpc = parse_opt("rpc delete(Key) throws NotFound;", |p| p.next_rpc_opt());
+        assert!(rpc.output_type.is_none());
+        assert_eq!(rpc.errors.len(), 1);
+
+        let mut parser = Parser::new("rpc watch(Key) returns (stream Value) throws NotFound;");
+        assert!(parser.next_rpc_opt().is_err());
+    }
+
+    #[test]
+    fn test_attribute_values() {
+        let msg = r#"package t;
+        service Store {
+            @http(method="GET", path="/v1/count")
+            rpc count() returns uint32;
+        }"#;
+        let fd = FileDescriptor::parse(msg).expect("parse");
+        let attr = &fd.schema.services[0].methods[0].attributes[0];
+        assert_eq!(attr.values.len(), 2);
+        assert_eq!(attr.values[1].0, "path");
+    }
+
+    #[test]
+    fn test_oneway() {
+        let msg = r#"package t;
+        service Notify {
+            @oneway
+            rpc bindActor(string);
+        }"#;
+        assert!(FileDescriptor::parse(msg).is_ok());
+
+        let msg = r#"package t;
+        service Notify {
+            @oneway
+            rpc bindActor(string) returns bool;
+        }"#;
+        assert!(FileDescriptor::parse(msg).is_err());
+    }
+
+    #[test]
+    fn test_datetime() {
+        let msg = r#"
+        message Event
+    {
+        datetime created;
+        optional datetime updated;
+        [datetime] history;
+        map<string, datetime> marks;
+        Datetime other;
+    }"#;
+
+        let mess = parse_opt(msg, |p| p.next_message_opt());
+        assert_eq!(mess.fields[0].typ, FieldType::Datetime);
+        assert_eq!(mess.fields[1].typ, FieldType::Datetime);
+        assert_eq!(
+            mess.fields[2].typ,
+            FieldType::Array(Box::new(FieldType::Datetime))
+        );
+        assert_eq!(
+            mess.fields[3].typ,
+            FieldType::Map(Box::new((FieldType::String, FieldType::Datetime)))
+        );
+        // the keyword is lowercase; other spellings are message names
+        assert!(matches!(mess.fields[4].typ, FieldType::ObjectOrEnum(_)));
+    }
+
+    #[test]
+    fn test_protobuf_only() {
+        let msg = r#"
+            package p;
+            message A {
+                int32 a = 1;
+                oneof choice {
+                    string b = 2;
+                }
+            }
+        "#;
+        let err = parse_string(msg).unwrap_err();
+        assert!(matches!(err.error, ParserError::ProtobufOnly(ref s) if s == "oneof"));
+        assert_eq!(err.line, 5);
+    }
+
     #[test]
     fn test_incorrect_file_descriptor() {
         let msg = r#"message Foo {
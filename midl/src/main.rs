@@ -1,11 +1,19 @@
 use anyhow::{anyhow, Result};
 use clap::{self, Clap, ValueHint};
 use frodobuf::{
-    codegen::rust,
+    codegen::{
+        assemblyscript, c,
+        json_schema::{export_json_schema, export_openapi},
+        plugin::{run_plugin, PluginFormat, PluginRequest, PLUGIN_PROTOCOL_VERSION},
+        proto, python, rust, tinygo, typescript,
+    },
     render::{OutputLanguage, RenderConfig, Renderer},
 };
 use frodobuf_schema::model::Schema;
-use midl_parser::parse_string;
+use midl_parser::{
+    convert::{convert_proto, write_midl, ConvertAction},
+    parse_string,
+};
 use std::{fs, path::PathBuf};
 
 #[derive(Clap, Debug)]
@@ -34,6 +42,18 @@ pub enum Command {
     /// create new source project from idl file
     #[clap(name = "create")]
     Create(CreateOpt),
+
+    /// Generate code with a plugin
+    #[clap(name = "gen")]
+    Gen(GenOpt),
+
+    /// Export the schema in another idl format
+    #[clap(name = "export")]
+    Export(ExportOpt),
+
+    /// Convert a protobuf (.proto) file to idl
+    #[clap(name = "convert")]
+    Convert(ConvertOpt),
 }
 
 #[derive(Clap, Debug)]
@@ -73,6 +93,11 @@ pub struct CreateOpt {
     /// Default value is "X-interface", where X is the base name of the midl file.
     #[clap(long)]
     package: Option<String>,
+
+    /// Directory of templates that replace or add to the built-in templates.
+    /// `<name>.hbs` replaces the template `<name>`, and `output/<path>.hbs` generates the file `<path>`.
+    #[clap(long, parse(from_os_str), value_hint = ValueHint::DirPath)]
+    templates: Option<PathBuf>,
 }
 
 #[derive(Clap, Debug)]
@@ -88,6 +113,94 @@ pub struct UpdateOpt {
     /// Existing output directory where file will be generated. Defaults to current directory.
     #[clap(short, long, parse(from_os_str), value_hint = ValueHint::FilePath)]
     output_dir: Option<PathBuf>,
+
+    /// Directory of templates that replace or add to the built-in templates.
+    /// `<name>.hbs` replaces the template `<name>`, and `output/<path>.hbs` generates the file `<path>`.
+    #[clap(long, parse(from_os_str), value_hint = ValueHint::DirPath)]
+    templates: Option<PathBuf>,
+}
+
+#[derive(Clap, Debug)]
+pub struct GenOpt {
+    /// Input idl file
+    #[clap(short, long, parse(from_os_str), value_hint = ValueHint::FilePath)]
+    input: PathBuf,
+
+    /// Plugin program, such as `midl-gen-foo`, found in PATH unless it is a path
+    #[clap(long, parse(from_os_str), value_hint = ValueHint::CommandName)]
+    plugin: PathBuf,
+
+    /// Options for the plugin, as `--opt name=value`. May be repeated.
+    #[clap(long = "opt")]
+    options: Vec<String>,
+
+    /// Encoding of the plugin request and response: json or msgpack
+    #[clap(long, default_value = "json")]
+    format: PluginFormat,
+
+    /// Existing output directory where files will be generated. Defaults to current directory.
+    #[clap(short, long, parse(from_os_str), value_hint = ValueHint::FilePath)]
+    output_dir: Option<PathBuf>,
+
+    /// Arguments passed to the plugin program
+    #[clap(last = true)]
+    args: Vec<String>,
+}
+
+#[derive(Clap, Debug)]
+pub struct ExportOpt {
+    /// Input idl file
+    #[clap(short, long, parse(from_os_str), value_hint = ValueHint::FilePath)]
+    input: PathBuf,
+
+    /// Output format: proto, jsonschema, or openapi
+    #[clap(short, long)]
+    format: ExportFormat,
+
+    /// Output file. Defaults to stdout.
+    /// For jsonschema, an existing directory, where a file is written for each message.
+    #[clap(short, long, parse(from_os_str), value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Clap, Debug)]
+pub struct ConvertOpt {
+    /// Input .proto file
+    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
+    input: PathBuf,
+
+    /// Output idl file. Defaults to the input file with the extension `.midl`
+    #[clap(short, long, parse(from_os_str), value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+
+    /// Package name, if the .proto file doesn't declare one.
+    /// Default value is the base name of the input file.
+    #[clap(long)]
+    package: Option<String>,
+}
+
+/// Formats for `midl export`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// protobuf (proto3)
+    Proto,
+    /// JSON Schema, a document for each message
+    JsonSchema,
+    /// OpenAPI 3.1, with an operation for each method served by the HTTP gateway
+    OpenApi,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "proto" | "protobuf" => Ok(ExportFormat::Proto),
+            "jsonschema" | "json-schema" => Ok(ExportFormat::JsonSchema),
+            "openapi" => Ok(ExportFormat::OpenApi),
+            _ => Err(format!("Unsupported export format {}", s)),
+        }
+    }
 }
 
 fn current_dir() -> PathBuf {
@@ -105,6 +218,7 @@ fn main() {
 
     if let Err(e) = run(opt) {
         eprintln!("Error: {}", e.to_string());
+        std::process::exit(1);
     }
 }
 
@@ -113,6 +227,9 @@ fn run(opt: Opt) -> Result<()> {
         Command::Json(json_opt) => to_json(json_opt)?,
         Command::Update(gen_opt) => update(gen_opt)?,
         Command::Create(create_opt) => create(create_opt)?,
+        Command::Gen(gen_opt) => gen(gen_opt)?,
+        Command::Export(export_opt) => export(export_opt)?,
+        Command::Convert(convert_opt) => convert(convert_opt)?,
     }
     Ok(())
 }
@@ -131,7 +248,7 @@ fn to_json(opt: &JsonOpt) -> Result<()> {
     Ok(())
 }
 
-/// Create a project (only rust currently supported)
+/// Create a project
 fn create(opt: &CreateOpt) -> Result<()> {
     // first ensure we can read the schema
     let schema = parse_idl(&opt.input)?;
@@ -153,6 +270,7 @@ fn create(opt: &CreateOpt) -> Result<()> {
     for language in opt.language.iter() {
         let mut renderer = Renderer::init(&RenderConfig {
             language: *language,
+            template_dir: opt.templates.clone(),
             ..Default::default()
         })?;
         renderer.set("schema", schema_json.clone());
@@ -166,6 +284,61 @@ fn create(opt: &CreateOpt) -> Result<()> {
                         output: &output,
                         package: &package,
                         edition: &opt.edition,
+                        template_dir: opt.templates.as_deref(),
+                    },
+                )?;
+            }
+            OutputLanguage::AssemblyScript => {
+                assemblyscript::create_project(
+                    &mut renderer,
+                    &schema,
+                    assemblyscript::CreateProject {
+                        input: &opt.input,
+                        output: &output,
+                        package: &package,
+                    },
+                )?;
+            }
+            OutputLanguage::TinyGo => {
+                tinygo::create_project(
+                    &mut renderer,
+                    &schema,
+                    tinygo::CreateProject {
+                        input: &opt.input,
+                        output: &output,
+                        package: &package,
+                    },
+                )?;
+            }
+            OutputLanguage::C => {
+                c::create_project(
+                    &mut renderer,
+                    &schema,
+                    c::CreateProject {
+                        input: &opt.input,
+                        output: &output,
+                    },
+                )?;
+            }
+            OutputLanguage::TypeScript => {
+                typescript::create_project(
+                    &mut renderer,
+                    &schema,
+                    typescript::CreateProject {
+                        input: &opt.input,
+                        output: &output,
+                        package: &package,
+                    },
+                )?;
+            }
+            OutputLanguage::Python => {
+                python::create_project(
+                    &mut renderer,
+                    &schema,
+                    python::CreateProject {
+                        input: &opt.input,
+                        output: &output,
+                        package: &package,
                     },
                 )?;
             }
@@ -190,6 +363,7 @@ fn update(opt: &UpdateOpt) -> Result<()> {
 
     let mut renderer = Renderer::init(&RenderConfig {
         language: opt.language,
+        template_dir: opt.templates.clone(),
         ..Default::default()
     })?;
     renderer.set("schema", schema_json);
@@ -198,6 +372,104 @@ fn update(opt: &UpdateOpt) -> Result<()> {
     Ok(())
 }
 
+/// Generate code with a plugin
+fn gen(opt: &GenOpt) -> Result<()> {
+    let schema = parse_idl(&opt.input)?;
+    let output = match opt.output_dir.as_ref() {
+        Some(o) => o.clone(),
+        None => current_dir(),
+    };
+    if !output.is_dir() {
+        return Err(anyhow!(
+            "output-dir parameter must be an existing directory"
+        ));
+    }
+    let mut options = std::collections::BTreeMap::new();
+    for opt in opt.options.iter() {
+        let (name, value) = opt
+            .split_once('=')
+            .ok_or_else(|| anyhow!("plugin option '{}' must be name=value", opt))?;
+        options.insert(name.to_string(), value.to_string());
+    }
+    let request = PluginRequest {
+        version: PLUGIN_PROTOCOL_VERSION,
+        input: opt.input.display().to_string(),
+        options,
+        schema,
+    };
+    let response = run_plugin(&opt.plugin, &opt.args, &request, opt.format)?;
+    response.write_files(&output)?;
+    Ok(())
+}
+
+/// Export the schema in another format
+fn export(opt: &ExportOpt) -> Result<()> {
+    let schema = parse_idl(&opt.input)?;
+    let text = match opt.format {
+        ExportFormat::Proto => proto::export_proto(&schema),
+        ExportFormat::OpenApi => serde_json::to_string_pretty(&export_openapi(&schema))? + "\n",
+        ExportFormat::JsonSchema => {
+            let output = opt.output.as_ref().filter(|o| o.is_dir()).ok_or_else(|| {
+                anyhow!("jsonschema writes a file for each message: use -o to set an existing output directory")
+            })?;
+            for (name, doc) in export_json_schema(&schema).iter() {
+                let path = output.join(name);
+                fs::write(&path, serde_json::to_string_pretty(doc)? + "\n")
+                    .map_err(|e| anyhow!("writing output file '{}': {}", path.display(), e))?;
+            }
+            return Ok(());
+        }
+    };
+    match opt.output.as_ref() {
+        Some(output) => fs::write(output, text.as_bytes())
+            .map_err(|e| anyhow!("writing output file '{}': {}", output.display(), e))?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Convert a .proto file to idl, and report the constructs that were dropped or transformed
+fn convert(opt: &ConvertOpt) -> Result<()> {
+    let text = fs::read_to_string(&opt.input)
+        .map_err(|e| anyhow!("reading input file '{}': {}", &opt.input.display(), e))?;
+    let package = match opt.package.as_ref() {
+        Some(package) => package.clone(),
+        None => opt
+            .input
+            .file_stem()
+            .map(|s| s.to_string_lossy().replace('-', "_"))
+            .unwrap_or_else(|| "proto".to_string()),
+    };
+    let conversion =
+        convert_proto(&text, &package).map_err(|e| anyhow!("{}: {}", &opt.input.display(), e))?;
+    let output = match opt.output.as_ref() {
+        Some(output) => output.clone(),
+        None => opt.input.with_extension("midl"),
+    };
+    if output == opt.input {
+        return Err(anyhow!("output file must be different from the input file"));
+    }
+    fs::write(&output, write_midl(&conversion.schema).as_bytes())
+        .map_err(|e| anyhow!("writing output file '{}': {}", output.display(), e))?;
+    for note in conversion.notes.iter() {
+        eprintln!("{}:{}", &opt.input.display(), note);
+    }
+    if !conversion.notes.is_empty() {
+        let dropped = conversion
+            .notes
+            .iter()
+            .filter(|n| n.action == ConvertAction::Dropped)
+            .count();
+        eprintln!(
+            "{}: {} constructs dropped, {} transformed",
+            output.display(),
+            dropped,
+            conversion.notes.len() - dropped
+        );
+    }
+    Ok(())
+}
+
 /// Read idl file and convert to Schema
 fn parse_idl(input: &std::path::Path) -> Result<Schema> {
     let text = fs::read_to_string(input)